@@ -1,3 +1,6 @@
 SCRIPT_DIR=$( cd -- "$( dirname -- "${BASH_SOURCE[0]}" )" &> /dev/null && pwd )
 cd "$SCRIPT_DIR/.."
-cargo run -- examples/ --rust-out examples/rust-server/src --csharp-out examples/cs-server/src
+# The Rust example generates its code in build.rs.
+# Message bodies are protobuf binary since protogen-runtime 0.2, C# code
+# generated before that sends JSON. Rerun this whenever the Rust side changes.
+cargo run -- examples/ --csharp-out examples/cs-server/src
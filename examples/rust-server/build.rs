@@ -0,0 +1,6 @@
+fn main() -> Result<(), protogen::Error> {
+    protogen::Config::new()
+        .include("..")
+        .rust_out(std::env::var("OUT_DIR").unwrap())
+        .compile(&["hello.proto"])
+}
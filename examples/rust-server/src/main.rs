@@ -1,93 +1,47 @@
-use std::{
-    net::UdpSocket,
-    sync::mpsc::{sync_channel, Receiver, SyncSender},
-    thread::JoinHandle,
-};
+use protogen_runtime::transport::UdpTransport;
 
-use hello::GameFrontendMessage;
-
-pub mod hello;
-
-fn main() {
-    // TODO FrontendClient
-    // TODO BackendServer
-    todo!()
+pub mod hello {
+    include!(concat!(env!("OUT_DIR"), "/hello.rs"));
 }
 
-struct FrontendClient {
-    handle: JoinHandle<()>,
-    chan: FrontendClientHandle,
-}
-struct FrontendClientHandle(SyncSender<hello::GameFrontendMessage>);
-struct FrontendClientThread {}
-impl FrontendClient {
-    fn new(bind: String) -> Self {
-        let (client_chan, thread_chan) = sync_channel(1000);
-        let handle = std::thread::spawn(|| {
-            (FrontendClientThread::run(bind, thread_chan));
-            ()
-        });
-        Self {
-            handle,
-            chan: FrontendClientHandle(client_chan),
-        }
-    }
-}
-impl FrontendClientThread {
-    fn run(bind: String, mut queue: Receiver<GameFrontendMessage>) {
-        loop {
-            let _ = Self::run_loop(&bind, &mut queue);
-        }
-    }
+// Matches the ports used by examples/cs-server, whose generated code has to
+// be as new as ours: older builds sent JSON message bodies, not protobuf.
+const FRONTEND_ADDR: &str = "127.0.0.1:10001";
+const BACKEND_ADDR: &str = "127.0.0.1:10002";
 
-    fn run_loop(bind: &String, queue: &mut Receiver<GameFrontendMessage>) -> std::io::Result<()> {
-        let srv = UdpSocket::bind(bind).unwrap();
-        let mut buf = vec![0; 4096];
-        loop {
-            let res = queue.recv().unwrap();
-            // TODO: Needed impl.
-            res.serialize_into(buf);
-        }
-    }
+fn main() {
+    let frontend_transport = UdpTransport::connect("127.0.0.1:0", FRONTEND_ADDR).unwrap();
+    let backend = Backend {
+        frontend: hello::GameFrontendClient::new(frontend_transport),
+        x: 0,
+        y: 0,
+    };
+
+    // Replies go back to whoever sent the request.
+    let mut transport = UdpTransport::bind(BACKEND_ADDR).unwrap();
+    let mut dispatcher = hello::GameBackendDispatcher::new(backend);
+    dispatcher.serve(&mut transport).unwrap();
 }
 
-struct BackendServer {
-    handle: Option<JoinHandle<()>>,
-    bind: String,
+struct Backend {
+    frontend: hello::GameFrontendClient<UdpTransport>,
+    x: i32,
+    y: i32,
 }
-struct BackendServerThread {}
-impl BackendServer {
-    fn new(bind: String) -> Self {
-        Self { handle: None, bind }
-    }
-
-    fn start(&mut self) {
-        let bind = self.bind.clone();
-        self.handle = Some(std::thread::spawn(|| (BackendServerThread::run(&bind))));
-    }
-}
-
-impl BackendServerThread {
-    fn run(bind: &String) {
-        loop {
-            let _ = Self::run_loop(bind);
-        }
-    }
 
-    fn run_loop(bind: &String) -> std::io::Result<()> {
-        let srv = UdpSocket::bind(bind).unwrap();
-        let mut buf = vec![0; 4096];
-        loop {
-            let cnt = srv.recv(&mut buf)?;
-            let msg = hello::GameBackendMessage::try_deserialize_msg(&buf[..cnt])
-                .expect("Failed to parse message");
-            // TODO receive updates, print them. Then trigger a move commnd send
-            match msg {
-                hello::GameBackendMessage::NotifyInputEventArg(input) => {
-                    println!("Input Keycode: {:?}", input.keycode)
-                } //,
-                hello::GameBackendMessage::NotifyInputEventRet(_) => todo!(),
-            }
-        }
+impl hello::GameBackend for Backend {
+    fn notify_input_event(
+        &mut self,
+        req: hello::InputEvent,
+    ) -> Result<hello::Nil, hello::RpcError> {
+        println!("Input Keycode: {:?}", req.keycode);
+        // Receive updates, print them. Then trigger a move command send.
+        self.x += 1;
+        self.frontend.move_screen_oneway(&hello::Location {
+            x: self.x,
+            y: self.y,
+            ..Default::default()
+        })?;
+        Ok(hello::Nil::default())
     }
 }
@@ -0,0 +1,136 @@
+//! Async client side of generated services.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use crate::rpc::{
+    serialize_frame_into, try_deserialize_reply, MessageId, ProtoMessage, RpcError, RpcHeader,
+    RpcStatus, DEFAULT_RPC_TIMEOUT, FRAME_BUFFER_SIZE,
+};
+use crate::AsyncTransport;
+
+/// Request id bookkeeping and framing shared by the generated clients.
+///
+/// Calls take turns, only one request at a time waits for its reply.
+pub struct RpcClient<T: AsyncTransport> {
+    transport: T,
+    timeout: Duration,
+    next_request_id: AtomicU32,
+    frame_size: usize,
+    buf: tokio::sync::Mutex<Vec<u8>>,
+}
+
+impl<T: AsyncTransport> RpcClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self::with_frame_size(transport, FRAME_BUFFER_SIZE)
+    }
+    /// Sends and receives frames of up to `frame_size` bytes.
+    pub fn with_frame_size(transport: T, frame_size: usize) -> Self {
+        Self {
+            transport,
+            timeout: DEFAULT_RPC_TIMEOUT,
+            next_request_id: AtomicU32::new(1),
+            frame_size,
+            buf: tokio::sync::Mutex::new(vec![0; frame_size]),
+        }
+    }
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Send a request and wait for the reply with the same request id.
+    pub async fn call<A: ProtoMessage, R: ProtoMessage + Default>(
+        &self,
+        arg_id: MessageId,
+        ret_id: MessageId,
+        req: &A,
+    ) -> Result<R, RpcError> {
+        let request_id = loop {
+            // Zero is reserved for one-way requests.
+            let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+            if id != 0 {
+                break id;
+            }
+        };
+        let mut buf = self.buf.lock().await;
+        let len = serialize_request(arg_id, request_id, req, &mut buf)?;
+        self.transport.send(&buf[..len]).await?;
+
+        let wait_reply = async {
+            loop {
+                let (len, _) = self.transport.recv_from(&mut buf).await?;
+                match RpcHeader::try_deserialize(&buf[..len]) {
+                    Some(header) if header.request_id == request_id && header.msg_id == ret_id => {
+                        return Ok::<_, std::io::Error>(len);
+                    }
+                    // Late replies to requests that already timed out.
+                    _ => continue,
+                }
+            }
+        };
+        let len = tokio::time::timeout(self.timeout, wait_reply)
+            .await
+            .or(Err(RpcError::new(
+                RpcStatus::DeadlineExceeded,
+                "No reply received",
+            )))??;
+        try_deserialize_reply(&buf[RpcHeader::SIZE..len])
+    }
+
+    /// Send a request without waiting for a reply.
+    pub async fn send_oneway<A: ProtoMessage>(
+        &self,
+        arg_id: MessageId,
+        req: &A,
+    ) -> Result<(), RpcError> {
+        // Not `self.buf`, a call may hold it while waiting for its reply.
+        let mut buf = vec![0; self.frame_size];
+        let len = serialize_request(arg_id, 0, req, &mut buf)?;
+        self.transport.send(&buf[..len]).await?;
+        Ok(())
+    }
+}
+
+fn serialize_request<A: ProtoMessage>(
+    msg_id: MessageId,
+    request_id: u32,
+    req: &A,
+    buf: &mut [u8],
+) -> Result<usize, RpcError> {
+    let header = RpcHeader { msg_id, request_id };
+    serialize_frame_into(&header, req, buf)
+        .map_err(|e| RpcError::new(RpcStatus::ResourceExhausted, e.to_string()))
+}
+
+// `#[tokio::test]` needs the tokio macros, which are only a dev-dependency.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_transport::AsyncChannelTransport;
+    use crate::rpc::Bytes;
+
+    // One-way requests are framed like calls, in frames of the configured size.
+    #[tokio::test]
+    async fn test_send_oneway_frame_size() {
+        let (a, b) = AsyncChannelTransport::pair();
+        let client = RpcClient::with_frame_size(a, 64);
+        let req = Bytes(vec![7; 32]);
+        client.send_oneway(2, &req).await.unwrap();
+        let mut buf = [0u8; 64];
+        let (len, _) = b.recv_from(&mut buf).await.unwrap();
+        let body = Bytes::try_deserialize_body(&buf[RpcHeader::SIZE..len]).unwrap();
+        assert_eq!(body, req);
+
+        let err = client
+            .send_oneway(2, &Bytes(vec![7; 64]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, RpcStatus::ResourceExhausted);
+    }
+}
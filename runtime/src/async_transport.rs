@@ -0,0 +1,347 @@
+use std::{future::Future, io, net::SocketAddr};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{tcp, ToSocketAddrs, UdpSocket},
+    sync::{mpsc, Mutex},
+};
+
+#[cfg(unix)]
+use tokio::net::unix;
+
+/// Moves whole frames between RPC peers from any number of tasks at once.
+///
+/// Receiving must be cancel safe: a `recv_from` future that is dropped before
+/// completing must not lose or corrupt frames.
+pub trait AsyncTransport: Send + Sync + 'static {
+    /// Who sent a frame, so that the reply can go back to them.
+    type Peer: Send + Sync + 'static;
+
+    /// Send a frame to the default peer.
+    fn send(&self, frame: &[u8]) -> impl Future<Output = io::Result<()>> + Send;
+    fn send_to(
+        &self,
+        frame: &[u8],
+        peer: &Self::Peer,
+    ) -> impl Future<Output = io::Result<()>> + Send;
+    /// Receive a single frame into `buf`, returns its length and sender.
+    fn recv_from(
+        &self,
+        buf: &mut [u8],
+    ) -> impl Future<Output = io::Result<(usize, Self::Peer)>> + Send;
+}
+
+fn frame_too_large(len: usize, max: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Frame of {} bytes exceeds the limit of {} bytes", len, max),
+    )
+}
+
+/// One frame per datagram, see [`crate::transport::UdpTransport`].
+pub struct AsyncUdpTransport {
+    socket: UdpSocket,
+    mtu: usize,
+}
+
+impl AsyncUdpTransport {
+    pub const DEFAULT_MTU: usize = crate::transport::UdpTransport::DEFAULT_MTU;
+
+    /// Sends to the connected address of `socket`, if any.
+    pub fn new(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            mtu: Self::DEFAULT_MTU,
+        }
+    }
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(Self::new(UdpSocket::bind(addr).await?))
+    }
+    pub async fn connect<A: ToSocketAddrs, B: ToSocketAddrs>(bind: A, peer: B) -> io::Result<Self> {
+        let socket = UdpSocket::bind(bind).await?;
+        socket.connect(peer).await?;
+        Ok(Self::new(socket))
+    }
+
+    /// Frames larger than `mtu` are rejected when sending.
+    pub fn set_mtu(&mut self, mtu: usize) {
+        self.mtu = mtu;
+    }
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    fn check_mtu(&self, frame: &[u8]) -> io::Result<()> {
+        if frame.len() > self.mtu {
+            return Err(frame_too_large(frame.len(), self.mtu));
+        }
+        Ok(())
+    }
+}
+
+impl AsyncTransport for AsyncUdpTransport {
+    type Peer = SocketAddr;
+
+    async fn send(&self, frame: &[u8]) -> io::Result<()> {
+        self.check_mtu(frame)?;
+        self.socket.send(frame).await?;
+        Ok(())
+    }
+    async fn send_to(&self, frame: &[u8], peer: &SocketAddr) -> io::Result<()> {
+        self.check_mtu(frame)?;
+        self.socket.send_to(frame, peer).await?;
+        Ok(())
+    }
+    async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.socket.recv_from(buf).await
+    }
+}
+
+struct StreamReader<R> {
+    stream: R,
+    pending: Vec<u8>,
+}
+
+/// Frames prefixed with their length as a little endian `u32`, see
+/// [`crate::transport::StreamTransport`].
+///
+/// Partially received frames are kept across cancelled receives.
+pub struct AsyncStreamTransport<R, W> {
+    reader: Mutex<StreamReader<R>>,
+    writer: Mutex<W>,
+    max_frame_size: usize,
+}
+
+pub type AsyncTcpTransport = AsyncStreamTransport<tcp::OwnedReadHalf, tcp::OwnedWriteHalf>;
+#[cfg(unix)]
+pub type AsyncUnixTransport = AsyncStreamTransport<unix::OwnedReadHalf, unix::OwnedWriteHalf>;
+
+impl<R, W> AsyncStreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024;
+
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader: Mutex::new(StreamReader {
+                stream: reader,
+                pending: Vec::new(),
+            }),
+            writer: Mutex::new(writer),
+            max_frame_size: Self::DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+    /// Frames larger than `max` are rejected in both directions.
+    pub fn set_max_frame_size(&mut self, max: usize) {
+        self.max_frame_size = max;
+    }
+}
+
+impl AsyncTcpTransport {
+    pub fn from_stream(stream: tokio::net::TcpStream) -> io::Result<Self> {
+        // Frames are written whole, don't hold them back.
+        stream.set_nodelay(true)?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self::new(reader, writer))
+    }
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Self::from_stream(tokio::net::TcpStream::connect(addr).await?)
+    }
+}
+
+#[cfg(unix)]
+impl AsyncUnixTransport {
+    pub fn from_stream(stream: tokio::net::UnixStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self::new(reader, writer)
+    }
+    pub async fn connect<P: AsRef<std::path::Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::from_stream(
+            tokio::net::UnixStream::connect(path).await?,
+        ))
+    }
+}
+
+impl<R, W> AsyncTransport for AsyncStreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    /// Streams only have one peer.
+    type Peer = ();
+
+    async fn send(&self, frame: &[u8]) -> io::Result<()> {
+        if frame.len() > self.max_frame_size {
+            return Err(frame_too_large(frame.len(), self.max_frame_size));
+        }
+        let mut buf = Vec::with_capacity(4 + frame.len());
+        buf.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        buf.extend_from_slice(frame);
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&buf).await?;
+        writer.flush().await
+    }
+    async fn send_to(&self, frame: &[u8], _peer: &()) -> io::Result<()> {
+        self.send(frame).await
+    }
+    async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, ())> {
+        let mut reader = self.reader.lock().await;
+        let StreamReader { stream, pending } = &mut *reader;
+        loop {
+            if let Some(len) = pending.get(..4) {
+                let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+                if len > self.max_frame_size || len > buf.len() {
+                    return Err(frame_too_large(len, self.max_frame_size.min(buf.len())));
+                }
+                if pending.len() >= 4 + len {
+                    buf[..len].copy_from_slice(&pending[4..4 + len]);
+                    pending.drain(..4 + len);
+                    return Ok((len, ()));
+                }
+            }
+            // `read_buf` is cancel safe, bytes it returned are already in `pending`.
+            if stream.read_buf(pending).await? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+}
+
+/// In-memory transport, mostly for tests.
+pub struct AsyncChannelTransport {
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    rx: Mutex<mpsc::UnboundedReceiver<Vec<u8>>>,
+}
+
+impl AsyncChannelTransport {
+    /// Two transports connected to each other.
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, b_rx) = mpsc::unbounded_channel();
+        let (b_tx, a_rx) = mpsc::unbounded_channel();
+        (
+            Self {
+                tx: a_tx,
+                rx: Mutex::new(a_rx),
+            },
+            Self {
+                tx: b_tx,
+                rx: Mutex::new(b_rx),
+            },
+        )
+    }
+}
+
+impl AsyncTransport for AsyncChannelTransport {
+    type Peer = ();
+
+    async fn send(&self, frame: &[u8]) -> io::Result<()> {
+        self.tx
+            .send(frame.to_vec())
+            .or(Err(io::ErrorKind::BrokenPipe.into()))
+    }
+    async fn send_to(&self, frame: &[u8], _peer: &()) -> io::Result<()> {
+        self.send(frame).await
+    }
+    async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, ())> {
+        let frame = self
+            .rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(io::Error::from(io::ErrorKind::BrokenPipe))?;
+        if frame.len() > buf.len() {
+            return Err(frame_too_large(frame.len(), buf.len()));
+        }
+        buf[..frame.len()].copy_from_slice(&frame);
+        Ok((frame.len(), ()))
+    }
+}
+
+// `#[tokio::test]` needs the tokio macros, which are only a dev-dependency.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Send a frame each way and check both arrive intact.
+    async fn check_round_trip<A: AsyncTransport, B: AsyncTransport>(a: &A, b: &B) {
+        let mut buf = [0u8; 64];
+        a.send(b"ping").await.unwrap();
+        let (len, peer) = b.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"ping");
+        b.send_to(b"pong", &peer).await.unwrap();
+        let (len, _) = a.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"pong");
+    }
+
+    #[tokio::test]
+    async fn test_async_udp_round_trip() {
+        let server = AsyncUdpTransport::bind("127.0.0.1:0").await.unwrap();
+        let client = AsyncUdpTransport::connect("127.0.0.1:0", server.local_addr().unwrap())
+            .await
+            .unwrap();
+        check_round_trip(&client, &server).await;
+    }
+
+    #[tokio::test]
+    async fn test_async_udp_mtu() {
+        let server = AsyncUdpTransport::bind("127.0.0.1:0").await.unwrap();
+        let mut client = AsyncUdpTransport::connect("127.0.0.1:0", server.local_addr().unwrap())
+            .await
+            .unwrap();
+        client.set_mtu(8);
+        let err = client.send(&[0u8; 9]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_async_tcp_round_trip() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = AsyncTcpTransport::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let server = AsyncTcpTransport::from_stream(listener.accept().await.unwrap().0).unwrap();
+        check_round_trip(&client, &server).await;
+    }
+
+    #[tokio::test]
+    async fn test_async_stream_cancelled_recv() {
+        let (a, b) = tokio::io::duplex(64);
+        let (a_read, a_write) = tokio::io::split(a);
+        let (b_read, mut b_write) = tokio::io::split(b);
+        let a = AsyncStreamTransport::new(a_read, a_write);
+        drop(b_read);
+
+        // Deliver half a frame, give up waiting, then deliver the rest.
+        b_write.write_all(&[4, 0, 0, 0, b'p', b'i']).await.unwrap();
+        let mut buf = [0u8; 64];
+        let res =
+            tokio::time::timeout(std::time::Duration::from_millis(10), a.recv_from(&mut buf)).await;
+        assert!(res.is_err());
+        b_write.write_all(b"ng").await.unwrap();
+        let (len, _) = a.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"ping");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_async_unix_round_trip() {
+        let (a, b) = tokio::net::UnixStream::pair().unwrap();
+        let (a, b) = (
+            AsyncUnixTransport::from_stream(a),
+            AsyncUnixTransport::from_stream(b),
+        );
+        check_round_trip(&a, &b).await;
+    }
+
+    #[tokio::test]
+    async fn test_async_channel_round_trip() {
+        let (a, b) = AsyncChannelTransport::pair();
+        check_round_trip(&a, &b).await;
+    }
+}
@@ -0,0 +1,18 @@
+//! Support for the builders generated with the `builders` option.
+
+use core::fmt;
+
+/// A `[(required) = true]` field that wasn't set before building the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingField(
+    /// Qualified name of the field, e.g. `Outer.name`.
+    pub &'static str,
+);
+
+impl fmt::Display for MissingField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Required field {} not set", self.0)
+    }
+}
+
+impl core::error::Error for MissingField {}
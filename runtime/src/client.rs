@@ -0,0 +1,124 @@
+//! Blocking client side of generated services.
+
+use std::time::{Duration, Instant};
+
+use crate::rpc::{
+    serialize_frame_into, try_deserialize_reply, MessageId, ProtoMessage, RpcError, RpcHeader,
+    RpcStatus, DEFAULT_RPC_TIMEOUT, FRAME_BUFFER_SIZE,
+};
+use crate::Transport;
+
+/// Request id bookkeeping and framing shared by the generated clients.
+pub struct RpcClient<T: Transport> {
+    transport: T,
+    timeout: Duration,
+    next_request_id: u32,
+    buf: Vec<u8>,
+}
+
+impl<T: Transport> RpcClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self::with_frame_size(transport, FRAME_BUFFER_SIZE)
+    }
+    /// Sends and receives frames of up to `frame_size` bytes.
+    pub fn with_frame_size(transport: T, frame_size: usize) -> Self {
+        Self {
+            transport,
+            timeout: DEFAULT_RPC_TIMEOUT,
+            next_request_id: 1,
+            buf: vec![0; frame_size],
+        }
+    }
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Send a request and wait for the reply with the same request id.
+    pub fn call<A: ProtoMessage, R: ProtoMessage + Default>(
+        &mut self,
+        arg_id: MessageId,
+        ret_id: MessageId,
+        req: &A,
+    ) -> Result<R, RpcError> {
+        let request_id = self.next_request_id;
+        // Zero is reserved for one-way requests.
+        self.next_request_id = self.next_request_id.checked_add(1).unwrap_or(1);
+        self.send(arg_id, request_id, req)?;
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let timeout = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|t| !t.is_zero())
+                .ok_or_else(|| RpcError::new(RpcStatus::DeadlineExceeded, "No reply received"))?;
+            let len = self.transport.recv(&mut self.buf, Some(timeout))?;
+            let frame = &self.buf[..len];
+            match RpcHeader::try_deserialize(frame) {
+                Some(header) if header.request_id == request_id && header.msg_id == ret_id => {
+                    return try_deserialize_reply(&frame[RpcHeader::SIZE..]);
+                }
+                // Late replies to requests that already timed out.
+                _ => continue,
+            }
+        }
+    }
+
+    /// Send a request without waiting for a reply.
+    pub fn send_oneway<A: ProtoMessage>(
+        &mut self,
+        arg_id: MessageId,
+        req: &A,
+    ) -> Result<(), RpcError> {
+        self.send(arg_id, 0, req)
+    }
+
+    fn send<A: ProtoMessage>(
+        &mut self,
+        msg_id: MessageId,
+        request_id: u32,
+        req: &A,
+    ) -> Result<(), RpcError> {
+        let header = RpcHeader { msg_id, request_id };
+        let len = serialize_frame_into(&header, req, &mut self.buf)
+            .map_err(|e| RpcError::new(RpcStatus::ResourceExhausted, e.to_string()))?;
+        self.transport.send(&self.buf[..len])?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_call() {
+    use crate::rpc::{serialize_reply, Bytes};
+    use crate::transport::ChannelTransport;
+
+    let (client_transport, mut server) = ChannelTransport::pair();
+    let mut client = RpcClient::new(client_transport);
+    // Echoes requests, after a stale reply the client has to skip.
+    let echo = std::thread::spawn(move || {
+        let mut frame = [0u8; 64];
+        let mut reply = [0u8; 64];
+        let len = server.recv(&mut frame, None).unwrap();
+        let header = RpcHeader::try_deserialize(&frame).unwrap();
+        let body = Bytes::try_deserialize_body(&frame[RpcHeader::SIZE..len]).unwrap();
+        let stale = serialize_reply(3, header.request_id + 1, Ok(&body), &mut reply).unwrap();
+        server.send(&reply[..stale]).unwrap();
+        let len = serialize_reply(3, header.request_id, Ok(&body), &mut reply).unwrap();
+        server.send(&reply[..len]).unwrap();
+    });
+    let ret: Bytes = client.call(2, 3, &Bytes(b"hi".to_vec())).unwrap();
+    assert_eq!(ret.0, b"hi");
+    echo.join().unwrap();
+
+    client.set_timeout(Duration::from_millis(1));
+    let err = client.call::<_, Bytes>(2, 3, &Bytes(vec![])).unwrap_err();
+    assert_eq!(err.status, RpcStatus::Unavailable);
+}
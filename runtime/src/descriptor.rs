@@ -0,0 +1,483 @@
+//! Descriptions of messages, enums and services, for code that handles
+//! messages it wasn't compiled against. See
+//! [`crate::dynamic::DynamicMessage`].
+//!
+//! Generated code embeds them as statics borrowing its strings and slices,
+//! [`FileDescriptor::decode_set`] builds owned ones from the
+//! `google.protobuf.FileDescriptorSet` that `protogen --descriptor-set-out`
+//! and protoc write. Fields refer to the types of their values by qualified
+//! name, which the [`FileDescriptor`] holding them resolves.
+
+pub use alloc::borrow::Cow;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use crate::{
+    rpc::{MessageId, MAX_METHOD_ID},
+    wire::{DecodeError, Decoder},
+};
+
+/// Generated messages, with the file their descriptor is in.
+pub trait Describe {
+    const FILE: &'static FileDescriptor;
+    /// Qualified name of the message in [`Self::FILE`].
+    const NAME: &'static str;
+
+    fn descriptor() -> &'static MessageDescriptor {
+        Self::FILE
+            .message(Self::NAME)
+            .expect("generated messages are in their file descriptor")
+    }
+}
+
+/// Everything generated from one `.proto` file, as its `FILE_DESCRIPTOR`, or
+/// loaded from a descriptor set.
+#[derive(Debug, Clone)]
+pub struct FileDescriptor {
+    /// Nested messages and enums too.
+    pub messages: Cow<'static, [MessageDescriptor]>,
+    pub enums: Cow<'static, [EnumDescriptor]>,
+    pub services: Cow<'static, [ServiceDescriptor]>,
+}
+
+impl FileDescriptor {
+    /// Look up a message by its qualified name, e.g. `Outer.Inner`.
+    pub fn message(&self, name: &str) -> Option<&MessageDescriptor> {
+        self.messages.iter().find(|m| m.name == name)
+    }
+    pub fn enum_(&self, name: &str) -> Option<&EnumDescriptor> {
+        self.enums.iter().find(|e| e.name == name)
+    }
+    pub fn service(&self, name: &str) -> Option<&ServiceDescriptor> {
+        self.services.iter().find(|s| s.name == name)
+    }
+
+    /// Load every file of an encoded `google.protobuf.FileDescriptorSet`
+    /// into one descriptor, so types resolve across imports. Names in a
+    /// `package` are qualified with it, e.g. `pkg.Outer`.
+    pub fn decode_set(buf: &[u8]) -> Result<Self, DescriptorError> {
+        let mut file = Self {
+            messages: Cow::Owned(Vec::new()),
+            enums: Cow::Owned(Vec::new()),
+            services: Cow::Owned(Vec::new()),
+        };
+        for field in Decoder::new(buf) {
+            let (number, value) = field?;
+            if number == 1 {
+                file.decode_file(value.bytes(number)?)?;
+            }
+        }
+        file.check_types()?;
+        Ok(file)
+    }
+
+    // A `FileDescriptorProto`.
+    fn decode_file(&mut self, buf: &[u8]) -> Result<(), DescriptorError> {
+        let mut package = String::new();
+        let mut proto3 = false;
+        // The package comes first in files protoc writes, but needn't.
+        for field in Decoder::new(buf) {
+            match field? {
+                (2, value) => package = format!("{}.", value.str(2)?),
+                (12, value) => proto3 = value.str(12)? == "proto3",
+                _ => {}
+            }
+        }
+        for field in Decoder::new(buf) {
+            match field? {
+                (4, value) => self.decode_message(&package, proto3, value.bytes(4)?)?,
+                (5, value) => self.decode_enum(&package, value.bytes(5)?)?,
+                (6, value) => self.decode_service(&package, value.bytes(6)?)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // A `DescriptorProto`, nested messages and enums are added after it.
+    fn decode_message(
+        &mut self,
+        scope: &str,
+        proto3: bool,
+        buf: &[u8],
+    ) -> Result<(), DescriptorError> {
+        let name = format!("{}{}", scope, decode_name(buf)?);
+        let mut fields = Vec::new();
+        for field in Decoder::new(buf) {
+            if let (2, value) = field? {
+                fields.push(FieldDescriptor::decode(proto3, value.bytes(2)?)?);
+            }
+        }
+        let scope = format!("{}.", name);
+        self.messages.to_mut().push(MessageDescriptor {
+            name: Cow::Owned(name),
+            fields: Cow::Owned(fields),
+        });
+        for field in Decoder::new(buf) {
+            match field? {
+                (3, value) => self.decode_message(&scope, proto3, value.bytes(3)?)?,
+                (4, value) => self.decode_enum(&scope, value.bytes(4)?)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // An `EnumDescriptorProto`.
+    fn decode_enum(&mut self, scope: &str, buf: &[u8]) -> Result<(), DescriptorError> {
+        let mut values = Vec::new();
+        for field in Decoder::new(buf) {
+            if let (2, value) = field? {
+                let buf = value.bytes(2)?;
+                let mut number = 0;
+                for field in Decoder::new(buf) {
+                    if let (2, value) = field? {
+                        number = value.varint(2)?;
+                    }
+                }
+                values.push(EnumValueDescriptor {
+                    name: Cow::Owned(decode_name(buf)?.into()),
+                    number,
+                });
+            }
+        }
+        self.enums.to_mut().push(EnumDescriptor {
+            name: Cow::Owned(format!("{}{}", scope, decode_name(buf)?)),
+            values: Cow::Owned(values),
+        });
+        Ok(())
+    }
+
+    // A `ServiceDescriptorProto`, method ids are derived like protogen
+    // derives them unless the `(method_id)` option is set.
+    fn decode_service(&mut self, scope: &str, buf: &[u8]) -> Result<(), DescriptorError> {
+        let service = decode_name(buf)?;
+        let mut rpcs = Vec::new();
+        for field in Decoder::new(buf) {
+            let (2, value) = field? else { continue };
+            let buf = value.bytes(2)?;
+            let name = decode_name(buf)?;
+            let mut method_id = derived_method_id(service, name);
+            let (mut arg, mut ret) = (String::new(), String::new());
+            for field in Decoder::new(buf) {
+                match field? {
+                    (2, value) => arg = type_name(value.str(2)?),
+                    (3, value) => ret = type_name(value.str(3)?),
+                    (4, value) => {
+                        for option in Decoder::new(value.bytes(4)?) {
+                            if let (METHOD_ID_OPTION, value) = option? {
+                                method_id = value.varint::<u32>(METHOD_ID_OPTION)? & MAX_METHOD_ID;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            rpcs.push(RpcDescriptor {
+                name: Cow::Owned(name.into()),
+                arg_id: method_id << 1,
+                ret_id: method_id << 1 | 1,
+                arg: Cow::Owned(arg),
+                ret: Cow::Owned(ret),
+            });
+        }
+        self.services.to_mut().push(ServiceDescriptor {
+            name: Cow::Owned(format!("{}{}", scope, service)),
+            rpcs: Cow::Owned(rpcs),
+        });
+        Ok(())
+    }
+
+    // Every type a field or rpc refers to is in the file, so dynamic
+    // messages needn't check.
+    fn check_types(&self) -> Result<(), DescriptorError> {
+        for message in self.messages.iter() {
+            for field in message.fields.iter() {
+                let missing = match &field.ty {
+                    FieldType::Enum(name) => self.enum_(name).is_none().then_some(name),
+                    FieldType::Message(name) => self.message(name).is_none().then_some(name),
+                    _ => None,
+                };
+                if let Some(name) = missing {
+                    return Err(DescriptorError::UnknownType(name.to_string()));
+                }
+            }
+        }
+        for rpc in self.services.iter().flat_map(|s| s.rpcs.iter()) {
+            for name in [&rpc.arg, &rpc.ret] {
+                if self.message(name).is_none() {
+                    return Err(DescriptorError::UnknownType(name.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// The `name` field of a descriptor proto, always field 1.
+fn decode_name(buf: &[u8]) -> Result<&str, DecodeError> {
+    let mut name = "";
+    for field in Decoder::new(buf) {
+        if let (1, value) = field? {
+            name = value.str(1)?;
+        }
+    }
+    Ok(name)
+}
+
+// Type names in descriptor sets are fully qualified, `.pkg.Outer.Inner`.
+fn type_name(name: &str) -> String {
+    name.strip_prefix('.').unwrap_or(name).into()
+}
+
+/// Number of the `MethodOptions` extension that holds an explicit
+/// `(method_id)` in descriptor sets, from the range for private extensions.
+pub const METHOD_ID_OPTION: u32 = 50_000;
+
+/// Number of the `FieldOptions` extension that marks `[(required) = true]`
+/// fields in descriptor sets, which keep their proto3 label.
+pub const REQUIRED_OPTION: u32 = 50_001;
+
+/// The method id protogen gives an rpc without a `(method_id)` option, a
+/// stable hash of `Service.Method` so that ids don't shift when rpcs are
+/// reordered.
+pub fn derived_method_id(service: &str, method: &str) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for b in service.bytes().chain([b'.']).chain(method.bytes()) {
+        hash ^= b as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash & MAX_METHOD_ID
+}
+
+/// Why a descriptor set couldn't be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptorError {
+    Decode(DecodeError),
+    /// A field of a type generated code doesn't support, e.g. `double`.
+    UnsupportedType {
+        field: String,
+        ty: u32,
+    },
+    /// A field or rpc refers to a type that isn't in the set.
+    UnknownType(String),
+}
+
+impl From<DecodeError> for DescriptorError {
+    fn from(e: DecodeError) -> Self {
+        DescriptorError::Decode(e)
+    }
+}
+
+impl fmt::Display for DescriptorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DescriptorError::Decode(e) => write!(f, "Invalid descriptor set: {}", e),
+            DescriptorError::UnsupportedType { field, ty } => {
+                write!(f, "Field {} has unsupported type {}", field, ty)
+            }
+            DescriptorError::UnknownType(name) => write!(f, "Unknown type {}", name),
+        }
+    }
+}
+
+impl core::error::Error for DescriptorError {}
+
+#[derive(Debug, Clone)]
+pub struct MessageDescriptor {
+    /// Qualified, e.g. `Outer.Inner` for nested messages.
+    pub name: Cow<'static, str>,
+    pub fields: Cow<'static, [FieldDescriptor]>,
+}
+
+impl MessageDescriptor {
+    pub fn field(&self, name: &str) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+    pub fn field_by_number(&self, number: u32) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|f| f.number == number)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldDescriptor {
+    pub name: Cow<'static, str>,
+    pub number: u32,
+    pub ty: FieldType,
+    pub label: Label,
+}
+
+// FieldDescriptorProto.Label
+const LABEL_OPTIONAL: u32 = 1;
+const LABEL_REQUIRED: u32 = 2;
+const LABEL_REPEATED: u32 = 3;
+
+impl FieldDescriptor {
+    // A `FieldDescriptorProto`. Plain fields of proto2 files track presence
+    // like proto3 `optional` ones.
+    fn decode(proto3: bool, buf: &[u8]) -> Result<Self, DescriptorError> {
+        let mut number = 0;
+        let (mut label, mut ty, mut type_name) = (LABEL_OPTIONAL, 0, "");
+        let (mut proto3_optional, mut required) = (false, false);
+        for field in Decoder::new(buf) {
+            match field? {
+                (3, value) => number = value.varint(3)?,
+                (4, value) => label = value.varint(4)?,
+                (5, value) => ty = value.varint(5)?,
+                (6, value) => type_name = value.str(6)?,
+                (8, value) => {
+                    for option in Decoder::new(value.bytes(8)?) {
+                        if let (REQUIRED_OPTION, value) = option? {
+                            required = value.varint::<u64>(REQUIRED_OPTION)? != 0;
+                        }
+                    }
+                }
+                (17, value) => proto3_optional = value.varint::<u64>(17)? != 0,
+                _ => {}
+            }
+        }
+        let name = decode_name(buf)?;
+        Ok(Self {
+            name: Cow::Owned(name.into()),
+            number,
+            // FieldDescriptorProto.Type
+            ty: match ty {
+                3 => FieldType::Int64,
+                4 => FieldType::Uint64,
+                5 => FieldType::Int32,
+                9 => FieldType::String,
+                11 => FieldType::Message(Cow::Owned(self::type_name(type_name))),
+                13 => FieldType::Uint32,
+                14 => FieldType::Enum(Cow::Owned(self::type_name(type_name))),
+                ty => {
+                    return Err(DescriptorError::UnsupportedType {
+                        field: name.into(),
+                        ty,
+                    })
+                }
+            },
+            label: match label {
+                LABEL_REPEATED => Label::Repeated,
+                LABEL_REQUIRED => Label::Required,
+                _ if required => Label::Required,
+                _ if proto3_optional || !proto3 => Label::Optional,
+                _ => Label::Singular,
+            },
+        })
+    }
+}
+
+/// The type of the values of a field, messages and enums by qualified name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    String,
+    Enum(Cow<'static, str>),
+    Message(Cow<'static, str>),
+}
+
+/// How many values a field holds. `[(required) = true]` fields encode like
+/// singular ones, only builders check them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    Singular,
+    Optional,
+    Repeated,
+    Required,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDescriptor {
+    /// Qualified, e.g. `Outer.Kind` for enums nested in messages.
+    pub name: Cow<'static, str>,
+    pub values: Cow<'static, [EnumValueDescriptor]>,
+}
+
+impl EnumDescriptor {
+    pub fn name_of(&self, number: i32) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|v| v.number == number)
+            .map(|v| &*v.name)
+    }
+    pub fn number_of(&self, name: &str) -> Option<i32> {
+        self.values
+            .iter()
+            .find(|v| v.name == name)
+            .map(|v| v.number)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumValueDescriptor {
+    pub name: Cow<'static, str>,
+    pub number: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServiceDescriptor {
+    pub name: Cow<'static, str>,
+    pub rpcs: Cow<'static, [RpcDescriptor]>,
+}
+
+impl ServiceDescriptor {
+    /// Qualified name of the request or response type with message id `id`,
+    /// as found in [`crate::rpc::RpcHeader::msg_id`].
+    pub fn message(&self, id: MessageId) -> Option<&str> {
+        self.rpcs.iter().find_map(|rpc| match id {
+            _ if id == rpc.arg_id => Some(&*rpc.arg),
+            _ if id == rpc.ret_id => Some(&*rpc.ret),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcDescriptor {
+    pub name: Cow<'static, str>,
+    pub arg_id: MessageId,
+    pub ret_id: MessageId,
+    /// Qualified names of the request and response messages.
+    pub arg: Cow<'static, str>,
+    pub ret: Cow<'static, str>,
+}
+
+#[test]
+fn test_decode_set_errors() {
+    // Length delimited field `field` holding `fields`.
+    fn len(field: u8, fields: &[&[u8]]) -> Vec<u8> {
+        let body = fields.concat();
+        [&[field << 3 | 2, body.len() as u8][..], &body].concat()
+    }
+    let set = |field: &[u8]| len(1, &[&len(4, &[&len(1, &[b"M"]), &len(2, &[field])])]);
+
+    let missing = [
+        &len(1, &[b"f"])[..],
+        &[0x18, 1, 0x28, 11],
+        &len(6, &[b".pkg.Missing"]),
+    ];
+    assert_eq!(
+        FileDescriptor::decode_set(&set(&missing.concat())).unwrap_err(),
+        DescriptorError::UnknownType("pkg.Missing".into())
+    );
+    // TYPE_DOUBLE
+    let double = [&len(1, &[b"f"])[..], &[0x18, 1, 0x28, 1]];
+    assert_eq!(
+        FileDescriptor::decode_set(&set(&double.concat())).unwrap_err(),
+        DescriptorError::UnsupportedType {
+            field: "f".into(),
+            ty: 1
+        }
+    );
+    assert_eq!(
+        FileDescriptor::decode_set(&[0x0a, 5]).unwrap_err(),
+        DescriptorError::Decode(DecodeError::Truncated)
+    );
+}
@@ -0,0 +1,512 @@
+//! Messages of any type, decoded, inspected and encoded through their
+//! [`MessageDescriptor`] instead of generated code.
+
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt;
+
+use crate::{
+    descriptor::{
+        Describe, EnumDescriptor, FieldDescriptor, FieldType, FileDescriptor, Label,
+        MessageDescriptor,
+    },
+    rpc::ProtoMessage,
+    text::{self, TextError, TextMessage, TextParser, TextWriter},
+    wire::{self, DecodeError, EncodeError, Encoder, UnknownFields, Value, Varint},
+};
+
+/// The value of a field of a [`DynamicMessage`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicValue<'d> {
+    Int32(i32),
+    Int64(i64),
+    Uint32(u32),
+    Uint64(u64),
+    String(String),
+    /// Numbers the enum has no name for are kept too.
+    Enum(i32),
+    Message(DynamicMessage<'d>),
+}
+
+impl<'d> DynamicValue<'d> {
+    fn from_varint(ty: &FieldType, value: u64) -> Self {
+        match ty {
+            FieldType::Int32 => Self::Int32(i32::from_varint(value)),
+            FieldType::Int64 => Self::Int64(i64::from_varint(value)),
+            FieldType::Uint32 => Self::Uint32(u32::from_varint(value)),
+            FieldType::Uint64 => Self::Uint64(value),
+            FieldType::Enum(_) => Self::Enum(i32::from_varint(value)),
+            FieldType::String | FieldType::Message(_) => unreachable!("not a varint type"),
+        }
+    }
+
+    // Strings and messages aren't varints, fields only hold values of their type.
+    fn to_varint(&self) -> u64 {
+        match self {
+            Self::Int32(v) | Self::Enum(v) => v.to_varint(),
+            Self::Int64(v) => v.to_varint(),
+            Self::Uint32(v) => v.to_varint(),
+            Self::Uint64(v) => *v,
+            Self::String(_) | Self::Message(_) => unreachable!("not a varint value"),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        match self {
+            Self::String(v) => v.is_empty(),
+            Self::Message(v) => v.serialized_size() == 0,
+            v => v.to_varint() == 0,
+        }
+    }
+
+    fn has_type(&self, ty: &FieldType) -> bool {
+        match (self, ty) {
+            (Self::Int32(_), FieldType::Int32)
+            | (Self::Int64(_), FieldType::Int64)
+            | (Self::Uint32(_), FieldType::Uint32)
+            | (Self::Uint64(_), FieldType::Uint64)
+            | (Self::String(_), FieldType::String)
+            | (Self::Enum(_), FieldType::Enum(_)) => true,
+            (Self::Message(m), FieldType::Message(name)) => m.descriptor.name == *name,
+            _ => false,
+        }
+    }
+
+    fn parse_text(
+        file: &'d FileDescriptor,
+        ty: &FieldType,
+        p: &mut TextParser,
+    ) -> Result<Self, TextError> {
+        Ok(match ty {
+            FieldType::Int32 => Self::Int32(p.int()?),
+            FieldType::Int64 => Self::Int64(p.int()?),
+            FieldType::Uint32 => Self::Uint32(p.int()?),
+            FieldType::Uint64 => Self::Uint64(p.int()?),
+            FieldType::String => Self::String(p.string()?),
+            FieldType::Enum(e) => {
+                let e = enum_type(file, e);
+                Self::Enum(p.enum_number(|name| e.number_of(name))?)
+            }
+            FieldType::Message(m) => {
+                let mut msg = DynamicMessage::new(file, message_type(file, m));
+                p.merge_message(&mut msg)?;
+                Self::Message(msg)
+            }
+        })
+    }
+}
+
+// Descriptor sets are checked to hold the types of all fields when loaded.
+fn message_type<'d>(file: &'d FileDescriptor, name: &str) -> &'d MessageDescriptor {
+    file.message(name)
+        .expect("message types of fields are in their file")
+}
+
+fn enum_type<'d>(file: &'d FileDescriptor, name: &str) -> &'d EnumDescriptor {
+    file.enum_(name)
+        .expect("enum types of fields are in their file")
+}
+
+// Singular fields at their default value aren't encoded, like generated ones.
+fn skipped(field: &FieldDescriptor, value: &DynamicValue) -> bool {
+    matches!(field.label, Label::Singular | Label::Required) && value.is_default()
+}
+
+/// A message of the type of its descriptor. Encodes to the same bytes and
+/// text as the generated message, unknown fields included.
+///
+/// Borrows the [`FileDescriptor`] its descriptor is in, which resolves the
+/// types of message and enum fields.
+#[derive(Clone)]
+pub struct DynamicMessage<'d> {
+    file: &'d FileDescriptor,
+    descriptor: &'d MessageDescriptor,
+    // Indexed like the fields of the descriptor, at most one value for
+    // fields that aren't repeated.
+    values: Vec<Vec<DynamicValue<'d>>>,
+    pub unknown_fields: UnknownFields,
+}
+
+impl<'d> DynamicMessage<'d> {
+    /// A message with no fields set. `descriptor` is one of the messages of
+    /// `file`.
+    pub fn new(file: &'d FileDescriptor, descriptor: &'d MessageDescriptor) -> Self {
+        Self {
+            file,
+            descriptor,
+            values: descriptor.fields.iter().map(|_| Vec::new()).collect(),
+            unknown_fields: UnknownFields::default(),
+        }
+    }
+    pub fn decode(
+        file: &'d FileDescriptor,
+        descriptor: &'d MessageDescriptor,
+        buf: &[u8],
+    ) -> Result<Self, DecodeError> {
+        let mut msg = Self::new(file, descriptor);
+        msg.merge(buf)?;
+        Ok(msg)
+    }
+    pub fn parse_text(
+        file: &'d FileDescriptor,
+        descriptor: &'d MessageDescriptor,
+        s: &str,
+    ) -> Result<Self, TextError> {
+        let mut msg = Self::new(file, descriptor);
+        text::merge(s, &mut msg)?;
+        Ok(msg)
+    }
+
+    pub fn descriptor(&self) -> &'d MessageDescriptor {
+        self.descriptor
+    }
+    /// The file holding the types of the fields.
+    pub fn file(&self) -> &'d FileDescriptor {
+        self.file
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.descriptor.fields.iter().position(|f| f.name == name)
+    }
+
+    /// The value of a field that isn't repeated, `None` if it isn't set or
+    /// there's no such field.
+    pub fn get(&self, name: &str) -> Option<&DynamicValue<'d>> {
+        self.values[self.index(name)?].first()
+    }
+    /// All values of a field, at most one if it isn't repeated.
+    pub fn get_all(&self, name: &str) -> &[DynamicValue<'d>] {
+        self.index(name).map_or(&[], |i| &self.values[i])
+    }
+    /// The fields that are set, in the order of the descriptor.
+    pub fn fields(&self) -> impl Iterator<Item = (&'d FieldDescriptor, &[DynamicValue<'d>])> {
+        self.descriptor
+            .fields
+            .iter()
+            .zip(&self.values)
+            .filter(|(_, values)| !values.is_empty())
+            .map(|(field, values)| (field, values.as_slice()))
+    }
+
+    /// Replace the value of field `name`, all values if it's repeated.
+    /// Returns false if there's no such field of the value's type.
+    pub fn set(&mut self, name: &str, value: DynamicValue<'d>) -> bool {
+        match self.index(name) {
+            Some(i) if value.has_type(&self.descriptor.fields[i].ty) => {
+                self.values[i] = vec![value];
+                true
+            }
+            _ => false,
+        }
+    }
+    /// Add a value to the repeated field `name`. Returns false if there's no
+    /// such repeated field of the value's type.
+    pub fn push(&mut self, name: &str, value: DynamicValue<'d>) -> bool {
+        match self.index(name) {
+            Some(i)
+                if self.descriptor.fields[i].label == Label::Repeated
+                    && value.has_type(&self.descriptor.fields[i].ty) =>
+            {
+                self.values[i].push(value);
+                true
+            }
+            _ => false,
+        }
+    }
+    pub fn clear(&mut self, name: &str) {
+        if let Some(i) = self.index(name) {
+            self.values[i].clear();
+        }
+    }
+}
+
+impl DynamicMessage<'static> {
+    /// Copy a generated message.
+    pub fn from_message<M: ProtoMessage + Describe>(msg: &M) -> Self {
+        Self::decode(M::FILE, M::descriptor(), &msg.to_bytes())
+            .expect("generated messages match their descriptor")
+    }
+}
+
+impl ProtoMessage for DynamicMessage<'_> {
+    fn encode_fields(&self, e: &mut Encoder) -> Result<(), EncodeError> {
+        for (field, values) in self.descriptor.fields.iter().zip(&self.values) {
+            match field.ty {
+                FieldType::Int32
+                | FieldType::Int64
+                | FieldType::Uint32
+                | FieldType::Uint64
+                | FieldType::Enum(_)
+                    if field.label == Label::Repeated =>
+                {
+                    e.packed(field.number, values.iter().map(DynamicValue::to_varint))?;
+                }
+                _ => {
+                    for value in values.iter().filter(|v| !skipped(field, v)) {
+                        match value {
+                            DynamicValue::String(v) => e.string(field.number, v)?,
+                            DynamicValue::Message(v) => e.message(field.number, v)?,
+                            v => e.varint(field.number, v.to_varint())?,
+                        }
+                    }
+                }
+            }
+        }
+        e.unknown(&self.unknown_fields)
+    }
+    fn merge_field(&mut self, field: u32, value: Value) -> Result<(), DecodeError> {
+        let Some(i) = self
+            .descriptor
+            .fields
+            .iter()
+            .position(|f| f.number == field)
+        else {
+            self.unknown_fields.push(field, value);
+            return Ok(());
+        };
+        let desc = &self.descriptor.fields[i];
+        let values = &mut self.values[i];
+        let value = match &desc.ty {
+            FieldType::String => DynamicValue::String(value.str(field)?.into()),
+            FieldType::Message(m) => {
+                // Like generated code, a message field seen twice is merged.
+                if let (false, Some(DynamicValue::Message(msg))) =
+                    (desc.label == Label::Repeated, values.first_mut())
+                {
+                    return msg.merge(value.bytes(field)?);
+                }
+                let m = message_type(self.file, m);
+                DynamicValue::Message(Self::decode(self.file, m, value.bytes(field)?)?)
+            }
+            ty if desc.label == Label::Repeated => {
+                for v in value.varints::<u64>(field)? {
+                    values.push(DynamicValue::from_varint(ty, v?));
+                }
+                return Ok(());
+            }
+            ty => DynamicValue::from_varint(ty, value.varint(field)?),
+        };
+        if desc.label == Label::Repeated {
+            values.push(value);
+        } else {
+            *values = vec![value];
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        for (field, values) in self.descriptor.fields.iter().zip(&self.values) {
+            match field.ty {
+                FieldType::Int32
+                | FieldType::Int64
+                | FieldType::Uint32
+                | FieldType::Uint64
+                | FieldType::Enum(_)
+                    if field.label == Label::Repeated =>
+                {
+                    size += wire::packed_field_size(
+                        field.number,
+                        values.iter().map(DynamicValue::to_varint),
+                    );
+                }
+                _ => {
+                    for value in values.iter().filter(|v| !skipped(field, v)) {
+                        size += match value {
+                            DynamicValue::String(v) => wire::len_field_size(field.number, v.len()),
+                            DynamicValue::Message(v) => {
+                                wire::len_field_size(field.number, v.serialized_size())
+                            }
+                            v => wire::varint_field_size(field.number, v.to_varint()),
+                        };
+                    }
+                }
+            }
+        }
+        size + self.unknown_fields.len()
+    }
+}
+
+impl TextMessage for DynamicMessage<'_> {
+    fn write_fields(&self, w: &mut TextWriter) -> fmt::Result {
+        for (field, values) in self.descriptor.fields.iter().zip(&self.values) {
+            for value in values.iter().filter(|v| !skipped(field, v)) {
+                match value {
+                    DynamicValue::Int32(v) => w.int(&field.name, v)?,
+                    DynamicValue::Int64(v) => w.int(&field.name, v)?,
+                    DynamicValue::Uint32(v) => w.int(&field.name, v)?,
+                    DynamicValue::Uint64(v) => w.int(&field.name, v)?,
+                    DynamicValue::String(v) => w.string(&field.name, v)?,
+                    DynamicValue::Enum(v) => match &field.ty {
+                        FieldType::Enum(e) => match enum_type(self.file, e).name_of(*v) {
+                            Some(name) => w.enum_name(&field.name, name)?,
+                            None => w.int(&field.name, v)?,
+                        },
+                        _ => unreachable!("enum values are only in enum fields"),
+                    },
+                    DynamicValue::Message(v) => w.message(&field.name, v)?,
+                }
+            }
+        }
+        Ok(())
+    }
+    fn merge_text_field(&mut self, name: &str, p: &mut TextParser) -> Result<(), TextError> {
+        let Some(i) = self.index(name) else {
+            return Err(p.unknown_field(name));
+        };
+        let (file, desc) = (self.file, &self.descriptor.fields[i]);
+        let values = &mut self.values[i];
+        if desc.label == Label::Repeated {
+            return p.repeated(|p| {
+                values.push(DynamicValue::parse_text(file, &desc.ty, p)?);
+                Ok(())
+            });
+        }
+        if let Some(DynamicValue::Message(msg)) = values.first_mut() {
+            return p.merge_message(msg);
+        }
+        *values = vec![DynamicValue::parse_text(file, &desc.ty, p)?];
+        Ok(())
+    }
+}
+
+/// The text format, like generated messages.
+impl fmt::Display for DynamicMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        text::write(self, f)
+    }
+}
+
+// Named after the message type, the descriptor itself would be noise.
+impl fmt::Debug for DynamicMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct(&self.descriptor.name);
+        for (field, values) in self.fields() {
+            match field.label {
+                Label::Repeated => s.field(&field.name, &values),
+                _ => s.field(&field.name, &values[0]),
+            };
+        }
+        if !self.unknown_fields.is_empty() {
+            s.field("unknown_fields", &self.unknown_fields);
+        }
+        s.finish()
+    }
+}
+
+// Messages of the same type are equal whichever descriptor they were
+// decoded with, e.g. a generated or a loaded one.
+impl PartialEq for DynamicMessage<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.descriptor.name == other.descriptor.name
+            && self.values == other.values
+            && self.unknown_fields == other.unknown_fields
+    }
+}
+
+// Descriptors like generated code embeds them.
+#[cfg(test)]
+mod test_schema {
+    use crate::descriptor::*;
+
+    const COLOR: EnumDescriptor = EnumDescriptor {
+        name: Cow::Borrowed("Color"),
+        values: Cow::Borrowed(&[
+            EnumValueDescriptor {
+                name: Cow::Borrowed("RED"),
+                number: 0,
+            },
+            EnumValueDescriptor {
+                name: Cow::Borrowed("GREEN"),
+                number: 1,
+            },
+        ]),
+    };
+    const TREE: MessageDescriptor = MessageDescriptor {
+        name: Cow::Borrowed("Tree"),
+        fields: Cow::Borrowed(&[
+            FieldDescriptor {
+                name: Cow::Borrowed("size"),
+                number: 1,
+                ty: FieldType::Int32,
+                label: Label::Singular,
+            },
+            FieldDescriptor {
+                name: Cow::Borrowed("name"),
+                number: 2,
+                ty: FieldType::String,
+                label: Label::Optional,
+            },
+            FieldDescriptor {
+                name: Cow::Borrowed("colors"),
+                number: 3,
+                ty: FieldType::Enum(Cow::Borrowed("Color")),
+                label: Label::Repeated,
+            },
+            FieldDescriptor {
+                name: Cow::Borrowed("children"),
+                number: 4,
+                ty: FieldType::Message(Cow::Borrowed("Tree")),
+                label: Label::Repeated,
+            },
+        ]),
+    };
+    pub static FILE: FileDescriptor = FileDescriptor {
+        messages: Cow::Borrowed(&[TREE]),
+        enums: Cow::Borrowed(&[COLOR]),
+        services: Cow::Borrowed(&[]),
+    };
+
+    pub fn tree() -> &'static MessageDescriptor {
+        FILE.message("Tree").unwrap()
+    }
+}
+
+#[test]
+fn test_dynamic_round_trip() {
+    use test_schema::{tree, FILE};
+
+    let bytes = [
+        0x08, 0x7f, 0x12, 2, b'h', b'i', 0x1a, 2, 0x01, 0x05, 0x22, 2, 0x08, 0x01, 0x28, 0x03,
+    ];
+    let msg = DynamicMessage::decode(&FILE, tree(), &bytes).unwrap();
+    assert_eq!(msg.get("size"), Some(&DynamicValue::Int32(127)));
+    assert_eq!(
+        msg.get_all("colors"),
+        [DynamicValue::Enum(1), DynamicValue::Enum(5)]
+    );
+    let DynamicValue::Message(child) = &msg.get_all("children")[0] else {
+        panic!("children holds messages");
+    };
+    assert_eq!(child.get("size"), Some(&DynamicValue::Int32(1)));
+    assert_eq!(msg.unknown_fields.len(), 2);
+    assert_eq!(msg.serialized_size(), bytes.len());
+    assert_eq!(msg.to_bytes(), bytes);
+
+    let text = r#"size: 127 name: "hi" colors: GREEN colors: 5 children { size: 1 }"#;
+    assert_eq!(msg.to_string(), text);
+    let mut parsed = DynamicMessage::parse_text(&FILE, tree(), text).unwrap();
+    parsed.unknown_fields = msg.unknown_fields.clone();
+    assert_eq!(parsed, msg);
+}
+
+#[test]
+fn test_dynamic_set() {
+    use test_schema::{tree, FILE};
+
+    let mut msg = DynamicMessage::new(&FILE, tree());
+    assert!(msg.set("size", DynamicValue::Int32(0)));
+    assert!(!msg.set("size", DynamicValue::Int64(1)));
+    assert!(!msg.set("missing", DynamicValue::Int32(1)));
+    assert!(!msg.push("size", DynamicValue::Int32(1)));
+    assert!(msg.push(
+        "children",
+        DynamicValue::Message(DynamicMessage::new(&FILE, tree()))
+    ));
+    assert!(msg.set("name", DynamicValue::String("".into())));
+    // Singular fields at their default aren't encoded, set optional ones are.
+    assert_eq!(msg.to_bytes(), [0x12, 0, 0x22, 0]);
+    msg.clear("children");
+    assert_eq!(
+        msg.fields().map(|(f, _)| &*f.name).collect::<Vec<_>>(),
+        ["size", "name"]
+    );
+}
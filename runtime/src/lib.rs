@@ -0,0 +1,36 @@
+//! Runtime support shared by all code generated by protogen.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "tokio")]
+pub mod async_client;
+#[cfg(feature = "tokio")]
+pub mod async_transport;
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod client;
+pub mod descriptor;
+pub mod dynamic;
+pub mod rpc;
+pub mod text;
+#[cfg(feature = "std")]
+pub mod transport;
+pub mod view;
+pub mod wire;
+
+#[cfg(feature = "tokio")]
+pub use async_transport::AsyncTransport;
+#[cfg(feature = "std")]
+pub use transport::Transport;
+
+// Generated `--rust-async` code uses tokio through the runtime, so crates
+// don't have to keep a matching tokio dependency of their own.
+#[cfg(feature = "tokio")]
+pub use tokio;
+
+/// Generated code refers to this constant, so it only compiles against the
+/// runtime version it was generated for. Renamed whenever generated code
+/// would no longer work with this runtime.
+pub const VERSION_0_2: () = ();
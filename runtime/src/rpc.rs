@@ -0,0 +1,429 @@
+//! Framing, status codes and errors of RPC messages, shared by all generated
+//! code.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::time::Duration;
+
+use crate::wire::{DecodeError, Decoder, EncodeError, Encoder, Value};
+
+/// Numeric id of an RPC message, `method_id << 1` for requests and
+/// `method_id << 1 | 1` for responses.
+pub type MessageId = u32;
+
+/// Method ids are limited to 31 bits, the low bit of a message id holds the direction.
+pub const MAX_METHOD_ID: u32 = 0x7fff_ffff;
+
+/// A message in the protobuf binary encoding, implemented by generated code.
+pub trait ProtoMessage {
+    /// Largest encoded body accepted by [`Self::serialize_body_into`] and
+    /// [`Self::try_deserialize_body`], the `MAX_MESSAGE_SIZE` of the schema.
+    const MAX_SIZE: usize = usize::MAX;
+
+    /// Write all fields that aren't at their default value.
+    fn encode_fields(&self, e: &mut Encoder) -> Result<(), EncodeError>;
+    /// Update the message with one decoded field. Fields the message doesn't
+    /// know are skipped.
+    fn merge_field(&mut self, field: u32, value: Value) -> Result<(), DecodeError>;
+    /// Exact length of the encoded message.
+    fn serialized_size(&self) -> usize;
+
+    /// Update the message with all fields encoded in `buf`.
+    fn merge(&mut self, buf: &[u8]) -> Result<(), DecodeError> {
+        for field in Decoder::new(buf) {
+            let (field, value) = field?;
+            self.merge_field(field, value)?;
+        }
+        Ok(())
+    }
+    /// Encode the message into `buf`, returns the encoded length.
+    fn serialize_body_into(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        if Self::MAX_SIZE != usize::MAX {
+            let size = self.serialized_size();
+            if size > Self::MAX_SIZE {
+                return Err(EncodeError::MessageTooLarge {
+                    size,
+                    max: Self::MAX_SIZE,
+                });
+            }
+        }
+        let mut e = Encoder::new(buf);
+        self.encode_fields(&mut e)?;
+        Ok(e.position())
+    }
+    fn try_deserialize_body(buf: &[u8]) -> Result<Self, DecodeError>
+    where
+        Self: Default,
+    {
+        if buf.len() > Self::MAX_SIZE {
+            return Err(DecodeError::MessageTooLarge {
+                size: buf.len(),
+                max: Self::MAX_SIZE,
+            });
+        }
+        let mut msg = Self::default();
+        msg.merge(buf)?;
+        Ok(msg)
+    }
+    /// The encoded message in a buffer of its own, whatever its size.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = alloc::vec![0; self.serialized_size()];
+        self.encode_fields(&mut Encoder::new(&mut buf))
+            .expect("serialized_size is exact");
+        buf
+    }
+}
+
+/// Fixed size header in front of every RPC message, little endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcHeader {
+    pub msg_id: MessageId,
+    /// Correlates a reply with its request. Zero is reserved for one-way
+    /// requests, which never get a reply.
+    pub request_id: u32,
+}
+
+impl RpcHeader {
+    pub const SIZE: usize = 8;
+
+    pub fn serialize_into(&self, buf: &mut [u8]) -> Result<(), EncodeError> {
+        let bytes = buf
+            .get_mut(..Self::SIZE)
+            .ok_or(EncodeError::BufferTooSmall)?;
+        bytes[..4].copy_from_slice(&self.msg_id.to_le_bytes());
+        bytes[4..].copy_from_slice(&self.request_id.to_le_bytes());
+        Ok(())
+    }
+    pub fn try_deserialize(buf: &[u8]) -> Option<Self> {
+        let bytes = buf.get(..Self::SIZE)?;
+        Some(Self {
+            msg_id: MessageId::from_le_bytes(bytes[..4].try_into().ok()?),
+            request_id: u32::from_le_bytes(bytes[4..].try_into().ok()?),
+        })
+    }
+}
+
+/// Serialize a header followed by the body of `msg`, returns the frame length.
+pub fn serialize_frame_into<M: ProtoMessage>(
+    header: &RpcHeader,
+    msg: &M,
+    buf: &mut [u8],
+) -> Result<usize, EncodeError> {
+    header.serialize_into(buf)?;
+    let size = msg.serialize_body_into(&mut buf[RpcHeader::SIZE..])?;
+    Ok(RpcHeader::SIZE + size)
+}
+
+/// Serialize a reply frame, returns the frame length.
+///
+/// The header is followed by a little endian `u32` status. A successful reply
+/// continues with the body of the returned message, a failed one with the
+/// rest of the [`RpcError`]. Either runs to the end of the frame.
+pub fn serialize_reply_into<M: ProtoMessage>(
+    header: &RpcHeader,
+    ret: Result<&M, &RpcError>,
+    buf: &mut [u8],
+) -> Result<usize, EncodeError> {
+    header.serialize_into(buf)?;
+    let body = &mut buf[RpcHeader::SIZE..];
+    let size = match ret {
+        Ok(msg) => {
+            body.get_mut(..4)
+                .ok_or(EncodeError::BufferTooSmall)?
+                .copy_from_slice(&(RpcStatus::Ok as u32).to_le_bytes());
+            4 + msg.serialize_body_into(&mut body[4..])?
+        }
+        Err(e) => e.serialize_into(body)?,
+    };
+    Ok(RpcHeader::SIZE + size)
+}
+
+/// Decode what follows the header of a reply frame. A truncated or
+/// malformed reply becomes an [`RpcStatus::DataLoss`] error.
+pub fn try_deserialize_reply<M: ProtoMessage + Default>(buf: &[u8]) -> Result<M, RpcError> {
+    let malformed = || RpcError::new(RpcStatus::DataLoss, "Malformed reply");
+    let status = buf.get(..4).ok_or_else(malformed)?;
+    if status == (RpcStatus::Ok as u32).to_le_bytes() {
+        M::try_deserialize_body(&buf[4..])
+            .map_err(|e| RpcError::new(RpcStatus::DataLoss, format!("Malformed reply: {e}")))
+    } else {
+        Err(RpcError::try_deserialize(buf).ok_or_else(malformed)?)
+    }
+}
+
+/// Serialize the reply to a request, unless it was one-way, for generated
+/// dispatchers. Returns the frame length, zero for one-way requests.
+pub fn serialize_reply<M: ProtoMessage>(
+    msg_id: MessageId,
+    request_id: u32,
+    ret: Result<&M, &RpcError>,
+    reply: &mut [u8],
+) -> Result<usize, RpcError> {
+    if request_id == 0 {
+        return Ok(0);
+    }
+    let header = RpcHeader { msg_id, request_id };
+    serialize_reply_into(&header, ret, reply)
+        .map_err(|e| RpcError::new(RpcStatus::ResourceExhausted, e.to_string()))
+}
+
+/// Serialize an error reply, for requests that never reached a service
+/// method. Returns the frame length, zero for one-way requests.
+pub fn serialize_error_reply(
+    msg_id: MessageId,
+    request_id: u32,
+    error: &RpcError,
+    reply: &mut [u8],
+) -> Result<usize, RpcError> {
+    if request_id == 0 {
+        return Ok(0);
+    }
+    let header = RpcHeader { msg_id, request_id };
+    header
+        .serialize_into(reply)
+        .and_then(|()| error.serialize_into(&mut reply[RpcHeader::SIZE..]))
+        .map(|size| RpcHeader::SIZE + size)
+        .or(Err(RpcError::new(
+            RpcStatus::ResourceExhausted,
+            "Reply buffer too small",
+        )))
+}
+
+/// Status codes of a failed RPC, numbered like gRPC status codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RpcStatus {
+    Ok = 0,
+    Cancelled = 1,
+    Unknown = 2,
+    InvalidArgument = 3,
+    DeadlineExceeded = 4,
+    NotFound = 5,
+    AlreadyExists = 6,
+    PermissionDenied = 7,
+    ResourceExhausted = 8,
+    FailedPrecondition = 9,
+    Aborted = 10,
+    OutOfRange = 11,
+    Unimplemented = 12,
+    Internal = 13,
+    Unavailable = 14,
+    DataLoss = 15,
+    Unauthenticated = 16,
+}
+
+impl From<u32> for RpcStatus {
+    /// Codes this version doesn't know about become [`RpcStatus::Unknown`].
+    fn from(code: u32) -> Self {
+        match code {
+            0 => RpcStatus::Ok,
+            1 => RpcStatus::Cancelled,
+            3 => RpcStatus::InvalidArgument,
+            4 => RpcStatus::DeadlineExceeded,
+            5 => RpcStatus::NotFound,
+            6 => RpcStatus::AlreadyExists,
+            7 => RpcStatus::PermissionDenied,
+            8 => RpcStatus::ResourceExhausted,
+            9 => RpcStatus::FailedPrecondition,
+            10 => RpcStatus::Aborted,
+            11 => RpcStatus::OutOfRange,
+            12 => RpcStatus::Unimplemented,
+            13 => RpcStatus::Internal,
+            14 => RpcStatus::Unavailable,
+            15 => RpcStatus::DataLoss,
+            16 => RpcStatus::Unauthenticated,
+            _ => RpcStatus::Unknown,
+        }
+    }
+}
+
+/// A failed RPC, sent back to the caller in place of the reply message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    /// Never [`RpcStatus::Ok`].
+    pub status: RpcStatus,
+    pub message: String,
+    /// Application defined payload, e.g. a serialized message.
+    pub details: Vec<u8>,
+}
+
+impl RpcError {
+    pub fn new<S: Into<String>>(status: RpcStatus, message: S) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+    pub fn with_details(mut self, details: Vec<u8>) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// Serialize as the little endian `u32` status, the `u32` length of the
+    /// message, the UTF-8 message and finally the details, which run to the
+    /// end of the frame. Returns the serialized length. An error built with
+    /// [`RpcStatus::Ok`] goes out as [`RpcStatus::Unknown`], the peer would
+    /// take it for a successful reply otherwise.
+    pub fn serialize_into(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let size = 8 + self.message.len() + self.details.len();
+        let bytes = buf.get_mut(..size).ok_or(EncodeError::BufferTooSmall)?;
+        let status = match self.status {
+            RpcStatus::Ok => RpcStatus::Unknown,
+            status => status,
+        };
+        bytes[..4].copy_from_slice(&(status as u32).to_le_bytes());
+        bytes[4..8].copy_from_slice(&(self.message.len() as u32).to_le_bytes());
+        let (message, details) = bytes[8..].split_at_mut(self.message.len());
+        message.copy_from_slice(self.message.as_bytes());
+        details.copy_from_slice(&self.details);
+        Ok(size)
+    }
+    pub fn try_deserialize(buf: &[u8]) -> Option<Self> {
+        let status = u32::from_le_bytes(buf.get(..4)?.try_into().ok()?);
+        let len = u32::from_le_bytes(buf.get(4..8)?.try_into().ok()?) as usize;
+        let end = len.checked_add(8)?;
+        let message = core::str::from_utf8(buf.get(8..end)?).ok()?;
+        Some(Self {
+            status: status.into(),
+            message: message.into(),
+            details: buf[end..].to_vec(),
+        })
+    }
+}
+
+impl core::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{:?}: {}", self.status, self.message)
+    }
+}
+
+impl core::error::Error for RpcError {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for RpcError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                RpcError::new(RpcStatus::DeadlineExceeded, e.to_string())
+            }
+            _ => RpcError::new(RpcStatus::Unavailable, e.to_string()),
+        }
+    }
+}
+
+/// How long generated clients wait for a reply unless told otherwise.
+pub const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(1);
+/// Size of the buffers frames are serialized into and received in, for
+/// schemas without a `max_frame_size` option.
+pub const FRAME_BUFFER_SIZE: usize = 4096;
+/// What a successful reply frame holds besides the message body, the header
+/// and the status.
+pub const REPLY_OVERHEAD: usize = RpcHeader::SIZE + 4;
+
+// A message with a single bytes field, limited to 1 KiB.
+#[cfg(test)]
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Bytes(pub Vec<u8>);
+
+#[cfg(test)]
+impl ProtoMessage for Bytes {
+    const MAX_SIZE: usize = 1024;
+
+    fn encode_fields(&self, e: &mut Encoder) -> Result<(), EncodeError> {
+        e.bytes(1, &self.0)
+    }
+    fn merge_field(&mut self, field: u32, value: Value) -> Result<(), DecodeError> {
+        if field == 1 {
+            self.0 = value.bytes(field)?.to_vec();
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        crate::wire::len_field_size(1, self.0.len())
+    }
+}
+
+#[test]
+fn test_reply_round_trip() {
+    let mut buf = [0u8; 64];
+    let header = RpcHeader {
+        msg_id: 3,
+        request_id: 7,
+    };
+    let len = serialize_reply_into(&header, Ok(&Bytes(b"hi".to_vec())), &mut buf).unwrap();
+    assert_eq!(len, RpcHeader::SIZE + 4 + 4);
+    assert_eq!(RpcHeader::try_deserialize(&buf), Some(header));
+    let reply = try_deserialize_reply::<Bytes>(&buf[RpcHeader::SIZE..len]);
+    assert_eq!(reply, Ok(Bytes(b"hi".to_vec())));
+
+    let error = RpcError::new(RpcStatus::NotFound, "missing").with_details(vec![1, 2]);
+    let len = serialize_reply_into::<Bytes>(&header, Err(&error), &mut buf).unwrap();
+    let reply = try_deserialize_reply::<Bytes>(&buf[RpcHeader::SIZE..len]);
+    assert_eq!(reply, Err(error));
+
+    assert_eq!(
+        serialize_reply_into(&header, Ok(&Bytes(vec![0; 64])), &mut buf),
+        Err(EncodeError::BufferTooSmall)
+    );
+    assert_eq!(serialize_reply(3, 0, Ok(&Bytes(vec![])), &mut buf), Ok(0));
+}
+
+#[test]
+fn test_malformed_reply() {
+    let status = |r: Result<Bytes, RpcError>| r.unwrap_err().status;
+    assert_eq!(status(try_deserialize_reply(&[0, 0])), RpcStatus::DataLoss);
+    // Status Ok, then a length delimited field running past the end.
+    assert_eq!(
+        status(try_deserialize_reply(&[0, 0, 0, 0, 0x0a, 5])),
+        RpcStatus::DataLoss
+    );
+    // Status NotFound, then a message length running past the end.
+    assert_eq!(
+        status(try_deserialize_reply(&[5, 0, 0, 0, 9, 0, 0, 0])),
+        RpcStatus::DataLoss
+    );
+}
+
+#[test]
+fn test_ok_error_serialized_as_unknown() {
+    let mut buf = [0u8; 64];
+    let len = RpcError::new(RpcStatus::Ok, "oops")
+        .serialize_into(&mut buf)
+        .unwrap();
+    assert_eq!(
+        try_deserialize_reply::<Bytes>(&buf[..len]),
+        Err(RpcError::new(RpcStatus::Unknown, "oops"))
+    );
+}
+
+#[test]
+fn test_status_from_code() {
+    assert_eq!(RpcStatus::from(12), RpcStatus::Unimplemented);
+    assert_eq!(RpcStatus::from(99), RpcStatus::Unknown);
+}
+
+#[test]
+fn test_max_size() {
+    let mut buf = [0u8; 2048];
+    let fits = Bytes(vec![7; 1021]);
+    assert_eq!(fits.serialize_body_into(&mut buf), Ok(1024));
+    assert_eq!(Bytes::try_deserialize_body(&buf[..1024]), Ok(fits));
+    let large = Bytes(vec![7; 1022]);
+    let too_large = EncodeError::MessageTooLarge {
+        size: 1025,
+        max: 1024,
+    };
+    assert_eq!(large.serialize_body_into(&mut buf), Err(too_large));
+    assert_eq!(large.to_bytes().len(), 1025);
+    assert_eq!(
+        Bytes::try_deserialize_body(&large.to_bytes()),
+        Err(DecodeError::MessageTooLarge {
+            size: 1025,
+            max: 1024
+        })
+    );
+}
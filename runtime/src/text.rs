@@ -0,0 +1,537 @@
+//! The protobuf text format, `name: "a" point { x: 1 } bigs: [1, 2]`, which
+//! generated messages implement `Display` and `FromStr` with. Meant for logs
+//! and hand-written test data, unknown fields are left out.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Write};
+
+use crate::wire::Varint;
+
+/// A message in the text format, implemented by generated code.
+pub trait TextMessage {
+    /// Write all fields that aren't at their default value.
+    fn write_fields(&self, w: &mut TextWriter) -> fmt::Result;
+    /// Parse the value of field `name`, its name and colon are already read.
+    fn merge_text_field(&mut self, name: &str, p: &mut TextParser) -> Result<(), TextError>;
+}
+
+/// Generated enums, written as the names of their values.
+pub trait TextEnum: Varint {
+    /// Name of the value in the schema.
+    fn name(self) -> &'static str;
+    fn from_name(name: &str) -> Option<Self>;
+}
+
+/// Write `msg` on a single line, or one field per line for `{:#}`.
+pub fn write<M: TextMessage>(msg: &M, f: &mut fmt::Formatter) -> fmt::Result {
+    let pretty = f.alternate();
+    msg.write_fields(&mut TextWriter::new(f, pretty))
+}
+
+/// Parse a whole message.
+pub fn parse<M: TextMessage + Default>(s: &str) -> Result<M, TextError> {
+    let mut msg = M::default();
+    merge(s, &mut msg)?;
+    Ok(msg)
+}
+
+/// Update `msg` with all fields in `s`.
+pub fn merge<M: TextMessage>(s: &str, msg: &mut M) -> Result<(), TextError> {
+    TextParser::new(s).merge_fields(msg, None)
+}
+
+/// Why text couldn't be parsed, with the 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextError {
+    pub line: usize,
+    pub column: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.msg)
+    }
+}
+
+impl core::error::Error for TextError {}
+
+/// Writes the fields of messages, separated by spaces or, when pretty, by
+/// newlines with nested messages indented.
+pub struct TextWriter<'a> {
+    out: &'a mut dyn Write,
+    pretty: bool,
+    depth: usize,
+    // Nothing written in the current message yet.
+    first: bool,
+}
+
+impl<'a> TextWriter<'a> {
+    pub fn new(out: &'a mut dyn Write, pretty: bool) -> Self {
+        Self {
+            out,
+            pretty,
+            depth: 0,
+            first: true,
+        }
+    }
+
+    fn name(&mut self, name: &str) -> fmt::Result {
+        if !(self.first && self.depth == 0) {
+            self.out.write_char(if self.pretty { '\n' } else { ' ' })?;
+        }
+        if self.pretty {
+            for _ in 0..self.depth {
+                self.out.write_str("  ")?;
+            }
+        }
+        self.first = false;
+        self.out.write_str(name)
+    }
+
+    pub fn int(&mut self, name: &str, value: impl fmt::Display) -> fmt::Result {
+        self.name(name)?;
+        write!(self.out, ": {}", value)
+    }
+    pub fn string(&mut self, name: &str, value: &str) -> fmt::Result {
+        self.name(name)?;
+        self.out.write_str(": \"")?;
+        for c in value.chars() {
+            match c {
+                '"' => self.out.write_str("\\\"")?,
+                '\\' => self.out.write_str("\\\\")?,
+                '\n' => self.out.write_str("\\n")?,
+                '\r' => self.out.write_str("\\r")?,
+                '\t' => self.out.write_str("\\t")?,
+                c if c.is_control() && (c as u32) < 0x80 => {
+                    write!(self.out, "\\x{:02x}", c as u32)?
+                }
+                c => self.out.write_char(c)?,
+            }
+        }
+        self.out.write_char('"')
+    }
+    pub fn enum_value<T: TextEnum>(&mut self, name: &str, value: T) -> fmt::Result {
+        self.enum_name(name, value.name())
+    }
+    pub(crate) fn enum_name(&mut self, name: &str, value: &str) -> fmt::Result {
+        self.name(name)?;
+        write!(self.out, ": {}", value)
+    }
+    pub fn message<M: TextMessage>(&mut self, name: &str, msg: &M) -> fmt::Result {
+        self.name(name)?;
+        self.out.write_str(" {")?;
+        self.depth += 1;
+        self.first = true;
+        msg.write_fields(self)?;
+        let empty = self.first;
+        self.depth -= 1;
+        self.first = false;
+        if empty {
+            return self.out.write_char('}');
+        }
+        if self.pretty {
+            self.out.write_char('\n')?;
+            for _ in 0..self.depth {
+                self.out.write_str("  ")?;
+            }
+            self.out.write_char('}')
+        } else {
+            self.out.write_str(" }")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Ident(&'a str),
+    // Digits and letters after an optional minus, checked when converted.
+    Number(&'a str),
+    // Still quoted and escaped.
+    Str(&'a str),
+    Punct(char),
+    End,
+}
+
+/// Reads text format values, for generated `merge_text_field`s.
+pub struct TextParser<'a> {
+    src: &'a str,
+    pos: usize,
+    // Start of the last token read, where errors point.
+    token_start: usize,
+}
+
+impl<'a> TextParser<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            token_start: 0,
+        }
+    }
+
+    /// An error at the start of the last token read.
+    pub fn error(&self, msg: impl Into<String>) -> TextError {
+        let before = &self.src[..self.token_start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        TextError {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            msg: msg.into(),
+        }
+    }
+    pub fn unknown_field(&self, name: &str) -> TextError {
+        self.error(format!("Unknown field {}", name))
+    }
+
+    fn skip_space(&mut self) {
+        loop {
+            let rest = &self.src[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if !trimmed.starts_with('#') {
+                return;
+            }
+            self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    fn peek(&mut self) -> Result<Token<'a>, TextError> {
+        let (pos, token_start) = (self.pos, self.token_start);
+        let token = self.next();
+        self.pos = pos;
+        self.token_start = token_start;
+        token
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, TextError> {
+        self.skip_space();
+        self.token_start = self.pos;
+        let rest = &self.src[self.pos..];
+        let word_len = |s: &str| {
+            s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(s.len())
+        };
+        let token = match rest.chars().next() {
+            None => Token::End,
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => Token::Ident(&rest[..word_len(rest)]),
+            Some(c) if c.is_ascii_digit() => Token::Number(&rest[..word_len(rest)]),
+            Some('-') => Token::Number(&rest[..1 + word_len(&rest[1..])]),
+            Some(quote @ ('"' | '\'')) => {
+                let mut escaped = false;
+                let end = rest[1..].find(|c: char| {
+                    let end = !escaped && (c == quote || c == '\n');
+                    escaped = !escaped && c == '\\';
+                    end
+                });
+                match end {
+                    Some(end) if rest[1 + end..].starts_with(quote) => Token::Str(&rest[..end + 2]),
+                    _ => return Err(self.error("Unterminated string")),
+                }
+            }
+            Some(c) => Token::Punct(c),
+        };
+        self.pos += match token {
+            Token::Ident(s) | Token::Number(s) | Token::Str(s) => s.len(),
+            Token::Punct(c) => c.len_utf8(),
+            Token::End => 0,
+        };
+        Ok(token)
+    }
+
+    fn describe(token: Token) -> String {
+        match token {
+            Token::Ident(s) | Token::Number(s) | Token::Str(s) => s.to_string(),
+            Token::Punct(c) => c.to_string(),
+            Token::End => "end of input".to_string(),
+        }
+    }
+
+    fn expected(&self, token: Token, what: &str) -> TextError {
+        self.error(format!(
+            "Expected {}, found {}",
+            what,
+            Self::describe(token)
+        ))
+    }
+
+    // Fields up to `close`, or the end of the input at the top level.
+    fn merge_fields<M: TextMessage>(
+        &mut self,
+        msg: &mut M,
+        close: Option<char>,
+    ) -> Result<(), TextError> {
+        loop {
+            match self.next()? {
+                Token::End if close.is_none() => return Ok(()),
+                Token::Punct(c) if Some(c) == close => return Ok(()),
+                Token::Ident(name) => {
+                    let name_start = self.token_start;
+                    if self.peek()? == Token::Punct(':') {
+                        self.next()?;
+                    }
+                    // So unknown fields point at their name.
+                    self.token_start = name_start;
+                    msg.merge_text_field(name, self)?;
+                    if matches!(self.peek()?, Token::Punct(',' | ';')) {
+                        self.next()?;
+                    }
+                }
+                token => return Err(self.expected(token, "field name")),
+            }
+        }
+    }
+
+    /// Update `msg` with the fields of a `{ ... }` or `< ... >` block.
+    pub fn merge_message<M: TextMessage>(&mut self, msg: &mut M) -> Result<(), TextError> {
+        match self.next()? {
+            Token::Punct('{') => self.merge_fields(msg, Some('}')),
+            Token::Punct('<') => self.merge_fields(msg, Some('>')),
+            token => Err(self.expected(token, "{")),
+        }
+    }
+    pub fn message<M: TextMessage + Default>(&mut self) -> Result<M, TextError> {
+        let mut msg = M::default();
+        self.merge_message(&mut msg)?;
+        Ok(msg)
+    }
+
+    /// A decimal or `0x` hexadecimal integer.
+    pub fn int<T: TryFrom<i128>>(&mut self) -> Result<T, TextError> {
+        let token = self.next()?;
+        let Token::Number(s) = token else {
+            return Err(self.expected(token, "integer"));
+        };
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        let value = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+            Some(hex) => i128::from_str_radix(hex, 16),
+            None => digits.parse::<i128>(),
+        };
+        let value = value.map_err(|_| self.expected(token, "integer"))?;
+        T::try_from(if negative { -value } else { value })
+            .map_err(|_| self.error(format!("Integer {} out of range", s)))
+    }
+
+    /// A quoted string, adjacent ones are joined.
+    pub fn string(&mut self) -> Result<String, TextError> {
+        let token = self.next()?;
+        let Token::Str(quoted) = token else {
+            return Err(self.expected(token, "string"));
+        };
+        let mut bytes = Vec::new();
+        self.unescape(&quoted[1..quoted.len() - 1], &mut bytes)?;
+        while let Token::Str(quoted) = self.peek()? {
+            self.next()?;
+            self.unescape(&quoted[1..quoted.len() - 1], &mut bytes)?;
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("Invalid UTF-8 in string"))
+    }
+
+    fn unescape(&self, s: &str, out: &mut Vec<u8>) -> Result<(), TextError> {
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                continue;
+            }
+            let byte = match chars.next() {
+                Some('n') => b'\n',
+                Some('r') => b'\r',
+                Some('t') => b'\t',
+                Some(c @ ('"' | '\'' | '\\')) => c as u8,
+                Some('x') => {
+                    let hex = chars.as_str().get(..2).unwrap_or("");
+                    let byte = u8::from_str_radix(hex, 16)
+                        .map_err(|_| self.error("Invalid \\x escape in string"))?;
+                    chars.nth(1);
+                    byte
+                }
+                Some(c @ '0'..='7') => {
+                    let rest = chars.as_str();
+                    let len = rest
+                        .find(|c: char| !('0'..='7').contains(&c))
+                        .unwrap_or(rest.len())
+                        .min(2);
+                    let mut octal = String::from(c);
+                    octal.push_str(&rest[..len]);
+                    let byte = u8::from_str_radix(&octal, 8)
+                        .map_err(|_| self.error("Invalid octal escape in string"))?;
+                    for _ in 0..len {
+                        chars.next();
+                    }
+                    byte
+                }
+                _ => return Err(self.error("Invalid escape in string")),
+            };
+            out.push(byte);
+        }
+        Ok(())
+    }
+
+    /// The name of an enum value.
+    pub fn enum_value<T: TextEnum>(&mut self) -> Result<T, TextError> {
+        let token = self.next()?;
+        let Token::Ident(name) = token else {
+            return Err(self.expected(token, "enum value"));
+        };
+        T::from_name(name).ok_or_else(|| self.error(format!("Unknown enum value {}", name)))
+    }
+    /// The name of an enum value, or the number of one the schema doesn't
+    /// name.
+    pub(crate) fn enum_number(
+        &mut self,
+        from_name: impl FnOnce(&str) -> Option<i32>,
+    ) -> Result<i32, TextError> {
+        match self.peek()? {
+            Token::Number(_) => self.int(),
+            Token::Ident(name) => {
+                self.next()?;
+                from_name(name).ok_or_else(|| self.error(format!("Unknown enum value {}", name)))
+            }
+            token => {
+                self.next()?;
+                Err(self.expected(token, "enum value"))
+            }
+        }
+    }
+
+    /// The values of a repeated field, either one or a `[a, b]` list, each
+    /// read by `f`.
+    pub fn repeated(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<(), TextError>,
+    ) -> Result<(), TextError> {
+        if self.peek()? != Token::Punct('[') {
+            return f(self);
+        }
+        self.next()?;
+        if self.peek()? == Token::Punct(']') {
+            self.next()?;
+            return Ok(());
+        }
+        loop {
+            f(self)?;
+            match self.next()? {
+                Token::Punct(',') => continue,
+                Token::Punct(']') => return Ok(()),
+                token => return Err(self.expected(token, ", or ]")),
+            }
+        }
+    }
+}
+
+// A message with every kind of field, like generated code would write it.
+#[cfg(test)]
+#[derive(Debug, Default, PartialEq)]
+struct Sample {
+    small: i32,
+    name: String,
+    tags: Vec<String>,
+    big: Option<u64>,
+    nested: Option<Box<Sample>>,
+}
+
+#[cfg(test)]
+impl TextMessage for Sample {
+    fn write_fields(&self, w: &mut TextWriter) -> fmt::Result {
+        if self.small != 0 {
+            w.int("small", self.small)?;
+        }
+        if !self.name.is_empty() {
+            w.string("name", &self.name)?;
+        }
+        for v in &self.tags {
+            w.string("tags", v)?;
+        }
+        if let Some(v) = self.big {
+            w.int("big", v)?;
+        }
+        if let Some(v) = &self.nested {
+            w.message("nested", v.as_ref())?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(&mut self, name: &str, p: &mut TextParser) -> Result<(), TextError> {
+        match name {
+            "small" => self.small = p.int()?,
+            "name" => self.name = p.string()?,
+            "tags" => p.repeated(|p| {
+                self.tags.push(p.string()?);
+                Ok(())
+            })?,
+            "big" => self.big = Some(p.int()?),
+            "nested" => {
+                p.merge_message(self.nested.get_or_insert_with(Default::default).as_mut())?
+            }
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write(self, f)
+    }
+}
+
+#[test]
+fn test_text_round_trip() {
+    let msg = Sample {
+        small: -3,
+        name: "a \"b\"\n\u{1}é".into(),
+        tags: vec!["x".into(), "".into()],
+        big: Some(u64::MAX),
+        nested: Some(Box::new(Sample {
+            small: 1,
+            nested: Some(Box::default()),
+            ..Default::default()
+        })),
+    };
+    let line = msg.to_string();
+    assert_eq!(
+        line,
+        r#"small: -3 name: "a \"b\"\n\x01é" tags: "x" tags: "" big: 18446744073709551615 nested { small: 1 nested {} }"#
+    );
+    assert_eq!(parse::<Sample>(&line), Ok(msg));
+
+    let pretty = format!(
+        "{:#}",
+        parse::<Sample>("nested { small: 1 } small: 2").unwrap()
+    );
+    assert_eq!(pretty, "small: 2\nnested {\n  small: 1\n}");
+}
+
+#[test]
+fn test_text_syntax() {
+    let msg: Sample = parse(
+        "# comment\n small: 0x1f, name: 'a' \"\\101\\x42\"; tags: [\"x\", \"y\"]\n nested < big: 7 >",
+    )
+    .unwrap();
+    assert_eq!(msg.small, 31);
+    assert_eq!(msg.name, "aAB");
+    assert_eq!(msg.tags, ["x", "y"]);
+    assert_eq!(msg.nested.unwrap().big, Some(7));
+
+    let error = |s: &str| parse::<Sample>(s).unwrap_err().to_string();
+    assert_eq!(
+        error("small: 1\nsmall: 2147483648"),
+        "2:8: Integer 2147483648 out of range"
+    );
+    assert_eq!(error("other: 1"), "1:1: Unknown field other");
+    assert_eq!(
+        error("nested { small: 1"),
+        "1:18: Expected field name, found end of input"
+    );
+    assert_eq!(error("name: \"abc"), "1:7: Unterminated string");
+    assert_eq!(error("big: x"), "1:6: Expected integer, found x");
+}
@@ -0,0 +1,352 @@
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+/// Moves whole frames between two RPC peers.
+pub trait Transport {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()>;
+    /// Receive a single frame into `buf` and return its length. Waits at most
+    /// `timeout`, or forever if it is `None`.
+    fn recv(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize>;
+}
+
+impl<T: Transport + ?Sized> Transport for &mut T {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        (**self).send(frame)
+    }
+    fn recv(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        (**self).recv(buf, timeout)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        (**self).send(frame)
+    }
+    fn recv(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        (**self).recv(buf, timeout)
+    }
+}
+
+fn frame_too_large(len: usize, max: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Frame of {} bytes exceeds the limit of {} bytes", len, max),
+    )
+}
+
+/// One frame per datagram.
+///
+/// A transport created with [`UdpTransport::connect`] always talks to the same
+/// peer. One created with [`UdpTransport::bind`] sends to whoever sent the last
+/// received frame, which is what a server answering requests needs.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+    connected: bool,
+    mtu: usize,
+}
+
+impl UdpTransport {
+    /// Largest payload that fits an Ethernet frame without IP fragmentation.
+    pub const DEFAULT_MTU: usize = 1472;
+
+    pub fn new(socket: UdpSocket) -> Self {
+        let peer = socket.peer_addr().ok();
+        Self {
+            socket,
+            connected: peer.is_some(),
+            peer,
+            mtu: Self::DEFAULT_MTU,
+        }
+    }
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(Self::new(UdpSocket::bind(addr)?))
+    }
+    pub fn connect<A: ToSocketAddrs, B: ToSocketAddrs>(bind: A, peer: B) -> io::Result<Self> {
+        let socket = UdpSocket::bind(bind)?;
+        socket.connect(peer)?;
+        Ok(Self::new(socket))
+    }
+
+    /// Frames larger than `mtu` are rejected by [`Transport::send`].
+    pub fn set_mtu(&mut self, mtu: usize) {
+        self.mtu = mtu;
+    }
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        if frame.len() > self.mtu {
+            return Err(frame_too_large(frame.len(), self.mtu));
+        }
+        let sent = match self.peer {
+            Some(_) if self.connected => self.socket.send(frame)?,
+            Some(peer) => self.socket.send_to(frame, peer)?,
+            None => return Err(io::ErrorKind::NotConnected.into()),
+        };
+        if sent != frame.len() {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        Ok(())
+    }
+    fn recv(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        self.socket.set_read_timeout(timeout)?;
+        let (len, from) = self.socket.recv_from(buf)?;
+        if !self.connected {
+            self.peer = Some(from);
+        }
+        Ok(len)
+    }
+}
+
+/// A byte stream that [`StreamTransport`] can frame.
+pub trait FrameStream: Read + Write {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl FrameStream for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl FrameStream for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Frames prefixed with their length as a little endian `u32`.
+///
+/// A timeout in the middle of a frame leaves the stream out of sync, so the
+/// transport should be dropped after any receive error other than a timeout
+/// before the first byte.
+pub struct StreamTransport<S: FrameStream> {
+    stream: S,
+    max_frame_size: usize,
+}
+
+pub type TcpTransport = StreamTransport<TcpStream>;
+#[cfg(unix)]
+pub type UnixTransport = StreamTransport<UnixStream>;
+
+impl<S: FrameStream> StreamTransport<S> {
+    pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024;
+
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            max_frame_size: Self::DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+    /// Frames larger than `max` are rejected in both directions.
+    pub fn set_max_frame_size(&mut self, max: usize) {
+        self.max_frame_size = max;
+    }
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl TcpTransport {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        // Frames are written whole, don't hold them back.
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream))
+    }
+}
+
+#[cfg(unix)]
+impl UnixTransport {
+    pub fn connect<P: AsRef<std::path::Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(UnixStream::connect(path)?))
+    }
+}
+
+impl<S: FrameStream> Transport for StreamTransport<S> {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        if frame.len() > self.max_frame_size {
+            return Err(frame_too_large(frame.len(), self.max_frame_size));
+        }
+        let mut buf = Vec::with_capacity(4 + frame.len());
+        buf.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        buf.extend_from_slice(frame);
+        self.stream.write_all(&buf)?;
+        self.stream.flush()
+    }
+    fn recv(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        self.stream.set_read_timeout(timeout)?;
+        let mut len = [0u8; 4];
+        self.stream.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > self.max_frame_size || len > buf.len() {
+            return Err(frame_too_large(len, self.max_frame_size.min(buf.len())));
+        }
+        self.stream.read_exact(&mut buf[..len])?;
+        Ok(len)
+    }
+}
+
+/// In-memory transport, mostly for tests.
+pub struct ChannelTransport {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+}
+
+impl ChannelTransport {
+    /// Two transports connected to each other.
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, b_rx) = channel();
+        let (b_tx, a_rx) = channel();
+        (Self { tx: a_tx, rx: a_rx }, Self { tx: b_tx, rx: b_rx })
+    }
+}
+
+impl Transport for ChannelTransport {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.tx
+            .send(frame.to_vec())
+            .or(Err(io::ErrorKind::BrokenPipe.into()))
+    }
+    fn recv(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        let frame = match timeout {
+            Some(timeout) => self.rx.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => io::Error::from(io::ErrorKind::TimedOut),
+                RecvTimeoutError::Disconnected => io::ErrorKind::BrokenPipe.into(),
+            })?,
+            None => self
+                .rx
+                .recv()
+                .or(Err(io::Error::from(io::ErrorKind::BrokenPipe)))?,
+        };
+        if frame.len() > buf.len() {
+            return Err(frame_too_large(frame.len(), buf.len()));
+        }
+        buf[..frame.len()].copy_from_slice(&frame);
+        Ok(frame.len())
+    }
+}
+
+#[cfg(test)]
+const TEST_TIMEOUT: Option<Duration> = Some(Duration::from_secs(5));
+
+// Send a frame each way and check both arrive intact.
+#[cfg(test)]
+fn check_round_trip<A: Transport, B: Transport>(a: &mut A, b: &mut B) {
+    let mut buf = [0u8; 64];
+    a.send(b"ping").unwrap();
+    let len = b.recv(&mut buf, TEST_TIMEOUT).unwrap();
+    assert_eq!(&buf[..len], b"ping");
+    b.send(b"pong").unwrap();
+    let len = a.recv(&mut buf, TEST_TIMEOUT).unwrap();
+    assert_eq!(&buf[..len], b"pong");
+}
+
+#[test]
+fn test_udp_round_trip() {
+    let mut server = UdpTransport::bind("127.0.0.1:0").unwrap();
+    let mut client = UdpTransport::connect("127.0.0.1:0", server.local_addr().unwrap()).unwrap();
+    // The server replies to whoever sent the last frame.
+    assert_eq!(server.peer_addr(), None);
+    check_round_trip(&mut client, &mut server);
+    assert_eq!(server.peer_addr(), Some(client.local_addr().unwrap()));
+}
+
+#[test]
+fn test_udp_mtu() {
+    let server = UdpTransport::bind("127.0.0.1:0").unwrap();
+    let mut client = UdpTransport::connect("127.0.0.1:0", server.local_addr().unwrap()).unwrap();
+    client.set_mtu(8);
+    assert_eq!(
+        client.send(&[0u8; 9]).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    client.send(&[0u8; 8]).unwrap();
+}
+
+#[test]
+fn test_udp_unconnected_send() {
+    let mut server = UdpTransport::bind("127.0.0.1:0").unwrap();
+    assert_eq!(
+        server.send(b"hi").unwrap_err().kind(),
+        io::ErrorKind::NotConnected
+    );
+}
+
+#[test]
+fn test_udp_timeout() {
+    let mut server = UdpTransport::bind("127.0.0.1:0").unwrap();
+    let err = server
+        .recv(&mut [0u8; 8], Some(Duration::from_millis(10)))
+        .unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    ));
+}
+
+#[test]
+fn test_tcp_round_trip() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpTransport::connect(listener.local_addr().unwrap()).unwrap();
+    let mut server = TcpTransport::new(listener.accept().unwrap().0);
+    check_round_trip(&mut client, &mut server);
+}
+
+#[test]
+fn test_tcp_frame_too_large() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpTransport::connect(listener.local_addr().unwrap()).unwrap();
+    let mut server = TcpTransport::new(listener.accept().unwrap().0);
+    client.send(&[1u8; 16]).unwrap();
+    let err = server.recv(&mut [0u8; 8], TEST_TIMEOUT).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    client.set_max_frame_size(4);
+    let err = client.send(&[1u8; 5]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unix_round_trip() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let (mut a, mut b) = (UnixTransport::new(a), UnixTransport::new(b));
+    check_round_trip(&mut a, &mut b);
+}
+
+#[test]
+fn test_channel_round_trip() {
+    let (mut a, mut b) = ChannelTransport::pair();
+    check_round_trip(&mut a, &mut b);
+    let err = a
+        .recv(&mut [0u8; 8], Some(Duration::from_millis(1)))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    drop(b);
+    assert_eq!(a.send(b"hi").unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+}
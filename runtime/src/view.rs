@@ -0,0 +1,163 @@
+//! Borrowed views of encoded messages, what generated `XxxRef` types are
+//! built on. Nothing here allocates.
+
+use core::marker::PhantomData;
+
+use crate::rpc::ProtoMessage;
+use crate::wire::{DecodeError, Decoder, Value, Varint, Varints};
+
+/// Types a field decodes to in a borrowed view, without copying.
+pub trait FieldRef<'a>: Sized {
+    fn decode(field: u32, value: Value<'a>) -> Result<Self, DecodeError>;
+}
+
+impl<'a> FieldRef<'a> for &'a str {
+    fn decode(field: u32, value: Value<'a>) -> Result<Self, DecodeError> {
+        value.str(field)
+    }
+}
+
+impl<'a> FieldRef<'a> for &'a [u8] {
+    fn decode(field: u32, value: Value<'a>) -> Result<Self, DecodeError> {
+        value.bytes(field)
+    }
+}
+
+/// Lazily decodes the elements of a repeated string or message field.
+///
+/// Views check their whole buffer when they're created, so elements are
+/// yielded as is. Any that fail to decode anyway are skipped.
+#[derive(Debug, Clone)]
+pub struct Repeated<'a, T> {
+    fields: Decoder<'a>,
+    field: u32,
+    ty: PhantomData<T>,
+}
+
+impl<'a, T> Repeated<'a, T> {
+    /// The elements of field `field` in the encoded message `buf`.
+    pub fn new(buf: &'a [u8], field: u32) -> Self {
+        Self {
+            fields: Decoder::new(buf),
+            field,
+            ty: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: FieldRef<'a>> Iterator for Repeated<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.fields.by_ref().find_map(|res| match res {
+            Ok((field, value)) if field == self.field => T::decode(field, value).ok(),
+            _ => None,
+        })
+    }
+}
+
+/// Lazily decodes the elements of a repeated varint field, packed or not.
+///
+/// Like [`Repeated`], elements that fail to decode are skipped.
+#[derive(Debug, Clone)]
+pub struct RepeatedVarints<'a, T> {
+    fields: Decoder<'a>,
+    field: u32,
+    current: Option<Varints<'a, T>>,
+}
+
+impl<'a, T> RepeatedVarints<'a, T> {
+    /// The elements of field `field` in the encoded message `buf`.
+    pub fn new(buf: &'a [u8], field: u32) -> Self {
+        Self {
+            fields: Decoder::new(buf),
+            field,
+            current: None,
+        }
+    }
+}
+
+impl<T: Varint> Iterator for RepeatedVarints<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(Ok(value)) = self.current.as_mut().and_then(Iterator::next) {
+                return Some(value);
+            }
+            self.current = match self.fields.next()? {
+                Ok((field, value)) if field == self.field => value.varints(field).ok(),
+                _ => None,
+            };
+        }
+    }
+}
+
+/// Every occurrence of the singular message field `field` of `buf` merged
+/// into one, like decoding an owned message does, or `None` if it doesn't
+/// occur. Views only keep the last occurrence.
+///
+/// Like [`Repeated`], occurrences that fail to decode are skipped.
+pub fn merged<M: ProtoMessage + Default>(buf: &[u8], field: u32) -> Option<M> {
+    let mut merged: Option<M> = None;
+    for value in Repeated::<&[u8]>::new(buf, field) {
+        // A failed merge leaves the fields decoded before the error.
+        let _ = merged.get_or_insert_with(M::default).merge(value);
+    }
+    merged
+}
+
+#[test]
+fn test_repeated() {
+    use crate::wire::Encoder;
+
+    let mut buf = [0u8; 32];
+    let mut e = Encoder::new(&mut buf);
+    e.string(1, "a").unwrap();
+    e.packed(2, [1u32, 2].into_iter()).unwrap();
+    e.varint(3, 9u32).unwrap();
+    e.string(1, "b").unwrap();
+    e.varint(2, 3u32).unwrap();
+    let len = e.position();
+
+    let strings: Repeated<&str> = Repeated::new(&buf[..len], 1);
+    assert!(strings.eq(["a", "b"]));
+    let varints: RepeatedVarints<u32> = RepeatedVarints::new(&buf[..len], 2);
+    assert!(varints.eq([1, 2, 3]));
+    assert_eq!(Repeated::<&str>::new(&buf[..len], 4).next(), None);
+}
+
+#[test]
+fn test_merged() {
+    use crate::wire::{varint_field_size, EncodeError, Encoder};
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Pair(u32, u32);
+    impl ProtoMessage for Pair {
+        fn encode_fields(&self, e: &mut Encoder) -> Result<(), EncodeError> {
+            e.varint(1, self.0)?;
+            e.varint(2, self.1)
+        }
+        fn merge_field(&mut self, field: u32, value: Value) -> Result<(), DecodeError> {
+            match field {
+                1 => self.0 = value.varint(field)?,
+                2 => self.1 = value.varint(field)?,
+                _ => {}
+            }
+            Ok(())
+        }
+        fn serialized_size(&self) -> usize {
+            varint_field_size(1, self.0.into()) + varint_field_size(2, self.1.into())
+        }
+    }
+
+    let mut buf = [0u8; 32];
+    let mut e = Encoder::new(&mut buf);
+    e.bytes(1, &[0x08, 1, 0x10, 2]).unwrap();
+    e.varint(2, 9u32).unwrap();
+    e.bytes(1, &[0x10, 3]).unwrap();
+    let len = e.position();
+
+    assert_eq!(merged::<Pair>(&buf[..len], 1), Some(Pair(1, 3)));
+    assert_eq!(merged::<Pair>(&buf[..len], 3), None);
+}
@@ -0,0 +1,514 @@
+//! The protobuf binary wire format generated messages are encoded in.
+
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+
+/// Wire types of a field key.
+pub const VARINT: u8 = 0;
+pub const FIXED64: u8 = 1;
+pub const LEN: u8 = 2;
+pub const FIXED32: u8 = 5;
+
+/// Why a message couldn't be encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EncodeError {
+    /// The message doesn't fit in the buffer it's encoded into.
+    BufferTooSmall,
+    /// The message is larger than its schema allows.
+    MessageTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncodeError::BufferTooSmall => write!(f, "Buffer too small for the message"),
+            EncodeError::MessageTooLarge { size, max } => {
+                write!(
+                    f,
+                    "Message of {} bytes exceeds the {} byte limit",
+                    size, max
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+/// Why a message couldn't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The buffer ends in the middle of a field.
+    Truncated,
+    /// A varint longer than ten bytes.
+    InvalidVarint,
+    /// Groups and the unassigned wire types 6 and 7.
+    InvalidWireType(u8),
+    /// Field numbers start at one.
+    InvalidFieldNumber,
+    /// The wire type of a field doesn't match its type in the schema.
+    WrongWireType { field: u32 },
+    /// A string field holds invalid UTF-8.
+    InvalidUtf8 { field: u32 },
+    /// The message is larger than its schema allows.
+    MessageTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "Truncated message"),
+            DecodeError::InvalidVarint => write!(f, "Invalid varint"),
+            DecodeError::InvalidWireType(wire_type) => {
+                write!(f, "Invalid wire type {}", wire_type)
+            }
+            DecodeError::InvalidFieldNumber => write!(f, "Invalid field number 0"),
+            DecodeError::WrongWireType { field } => {
+                write!(f, "Wrong wire type for field {}", field)
+            }
+            DecodeError::InvalidUtf8 { field } => write!(f, "Invalid UTF-8 in field {}", field),
+            DecodeError::MessageTooLarge { size, max } => {
+                write!(
+                    f,
+                    "Message of {} bytes exceeds the {} byte limit",
+                    size, max
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Types encoded as varints, the integer types and generated enums.
+pub trait Varint: Copy {
+    /// `int32` values and enums are sign extended to 64 bits.
+    fn to_varint(self) -> u64;
+    /// Truncates like protobuf does, enums fall back to their default.
+    fn from_varint(value: u64) -> Self;
+}
+
+impl Varint for i32 {
+    fn to_varint(self) -> u64 {
+        self as i64 as u64
+    }
+    fn from_varint(value: u64) -> Self {
+        value as i32
+    }
+}
+
+impl Varint for i64 {
+    fn to_varint(self) -> u64 {
+        self as u64
+    }
+    fn from_varint(value: u64) -> Self {
+        value as i64
+    }
+}
+
+impl Varint for u32 {
+    fn to_varint(self) -> u64 {
+        self as u64
+    }
+    fn from_varint(value: u64) -> Self {
+        value as u32
+    }
+}
+
+impl Varint for u64 {
+    fn to_varint(self) -> u64 {
+        self
+    }
+    fn from_varint(value: u64) -> Self {
+        value
+    }
+}
+
+/// Encoded length of `value` as a varint.
+pub const fn varint_size(value: u64) -> usize {
+    // One byte per started group of 7 bits, at least one.
+    let bits = 64 - (value | 1).leading_zeros() as usize;
+    bits.div_ceil(7)
+}
+
+/// Encoded length of a key and varint value.
+pub const fn varint_field_size(field: u32, value: u64) -> usize {
+    varint_size((field as u64) << 3) + varint_size(value)
+}
+
+/// Encoded length of a key and length delimited value of `len` bytes.
+pub const fn len_field_size(field: u32, len: usize) -> usize {
+    varint_size((field as u64) << 3) + varint_size(len as u64) + len
+}
+
+/// Encoded length of a packed repeated field, nothing if it's empty.
+pub fn packed_field_size<T: Varint>(field: u32, values: impl Iterator<Item = T>) -> usize {
+    match values.map(|v| varint_size(v.to_varint())).sum() {
+        0 => 0,
+        len => len_field_size(field, len),
+    }
+}
+
+/// Encoded length of a key and `value`, whatever its wire type.
+pub fn value_field_size(field: u32, value: Value) -> usize {
+    match value {
+        Value::Varint(v) => varint_field_size(field, v),
+        Value::Fixed64(_) => varint_size((field as u64) << 3) + 8,
+        Value::Len(bytes) => len_field_size(field, bytes.len()),
+        Value::Fixed32(_) => varint_size((field as u64) << 3) + 4,
+    }
+}
+
+/// Writes protobuf encoded fields into a fixed buffer.
+pub struct Encoder<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+    /// Number of bytes written so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        let end = self.pos + bytes.len();
+        self.buf
+            .get_mut(self.pos..end)
+            .ok_or(EncodeError::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn raw_varint(&mut self, mut value: u64) -> Result<(), EncodeError> {
+        let mut bytes = [0u8; 10];
+        let mut len = 0;
+        while value >= 0x80 {
+            bytes[len] = value as u8 | 0x80;
+            value >>= 7;
+            len += 1;
+        }
+        bytes[len] = value as u8;
+        self.put(&bytes[..=len])
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) -> Result<(), EncodeError> {
+        self.raw_varint((field as u64) << 3 | wire_type as u64)
+    }
+
+    pub fn varint<T: Varint>(&mut self, field: u32, value: T) -> Result<(), EncodeError> {
+        self.key(field, VARINT)?;
+        self.raw_varint(value.to_varint())
+    }
+    pub fn bytes(&mut self, field: u32, value: &[u8]) -> Result<(), EncodeError> {
+        self.key(field, LEN)?;
+        self.raw_varint(value.len() as u64)?;
+        self.put(value)
+    }
+    pub fn string(&mut self, field: u32, value: &str) -> Result<(), EncodeError> {
+        self.bytes(field, value.as_bytes())
+    }
+    /// A packed repeated varint field, nothing if it's empty.
+    pub fn packed<T: Varint>(
+        &mut self,
+        field: u32,
+        values: impl Iterator<Item = T> + Clone,
+    ) -> Result<(), EncodeError> {
+        let len: usize = values.clone().map(|v| varint_size(v.to_varint())).sum();
+        if len == 0 {
+            return Ok(());
+        }
+        self.key(field, LEN)?;
+        self.raw_varint(len as u64)?;
+        for value in values {
+            self.raw_varint(value.to_varint())?;
+        }
+        Ok(())
+    }
+    /// Any field as it was decoded.
+    pub fn value(&mut self, field: u32, value: Value) -> Result<(), EncodeError> {
+        match value {
+            Value::Varint(v) => {
+                self.key(field, VARINT)?;
+                self.raw_varint(v)
+            }
+            Value::Fixed64(v) => {
+                self.key(field, FIXED64)?;
+                self.put(&v.to_le_bytes())
+            }
+            Value::Len(bytes) => self.bytes(field, bytes),
+            Value::Fixed32(v) => {
+                self.key(field, FIXED32)?;
+                self.put(&v.to_le_bytes())
+            }
+        }
+    }
+    /// Write back the fields a message didn't know when it was decoded.
+    pub fn unknown(&mut self, fields: &UnknownFields) -> Result<(), EncodeError> {
+        self.put(&fields.buf)
+    }
+    pub fn message<M: crate::rpc::ProtoMessage>(
+        &mut self,
+        field: u32,
+        msg: &M,
+    ) -> Result<(), EncodeError> {
+        self.key(field, LEN)?;
+        self.raw_varint(msg.serialized_size() as u64)?;
+        msg.encode_fields(self)
+    }
+}
+
+/// The value of one field, as far as the wire format tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Len(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl<'a> Value<'a> {
+    /// The value of varint field `field`.
+    pub fn varint<T: Varint>(self, field: u32) -> Result<T, DecodeError> {
+        match self {
+            Value::Varint(v) => Ok(T::from_varint(v)),
+            _ => Err(DecodeError::WrongWireType { field }),
+        }
+    }
+    /// The contents of length delimited field `field`.
+    pub fn bytes(self, field: u32) -> Result<&'a [u8], DecodeError> {
+        match self {
+            Value::Len(bytes) => Ok(bytes),
+            _ => Err(DecodeError::WrongWireType { field }),
+        }
+    }
+    pub fn str(self, field: u32) -> Result<&'a str, DecodeError> {
+        core::str::from_utf8(self.bytes(field)?).or(Err(DecodeError::InvalidUtf8 { field }))
+    }
+    /// The elements of repeated varint field `field`, packed or not.
+    pub fn varints<T: Varint>(self, field: u32) -> Result<Varints<'a, T>, DecodeError> {
+        let (single, packed) = match self {
+            Value::Varint(v) => (Some(v), &[][..]),
+            Value::Len(buf) => (None, buf),
+            _ => return Err(DecodeError::WrongWireType { field }),
+        };
+        Ok(Varints {
+            single,
+            packed,
+            ty: PhantomData,
+        })
+    }
+}
+
+/// Iterator over the elements of a repeated varint field.
+#[derive(Debug, Clone)]
+pub struct Varints<'a, T> {
+    single: Option<u64>,
+    packed: &'a [u8],
+    ty: PhantomData<T>,
+}
+
+impl<T: Varint> Iterator for Varints<'_, T> {
+    type Item = Result<T, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(v) = self.single.take() {
+            return Some(Ok(T::from_varint(v)));
+        }
+        if self.packed.is_empty() {
+            return None;
+        }
+        let res = read_varint(&mut self.packed);
+        if res.is_err() {
+            self.packed = &[];
+        }
+        Some(res.map(T::from_varint))
+    }
+}
+
+fn read_varint(buf: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0u64;
+    for (i, b) in buf.iter().enumerate().take(10) {
+        value |= ((b & 0x7f) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            *buf = &buf[i + 1..];
+            return Ok(value);
+        }
+    }
+    if buf.len() < 10 {
+        Err(DecodeError::Truncated)
+    } else {
+        Err(DecodeError::InvalidVarint)
+    }
+}
+
+/// Iterates over the `(field number, value)` pairs of an encoded message.
+#[derive(Debug, Clone)]
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.buf.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn field(&mut self) -> Result<(u32, Value<'a>), DecodeError> {
+        let key = read_varint(&mut self.buf)?;
+        let field = u32::try_from(key >> 3).or(Err(DecodeError::InvalidFieldNumber))?;
+        if field == 0 {
+            return Err(DecodeError::InvalidFieldNumber);
+        }
+        let value = match (key & 7) as u8 {
+            VARINT => Value::Varint(read_varint(&mut self.buf)?),
+            FIXED64 => Value::Fixed64(u64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+            LEN => {
+                let len = read_varint(&mut self.buf)?;
+                Value::Len(self.take(usize::try_from(len).or(Err(DecodeError::Truncated))?)?)
+            }
+            FIXED32 => Value::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+            wire_type => return Err(DecodeError::InvalidWireType(wire_type)),
+        };
+        Ok((field, value))
+    }
+}
+
+impl<'a> Iterator for Decoder<'a> {
+    type Item = Result<(u32, Value<'a>), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let res = self.field();
+        if res.is_err() {
+            // Nothing sensible follows a malformed field.
+            self.buf = &[];
+        }
+        Some(res)
+    }
+}
+
+/// Fields a message didn't know when it was decoded, kept encoded so that
+/// re-encoding the message doesn't lose what a newer schema added.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UnknownFields {
+    buf: Vec<u8>,
+}
+
+impl UnknownFields {
+    /// The fields of `buf` whose number isn't in `known`, for messages copied
+    /// out of a checked buffer. Decoding stops at the first malformed field.
+    pub fn filter(buf: &[u8], known: &[u32]) -> Self {
+        let mut fields = Self::default();
+        for (field, value) in Decoder::new(buf).map_while(Result::ok) {
+            if !known.contains(&field) {
+                fields.push(field, value);
+            }
+        }
+        fields
+    }
+    pub fn push(&mut self, field: u32, value: Value) {
+        let start = self.buf.len();
+        self.buf.resize(start + value_field_size(field, value), 0);
+        Encoder::new(&mut self.buf[start..])
+            .value(field, value)
+            .expect("value_field_size is exact");
+    }
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+    /// Encoded length of all fields.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+    pub fn clear(&mut self) {
+        self.buf.clear()
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+    pub fn iter(&self) -> Decoder<'_> {
+        Decoder::new(&self.buf)
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let mut buf = [0u8; 32];
+    let mut e = Encoder::new(&mut buf);
+    e.varint(1, 300).unwrap();
+    e.varint(2, -1i32).unwrap();
+    e.string(3, "hi").unwrap();
+    e.packed(4, [1u32, 128].into_iter()).unwrap();
+    e.packed(5, [0u64; 0].into_iter()).unwrap();
+    let len = e.position();
+    // Field 1 = 300 as in the protobuf encoding guide.
+    assert_eq!(buf[..3], [0x08, 0xac, 0x02]);
+    assert_eq!(len, 3 + 11 + 4 + 5);
+    assert_eq!(varint_field_size(2, u64::MAX), 11);
+    assert_eq!(packed_field_size(4, [1u32, 128].into_iter()), 5);
+
+    let fields: Vec<_> = Decoder::new(&buf[..len]).collect::<Result<_, _>>().unwrap();
+    assert_eq!(fields[0], (1, Value::Varint(300)));
+    assert_eq!(fields[1].1.varint(2), Ok(-1i32));
+    assert_eq!(fields[2].1.str(3), Ok("hi"));
+    assert_eq!(
+        fields[2].1.varint::<u64>(3),
+        Err(DecodeError::WrongWireType { field: 3 })
+    );
+    let packed: Vec<_> = fields[3].1.varints(4).unwrap().collect();
+    assert_eq!(packed, [Ok(1u32), Ok(128)]);
+
+    assert_eq!(
+        Decoder::new(&buf[..len - 1]).last(),
+        Some(Err(DecodeError::Truncated))
+    );
+    assert_eq!(
+        Decoder::new(&[0, 0]).next(),
+        Some(Err(DecodeError::InvalidFieldNumber))
+    );
+    assert_eq!(
+        Encoder::new(&mut [0; 2]).varint(1, 300u32),
+        Err(EncodeError::BufferTooSmall)
+    );
+}
+
+#[test]
+fn test_unknown_fields() {
+    let mut buf = [0u8; 32];
+    let mut e = Encoder::new(&mut buf);
+    e.varint(1, 7u32).unwrap();
+    e.value(2, Value::Fixed64(u64::MAX)).unwrap();
+    e.string(3, "new").unwrap();
+    e.value(4, Value::Fixed32(5)).unwrap();
+    let len = e.position();
+
+    let unknown = UnknownFields::filter(&buf[..len], &[1]);
+    assert_eq!(unknown.as_bytes(), &buf[2..len]);
+    let fields: Vec<_> = unknown.iter().collect::<Result<_, _>>().unwrap();
+    assert_eq!(fields[0], (2, Value::Fixed64(u64::MAX)));
+    assert_eq!(fields[2], (4, Value::Fixed32(5)));
+
+    let mut out = [0u8; 32];
+    let mut e = Encoder::new(&mut out);
+    e.varint(1, 7u32).unwrap();
+    e.unknown(&unknown).unwrap();
+    let out_len = e.position();
+    assert_eq!(out[..out_len], buf[..len]);
+}
@@ -0,0 +1,287 @@
+// Backend independent plumbing shared by the code generators.
+
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use tera::{Context, Tera};
+
+use crate::{
+    parser::ParseTree,
+    serializable_tree::{self, SerializeTree},
+};
+
+/// One parsed `.proto` file.
+pub struct Schema {
+    /// Where the schema was read from.
+    pub path: PathBuf,
+    /// Name of the schema in descriptors, what other files import it by: its
+    /// path relative to the include dir it was found in, with `/` separators.
+    pub name: String,
+    pub parse_tree: ParseTree,
+    pub serial_tree: SerializeTree,
+}
+
+impl Schema {
+    pub fn new(path: PathBuf, parse_tree: ParseTree) -> Self {
+        let serial_tree = SerializeTree::from_parse_tree(&parse_tree);
+        Self {
+            name: path.to_string_lossy().replace('\\', "/"),
+            path,
+            parse_tree,
+            serial_tree,
+        }
+    }
+
+    /// Name of the output file for this schema, the input file stem with
+    /// `extension`.
+    pub fn output_name(&self, extension: &str) -> PathBuf {
+        let mut name = PathBuf::from(self.path.file_stem().unwrap_or_default());
+        name.set_extension(extension);
+        name
+    }
+}
+
+/// All schemas of one protogen run.
+#[derive(Default)]
+pub struct SchemaSet {
+    pub schemas: Vec<Schema>,
+    /// Files imported by `schemas`, directly or not. Their types can be
+    /// referred to, but no code is generated for them.
+    pub imports: Vec<Schema>,
+}
+
+impl SchemaSet {
+    /// Every schema and import, each after the files it imports. The order
+    /// protoc hands files to plugins in.
+    pub fn dependency_order(&self) -> Vec<&Schema> {
+        fn visit<'a>(
+            schema: &'a Schema,
+            all: &[&'a Schema],
+            visited: &mut HashSet<&'a str>,
+            order: &mut Vec<&'a Schema>,
+        ) {
+            if !visited.insert(&schema.name) {
+                return;
+            }
+            for import in schema.parse_tree.imports.iter() {
+                if let Some(dep) = all.iter().find(|s| s.name == *import) {
+                    visit(dep, all, visited, order);
+                }
+            }
+            order.push(schema);
+        }
+        let all: Vec<_> = self.schemas.iter().chain(self.imports.iter()).collect();
+        let (mut visited, mut order) = (HashSet::new(), Vec::new());
+        for schema in all.iter() {
+            visit(schema, &all, &mut visited, &mut order);
+        }
+        order
+    }
+
+    /// [`Schema::output_name`] of every schema, failing if two of them would
+    /// write the same file, like `a/x.proto` and `b/x.proto`.
+    pub fn output_names(&self, extension: &str) -> std::io::Result<Vec<PathBuf>> {
+        let names: Vec<_> = self
+            .schemas
+            .iter()
+            .map(|s| s.output_name(extension))
+            .collect();
+        for (i, name) in names.iter().enumerate() {
+            if let Some(j) = names[..i].iter().position(|other| other == name) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "{} and {} would both be generated as {}, rename one of them",
+                        self.schemas[j].path.display(),
+                        self.schemas[i].path.display(),
+                        name.display()
+                    ),
+                ));
+            }
+        }
+        Ok(names)
+    }
+}
+
+/// A generated file, `path` is relative to the output directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+impl OutputFile {
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<()> {
+        let path = dir.join(&self.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &self.contents)
+    }
+}
+
+/// A backend turning schemas into source files of one language.
+pub trait CodeGenerator {
+    /// Selects the backend on the command line, as in `--out rust=src/gen`.
+    fn name(&self) -> &str;
+    /// Extension of the generated files, without the dot.
+    fn file_extension(&self) -> &'static str;
+    /// Options understood by [`CodeGenerator::set_option`], as
+    /// `(key, description)` pairs.
+    fn options(&self) -> &'static [(&'static str, &'static str)];
+    /// Set an option from `--opt name:key=value`, `value` is empty for flags.
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), String>;
+    fn generate(&self, schemas: &SchemaSet) -> std::io::Result<Vec<OutputFile>>;
+}
+
+/// Value of a boolean option, flags given without a value are on.
+pub fn parse_flag(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "" | "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!(
+            "Option {} expects true or false, got {}",
+            key, value
+        )),
+    }
+}
+
+/// The available backends, by name.
+#[derive(Default)]
+pub struct Registry {
+    generators: Vec<Box<dyn CodeGenerator>>,
+}
+
+impl Registry {
+    /// A registry holding the backends built into protogen.
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(Box::<crate::codegen_rust::RustCodeGen>::default());
+        registry.register(Box::<crate::codegen_csharp::CsharpCodeGen>::default());
+        registry
+    }
+
+    /// Add a backend, replacing any registered under the same name.
+    pub fn register(&mut self, generator: Box<dyn CodeGenerator>) {
+        self.generators.retain(|g| g.name() != generator.name());
+        self.generators.push(generator);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn CodeGenerator> {
+        self.generators
+            .iter()
+            .find(|g| g.name() == name)
+            .map(|g| g.as_ref())
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Box<dyn CodeGenerator>> {
+        self.generators.iter_mut().find(|g| g.name() == name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.generators.iter().map(|g| g.name())
+    }
+}
+
+/// Names of the templates a template based backend renders a schema with.
+pub struct TemplateSet {
+    pub builtin: &'static str,
+    pub message: &'static str,
+    pub enum_: &'static str,
+    pub service: &'static str,
+}
+
+/// Render the builtins followed by every message, enum and service of `serial`.
+pub fn render_schema<W: Write>(
+    tera: &Tera,
+    templates: &TemplateSet,
+    ctx: &Context,
+    serial: &SerializeTree,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let render = |name: &str, ctx: &Context, writer: &mut W| {
+        tera.render_to(name, ctx, writer)
+            .map_err(std::io::Error::other)
+    };
+    render(templates.builtin, ctx, writer)?;
+    for msg in serial.messages.iter() {
+        render_msg(tera, templates, ctx.clone(), writer, msg)?;
+    }
+    for enum_ in serial.enums.iter() {
+        let mut ctx = ctx.clone();
+        ctx.insert("enum", enum_);
+        render(templates.enum_, &ctx, writer)?;
+    }
+    for service in serial.services.iter() {
+        let mut ctx = ctx.clone();
+        ctx.insert("service", service);
+        render(templates.service, &ctx, writer)?;
+    }
+    Ok(())
+}
+
+// Nested messages and enums come first, tera can't recurse.
+fn render_msg<W: Write>(
+    tera: &Tera,
+    templates: &TemplateSet,
+    mut ctx: Context,
+    writer: &mut W,
+    message: &serializable_tree::Message,
+) -> std::io::Result<()> {
+    for msg in message.messages.iter() {
+        render_msg(tera, templates, ctx.clone(), writer, msg)?;
+    }
+    for enum_ in message.enums.iter() {
+        let mut ctx = ctx.clone();
+        ctx.insert("enum", enum_);
+        tera.render_to(templates.enum_, &ctx, &mut *writer)
+            .map_err(std::io::Error::other)?;
+    }
+    ctx.insert("message", message);
+    tera.render_to(templates.message, &ctx, writer)
+        .map_err(std::io::Error::other)
+}
+
+#[test]
+fn test_registry() {
+    let mut registry = Registry::with_builtin();
+    assert_eq!(registry.names().collect::<Vec<_>>(), ["rust", "csharp"]);
+    assert_eq!(registry.get("rust").unwrap().file_extension(), "rs");
+    assert!(registry.get("go").is_none());
+
+    let rust = registry.get_mut("rust").unwrap();
+    rust.set_option("async", "").unwrap();
+    assert!(rust.set_option("no_such_option", "").is_err());
+
+    let text = "syntax = \"proto3\"; message A { int32 a = 1; }";
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
+    let schemas = SchemaSet {
+        schemas: vec![Schema::new(PathBuf::from("dir/a.proto"), tree)],
+        ..Default::default()
+    };
+    let files = registry.get("csharp").unwrap().generate(&schemas).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, PathBuf::from("a.cs"));
+    assert!(files[0].contents.contains("public struct A"));
+
+    let parse = || crate::parser::Parser::new(text.chars()).parse().unwrap();
+    let schemas = SchemaSet {
+        schemas: vec![
+            Schema::new(PathBuf::from("a/x.proto"), parse()),
+            Schema::new(PathBuf::from("b/x.proto"), parse()),
+        ],
+        ..Default::default()
+    };
+    let err = registry
+        .get("rust")
+        .unwrap()
+        .generate(&schemas)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "a/x.proto and b/x.proto would both be generated as x.rs, rename one of them"
+    );
+}
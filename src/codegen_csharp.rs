@@ -1,130 +1,346 @@
 use convert_case::{Case, Casing};
-use std::{
-    collections::HashMap,
-    io::{BufWriter, Write},
-};
-use tera::{to_value, Context, Tera, Value};
+use serde::Serialize;
+use std::{collections::HashMap, io::Write, path::PathBuf};
+use tera::{to_value, Value};
 
 use crate::{
-    parser::{FieldType, Message, ParseError, ParseTree, Parser, Service},
-    serializable_tree::{self, SerializeTree},
+    codegen::{self, CodeGenerator, OutputFile, SchemaSet, TemplateSet},
+    serializable_tree::SerializeTree,
+    templates::{self, Template},
 };
 
+/// Reserved keywords, contextual keywords are fine as identifiers.
+pub(crate) const KEYWORDS: &[&str] = &[
+    "abstract",
+    "as",
+    "base",
+    "bool",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "checked",
+    "class",
+    "const",
+    "continue",
+    "decimal",
+    "default",
+    "delegate",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "event",
+    "explicit",
+    "extern",
+    "false",
+    "finally",
+    "fixed",
+    "float",
+    "for",
+    "foreach",
+    "goto",
+    "if",
+    "implicit",
+    "in",
+    "int",
+    "interface",
+    "internal",
+    "is",
+    "lock",
+    "long",
+    "namespace",
+    "new",
+    "null",
+    "object",
+    "operator",
+    "out",
+    "override",
+    "params",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "ref",
+    "return",
+    "sbyte",
+    "sealed",
+    "short",
+    "sizeof",
+    "stackalloc",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "uint",
+    "ulong",
+    "unchecked",
+    "unsafe",
+    "ushort",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+// Verbatim identifiers (`@class`) may use any keyword. Applied after casing,
+// which can turn a name like `Int` into the keyword `int`.
+fn escape(ident: String) -> String {
+    if KEYWORDS.contains(&ident.as_str()) {
+        format!("@{}", ident)
+    } else {
+        ident
+    }
+}
+
+// Qualified names of nested types (`Outer.Inner`) flatten into one name.
 fn fmt_struct(s: &str) -> String {
-    s.to_case(Case::UpperCamel)
+    escape(
+        s.split('.')
+            .map(|part| part.to_case(Case::UpperCamel))
+            .collect(),
+    )
 }
 
+// Fields become properties, which are PascalCase in C#.
 fn fmt_field(s: &str) -> String {
-    s.to_case(Case::Snake)
-}
-fn fmt_func(s: &str) -> String {
-    s.to_case(Case::Snake)
+    escape(s.to_case(Case::UpperCamel))
 }
 
-fn tera_func(args: &HashMap<String, Value>) -> tera::Result<tera::Value> {
-    Ok(to_value(args.get(&String::from("name")).unwrap())?)
+fn fmt_param(s: &str) -> String {
+    escape(s.to_case(Case::Camel))
 }
 
-fn render_msg<W: Write>(
-    tera: &mut Tera,
-    mut ctx: Context,
-    writer: &mut W,
-    message: &serializable_tree::Message,
-) {
-    // Recurse
-    for msg in message.messages.iter() {
-        render_msg(tera, ctx.clone(), writer, msg);
+fn fmt_type(s: &str, optional: bool, repeated: bool) -> String {
+    let ty = match s {
+        "int32" => "Int32".into(),
+        "int64" => "Int64".into(),
+        "uint32" => "UInt32".into(),
+        "uint64" => "UInt64".into(),
+        "string" => "string".into(),
+        _ => fmt_struct(s),
+    };
+    if repeated {
+        format!("List<{}>", ty)
+    } else if optional {
+        format!("{}?", ty)
+    } else {
+        ty
     }
-    for enum_ in message.enums.iter() {
-        render_enum(tera, ctx.clone(), writer, enum_);
-    }
-    ctx.insert("message", message);
-    tera.render_to("gen-message.cs.tera", &ctx, writer).unwrap()
-}
-fn render_enum<W: Write>(
-    tera: &mut Tera,
-    mut ctx: Context,
-    writer: &mut W,
-    enum_: &serializable_tree::Enum,
-) {
-    ctx.insert("enum", enum_);
-    tera.render_to("gen-enum.cs.tera", &ctx, writer).unwrap()
-}
-fn render_service<W: Write>(
-    tera: &mut Tera,
-    mut ctx: Context,
-    writer: &mut W,
-    service: &serializable_tree::Service,
-) {
-    ctx.insert("service", service);
-    tera.render_to("gen-service.cs.tera", &ctx, writer).unwrap()
-}
-
-pub struct CsharpCodeGen;
+}
+
+fn name_arg<'a>(func: &str, args: &'a HashMap<String, Value>) -> tera::Result<&'a str> {
+    args.get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| tera::Error::msg(format!("{} expects a string `name`", func)))
+}
+
+fn flag_arg(args: &HashMap<String, Value>, flag: &str) -> bool {
+    args.get(flag).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn tera_fmt_struct(args: &HashMap<String, Value>) -> tera::Result<tera::Value> {
+    Ok(to_value(fmt_struct(name_arg("fmt_struct", args)?))?)
+}
+
+fn tera_fmt_var(args: &HashMap<String, Value>) -> tera::Result<tera::Value> {
+    Ok(to_value(fmt_field(name_arg("fmt_var", args)?))?)
+}
+
+fn tera_fmt_param(args: &HashMap<String, Value>) -> tera::Result<tera::Value> {
+    Ok(to_value(fmt_param(name_arg("fmt_param", args)?))?)
+}
+
+/// `fmt_type(name=field.ftype, optional=field.optional, repeated=field.repeated)`,
+/// the flags default to false.
+fn tera_fmt_type(args: &HashMap<String, Value>) -> tera::Result<tera::Value> {
+    let name = name_arg("fmt_type", args)?;
+    Ok(to_value(fmt_type(
+        name,
+        flag_arg(args, "optional"),
+        flag_arg(args, "repeated"),
+    ))?)
+}
+
+/// Generator options.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CsharpOptions {
+    /// Generate `Create` and `WithX` methods for every message.
+    pub builders: bool,
+    /// Leave out the `UnknownFields` property keeping fields of newer schema
+    /// versions.
+    pub drop_unknown_fields: bool,
+    /// Replaces built-in templates with files of the same name.
+    #[serde(skip)]
+    pub template_dir: Option<PathBuf>,
+}
+
+const TEMPLATES: &[Template] = &[
+    (
+        "gen-builtin.cs",
+        include_str!("../templates/csharp/gen-builtin.cs"),
+    ),
+    (
+        "gen-enum.cs.tera",
+        include_str!("../templates/csharp/gen-enum.cs.tera"),
+    ),
+    (
+        "gen-message.cs.tera",
+        include_str!("../templates/csharp/gen-message.cs.tera"),
+    ),
+    (
+        "gen-service.cs.tera",
+        include_str!("../templates/csharp/gen-service.cs.tera"),
+    ),
+];
+
+const TEMPLATE_SET: TemplateSet = TemplateSet {
+    builtin: "gen-builtin.cs",
+    message: "gen-message.cs.tera",
+    enum_: "gen-enum.cs.tera",
+    service: "gen-service.cs.tera",
+};
+
+#[derive(Default)]
+pub struct CsharpCodeGen {
+    pub options: CsharpOptions,
+}
+
 impl CsharpCodeGen {
     pub fn gen<W: Write>(
         writer: &mut W,
-        parse: &ParseTree,
         serial: &SerializeTree,
+        options: &CsharpOptions,
     ) -> Result<(), std::io::Error> {
-        // Use globbing
-        let mut tera = match tera::Tera::new("templates/csharp/**") {
-            Ok(t) => t,
-            Err(e) => {
-                println!("Parsing error(s): {}", e);
-                ::std::process::exit(1);
-            }
+        let mut tera = templates::load(TEMPLATES, options.template_dir.as_deref())?;
+        tera.register_function("fmt_struct", tera_fmt_struct);
+        tera.register_function("fmt_var", tera_fmt_var);
+        tera.register_function("fmt_type", tera_fmt_type);
+        tera.register_function("fmt_param", tera_fmt_param);
+        let mut dropped;
+        let serial = if options.drop_unknown_fields {
+            dropped = serial.clone();
+            dropped.drop_unknown_fields();
+            &dropped
+        } else {
+            serial
         };
-        tera.register_function("fmt_struct", tera_func); // TODO:
-        tera.register_function("fmt_var", tera_func); // TODO:
-        tera.register_function("fmt_type", tera_func); // TODO:
-        let ctx = tera::Context::new();
-        tera.render_to("gen-builtin.cs", &ctx, &mut *writer)
-            .unwrap();
-        for msg in serial.messages.iter() {
-            // Render all messages recursively (tera doesn't support)
-            render_msg(&mut tera, ctx.clone(), writer, msg);
-        }
-        for enum_ in serial.enums.iter() {
-            render_enum(&mut tera, ctx.clone(), writer, enum_);
-        }
-        for service in serial.services.iter() {
-            render_service(&mut tera, ctx.clone(), writer, service);
+        let mut ctx = tera::Context::new();
+        ctx.insert("options", options);
+        ctx.insert("max_frame_size", &serial.max_frame_size);
+        codegen::render_schema(&tera, &TEMPLATE_SET, &ctx, serial, writer)
+    }
+}
+
+impl CodeGenerator for CsharpCodeGen {
+    fn name(&self) -> &'static str {
+        "csharp"
+    }
+    fn file_extension(&self) -> &'static str {
+        "cs"
+    }
+    fn options(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            (
+                "builders",
+                "Generate Create and WithX methods for every message",
+            ),
+            (
+                "drop_unknown_fields",
+                "Discard fields of newer schema versions when decoding",
+            ),
+            (
+                "template_dir",
+                "Replace built-in templates with files of the same name",
+            ),
+        ]
+    }
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "builders" => self.options.builders = codegen::parse_flag(key, value)?,
+            "drop_unknown_fields" => {
+                self.options.drop_unknown_fields = codegen::parse_flag(key, value)?
+            }
+            "template_dir" => self.options.template_dir = Some(value.into()),
+            _ => return Err(format!("Unknown csharp option {}", key)),
         }
         Ok(())
     }
+    fn generate(&self, schemas: &SchemaSet) -> std::io::Result<Vec<OutputFile>> {
+        let names = schemas.output_names(self.file_extension())?;
+        let mut files = Vec::new();
+        for (schema, path) in schemas.schemas.iter().zip(names) {
+            let mut contents = Vec::new();
+            Self::gen(&mut contents, &schema.serial_tree, &self.options)?;
+            files.push(OutputFile {
+                path,
+                contents: String::from_utf8(contents).map_err(std::io::Error::other)?,
+            });
+        }
+        Ok(files)
+    }
 }
 
 #[test]
-fn test_tera() -> Result<(), ParseError> {
-    let text = "
-    syntax = \"proto3\";
-    message Input {
-        KeyCode key_code = 0;
-    }
-    enum KeyCode {
-        Spacebar = 0;
-        Enter = 1;
-    }
-    message Empty {}
-    service Backend {
-        rpc InputEvent(Input) returns (Empty);
+fn test_fmt() {
+    assert_eq!(fmt_struct("Outer.inner_msg"), "OuterInnerMsg");
+    assert_eq!(fmt_field("key_code"), "KeyCode");
+    assert_eq!(fmt_param("key_code"), "keyCode");
+    assert_eq!(fmt_param("class"), "@class");
+    assert_eq!(fmt_param("Int"), "@int");
+    assert_eq!(fmt_field("class"), "Class");
+    assert_eq!(fmt_type("uint64", false, false), "UInt64");
+    assert_eq!(fmt_type("int32", true, false), "Int32?");
+    assert_eq!(fmt_type("string", false, true), "List<string>");
+}
+
+#[test]
+fn test_keywords() {
+    assert_eq!(escape("class".into()), "@class");
+    assert_eq!(escape("Class".into()), "Class");
+    // C# can't be compiled here, check that the properties got PascalCased instead.
+    let text = crate::codegen_rust::keyword_schema();
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
+    let serial = SerializeTree::from_parse_tree(&tree);
+    let mut w = vec![];
+    CsharpCodeGen::gen(&mut w, &serial, &CsharpOptions::default()).unwrap();
+    let out = String::from_utf8_lossy(&w);
+    for name in ["class", "event", "base"] {
+        let property = fmt_field(name);
+        assert!(out.contains(&format!("public Int32 {} {{get; set;}}", property)));
     }
-    ";
-    let mut p = Parser::new(text.chars());
-    let tree = p.parse()?;
-    let mut w = BufWriter::new(vec![]);
+    assert!(out.contains("public enum Type : UInt32 {\n         Self = 0,  Match = 1, "));
+
+    // Builder parameters keep the case of the schema and need escaping.
+    let text = "syntax = \"proto3\";
+message class {
+    int32 event = 1 [(required) = true];
+    int32 Int = 2 [(required) = true];
+    kind base = 3;
+}
+enum kind { class = 0; Int = 1; }";
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
     let serial = SerializeTree::from_parse_tree(&tree);
-    CsharpCodeGen::gen(&mut w, &tree, &serial);
-
-    println!("{}", String::from_utf8_lossy(w.buffer()));
-    //    assert_eq!(
-    //        String::from_utf8_lossy(w.buffer()),
-    //        "pub struct TestMessage {
-    //}
-    //pub fn doer(arg: TestMessage) -> TestMessage { todo!() }
-    //"
-    // );
-
-    Ok(())
+    let mut w = vec![];
+    let options = CsharpOptions {
+        builders: true,
+        ..Default::default()
+    };
+    CsharpCodeGen::gen(&mut w, &serial, &options).unwrap();
+    let out = String::from_utf8_lossy(&w);
+    assert!(out.contains("public struct Class : IProtoMessage"));
+    assert!(out.contains("public static Class Create(Int32 @event, Int32 @int) {"));
+    assert!(out.contains("Event = @event,"));
+    assert!(out.contains("Int = @int,"));
+    assert!(out.contains("public Class WithBase(Kind value) {"));
+    assert!(out.contains("public enum Kind : UInt32 {\n         Class = 0,  Int = 1, "));
+    assert!(out.contains("Kind.Class => \"class\","));
+    assert!(out.contains("\"Int\" => Kind.Int,"));
 }
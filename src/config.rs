@@ -0,0 +1,527 @@
+// Library entry point: configure backends and compile schemas, e.g. from a
+// build script.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    codegen::{Registry, Schema, SchemaSet},
+    descriptor,
+    parser::{ParseError, Parser},
+    plugin::PluginGenerator,
+};
+
+/// Anything that stops schemas from being compiled.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading a schema or writing output failed.
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: ParseError,
+    },
+    /// A backend failed to generate code.
+    Generate {
+        lang: String,
+        error: std::io::Error,
+    },
+    /// Invalid configuration, like an unknown language or option.
+    Config(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            Error::Parse { path, error } => write!(f, "{}: {}", path.display(), error),
+            Error::Generate { lang, error } => write!(f, "{} backend: {}", lang, error),
+            Error::Config(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { error, .. } | Error::Generate { error, .. } => Some(error),
+            Error::Parse { error, .. } => Some(error),
+            Error::Config(_) => None,
+        }
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> Error + '_ {
+    move |error| Error::Io {
+        path: path.to_path_buf(),
+        error,
+    }
+}
+
+/// Settings of one protogen run, what the command line flags map to.
+///
+/// ```no_run
+/// // build.rs
+/// fn main() -> Result<(), protogen::Error> {
+///     protogen::Config::new()
+///         .include("protos")
+///         .rust_out(std::env::var("OUT_DIR").unwrap())
+///         .compile(&["hello.proto"])
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    includes: Vec<PathBuf>,
+    outputs: Vec<(String, PathBuf)>,
+    options: Vec<(String, String, String)>,
+    plugins: Vec<(String, PathBuf)>,
+    template_dir: Option<PathBuf>,
+    descriptor_set_out: Option<PathBuf>,
+    include_source_info: bool,
+    emit_rerun_if_changed: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            includes: Vec::new(),
+            outputs: Vec::new(),
+            options: Vec::new(),
+            plugins: Vec::new(),
+            template_dir: None,
+            descriptor_set_out: None,
+            include_source_info: false,
+            emit_rerun_if_changed: true,
+        }
+    }
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directory to look up schemas and imports in, before the current
+    /// directory.
+    pub fn include(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.includes.push(dir.into());
+        self
+    }
+    /// Generate code with the backend named `lang` into `dir`.
+    pub fn out(&mut self, lang: impl Into<String>, dir: impl Into<PathBuf>) -> &mut Self {
+        self.outputs.push((lang.into(), dir.into()));
+        self
+    }
+    pub fn rust_out(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.out("rust", dir)
+    }
+    pub fn csharp_out(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.out("csharp", dir)
+    }
+    /// Set a backend option, `value` is empty for flags.
+    pub fn opt(
+        &mut self,
+        lang: impl Into<String>,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> &mut Self {
+        self.options.push((lang.into(), key.into(), value.into()));
+        self
+    }
+    /// Generate async Rust services.
+    pub fn rust_async(&mut self, enable: bool) -> &mut Self {
+        self.opt("rust", "async", enable.to_string())
+    }
+    /// Generate Rust that only needs `core` and `alloc`, without services or
+    /// clients.
+    pub fn rust_no_std(&mut self, enable: bool) -> &mut Self {
+        self.opt("rust", "no_std", enable.to_string())
+    }
+    /// Generate message builders in Rust and C#.
+    pub fn builders(&mut self, enable: bool) -> &mut Self {
+        self.opt("rust", "builders", enable.to_string());
+        self.opt("csharp", "builders", enable.to_string())
+    }
+    /// Discard fields of newer schema versions when decoding in Rust and C#,
+    /// instead of keeping them to be written back on encode.
+    pub fn drop_unknown_fields(&mut self, enable: bool) -> &mut Self {
+        self.opt("rust", "drop_unknown_fields", enable.to_string());
+        self.opt("csharp", "drop_unknown_fields", enable.to_string())
+    }
+    /// Run the protoc compatible plugin at `path` as backend `name`.
+    pub fn plugin(&mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> &mut Self {
+        self.plugins.push((name.into(), path.into()));
+        self
+    }
+    /// Directory of templates replacing the built-in ones with the same name.
+    pub fn template_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.template_dir = Some(dir.into());
+        self
+    }
+    /// Also write the schemas as a binary `google.protobuf.FileDescriptorSet`.
+    pub fn descriptor_set_out(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.descriptor_set_out = Some(path.into());
+        self
+    }
+    pub fn include_source_info(&mut self, enable: bool) -> &mut Self {
+        self.include_source_info = enable;
+        self
+    }
+    /// Print `cargo:rerun-if-changed` for every input, on by default for use
+    /// in build scripts.
+    pub fn emit_rerun_if_changed(&mut self, enable: bool) -> &mut Self {
+        self.emit_rerun_if_changed = enable;
+        self
+    }
+
+    /// Parse `protos` and generate code for every configured output.
+    pub fn compile(&self, protos: &[impl AsRef<Path>]) -> Result<(), Error> {
+        // Catch configuration mistakes before parsing anything.
+        let registry = self.registry()?;
+        let schemas = self.load(protos)?;
+        self.generate_with(&registry, &schemas)
+    }
+
+    /// Parse `protos`, files or directories searched for `.proto` files.
+    /// Like imports, they are looked up in the include dirs first.
+    ///
+    /// The files they import are loaded too. Like protoc, a file can refer to
+    /// the types of the files it imports directly.
+    pub fn load(&self, protos: &[impl AsRef<Path>]) -> Result<SchemaSet, Error> {
+        // Every file read, `protos` first, by path. `deps` are the indices of
+        // the files each one imports.
+        let (mut paths, mut trees, mut deps) = (Vec::new(), Vec::new(), Vec::new());
+        let mut seen = HashMap::new();
+        for proto in protos.iter() {
+            let proto = self.find(proto.as_ref(), proto.as_ref())?;
+            self.rerun_if_changed(&proto);
+            for entry in walkdir::WalkDir::new(&proto) {
+                let entry = entry.map_err(|e| Error::Io {
+                    path: proto.clone(),
+                    error: e.into(),
+                })?;
+                if (entry.file_type().is_file() || entry.file_type().is_symlink())
+                    && entry.path().exists()
+                    && entry.path().extension().is_some_and(|ext| ext == "proto")
+                {
+                    let path = entry.path().to_path_buf();
+                    if !seen.contains_key(&path) {
+                        seen.insert(path.clone(), paths.len());
+                        trees.push(parse(&path)?);
+                        paths.push(path);
+                    }
+                }
+            }
+        }
+        let generated = paths.len();
+        // Imports aren't generated, but changing them should rerun the build.
+        let mut next = 0;
+        while next < paths.len() {
+            let mut imported = Vec::new();
+            for import in trees[next].imports.clone() {
+                let path = self.find(Path::new(&import), &paths[next])?;
+                let index = match seen.get(&path) {
+                    Some(&index) => index,
+                    None => {
+                        self.rerun_if_changed(&path);
+                        seen.insert(path.clone(), paths.len());
+                        trees.push(parse(&path)?);
+                        paths.push(path);
+                        paths.len() - 1
+                    }
+                };
+                imported.push(index);
+            }
+            deps.push(imported);
+            next += 1;
+        }
+        for (i, imported) in deps.iter().enumerate() {
+            let mut tree = std::mem::take(&mut trees[i]);
+            let imports: Vec<_> = imported.iter().map(|&j| &trees[j]).collect();
+            tree.resolve_types(&imports).map_err(|error| Error::Parse {
+                path: paths[i].clone(),
+                error,
+            })?;
+            trees[i] = tree;
+        }
+        let mut schemas: Vec<_> = paths
+            .into_iter()
+            .zip(trees)
+            .map(|(path, tree)| {
+                let name = self.proto_name(&path);
+                Schema {
+                    name,
+                    ..Schema::new(path, tree)
+                }
+            })
+            .collect();
+        let imports = schemas.split_off(generated);
+        Ok(SchemaSet { schemas, imports })
+    }
+
+    /// Generate code for `schemas` with every configured output.
+    pub fn generate(&self, schemas: &SchemaSet) -> Result<(), Error> {
+        self.generate_with(&self.registry()?, schemas)
+    }
+
+    fn generate_with(&self, registry: &Registry, schemas: &SchemaSet) -> Result<(), Error> {
+        if let Some(ref path) = self.descriptor_set_out {
+            // Imports too, so tools can link the set.
+            let files = schemas
+                .dependency_order()
+                .into_iter()
+                .map(|s| (s.name.as_str(), &s.parse_tree));
+            let set = descriptor::file_descriptor_set(files, self.include_source_info);
+            std::fs::write(path, set).map_err(io_error(path))?;
+        }
+        for (lang, dir) in self.outputs.iter() {
+            let generator = registry.get(lang).unwrap();
+            let files = generator
+                .generate(schemas)
+                .map_err(|error| Error::Generate {
+                    lang: lang.clone(),
+                    error,
+                })?;
+            for file in files {
+                file.write_to(dir)
+                    .map_err(io_error(&dir.join(&file.path)))?;
+            }
+        }
+        Ok(())
+    }
+
+    // The backends with plugins and options applied, checking every output
+    // has one.
+    fn registry(&self) -> Result<Registry, Error> {
+        let mut registry = Registry::with_builtin();
+        for (name, path) in self.plugins.iter() {
+            registry.register(Box::new(PluginGenerator::new(name.as_str(), path)));
+        }
+        for (lang, dir) in self.outputs.iter() {
+            if registry.get(lang).is_none() {
+                let names: Vec<_> = registry.names().collect();
+                return Err(Error::Config(format!(
+                    "Unknown language {}, expected one of {}",
+                    lang,
+                    names.join(", ")
+                )));
+            }
+            if !dir.is_dir() {
+                return Err(Error::Config(format!(
+                    "{} output dir invalid {}",
+                    lang,
+                    dir.display()
+                )));
+            }
+        }
+        if let Some(ref dir) = self.template_dir {
+            if !dir.is_dir() {
+                return Err(Error::Config(format!(
+                    "Template dir invalid {}",
+                    dir.display()
+                )));
+            }
+            self.rerun_if_changed(dir);
+            let names: Vec<_> = registry.names().map(String::from).collect();
+            for name in names {
+                let generator = registry.get_mut(&name).unwrap();
+                if generator
+                    .options()
+                    .iter()
+                    .any(|(key, _)| *key == "template_dir")
+                {
+                    generator
+                        .set_option("template_dir", &dir.to_string_lossy())
+                        .map_err(Error::Config)?;
+                }
+            }
+        }
+        for (lang, key, value) in self.options.iter() {
+            let generator = registry
+                .get_mut(lang)
+                .ok_or_else(|| Error::Config(format!("Unknown language {}", lang)))?;
+            generator.set_option(key, value).map_err(Error::Config)?;
+        }
+        Ok(registry)
+    }
+
+    // Where `path` is: in the first include dir holding it, otherwise as
+    // given. `from` is what referred to it, for errors.
+    fn find(&self, path: &Path, from: &Path) -> Result<PathBuf, Error> {
+        self.includes
+            .iter()
+            .map(|dir| dir.join(path))
+            .chain(std::iter::once(path.to_path_buf()))
+            .find(|p| p.exists())
+            .ok_or_else(|| Error::Io {
+                path: from.to_path_buf(),
+                error: std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("{} not found in the include dirs", path.display()),
+                ),
+            })
+    }
+
+    // What other files import `path` by: relative to the include dir it's in.
+    fn proto_name(&self, path: &Path) -> String {
+        let name = self
+            .includes
+            .iter()
+            .find_map(|dir| path.strip_prefix(dir).ok())
+            .unwrap_or(path);
+        name.to_string_lossy().replace('\\', "/")
+    }
+
+    fn rerun_if_changed(&self, path: &Path) {
+        if self.emit_rerun_if_changed {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+}
+
+fn parse(path: &Path) -> Result<crate::parser::ParseTree, Error> {
+    let text = std::fs::read_to_string(path).map_err(io_error(path))?;
+    Parser::new(text.chars())
+        .parse_unresolved()
+        .map_err(|error| Error::Parse {
+            path: path.to_path_buf(),
+            error,
+        })
+}
+
+#[test]
+fn test_compile() {
+    let dir = std::env::temp_dir().join(format!("protogen-config-{}", std::process::id()));
+    let (protos, out) = (dir.join("protos"), dir.join("out"));
+    std::fs::create_dir_all(protos.join("common")).unwrap();
+    std::fs::create_dir_all(&out).unwrap();
+    std::fs::write(
+        protos.join("a.proto"),
+        "syntax = \"proto3\";\nimport \"common/b.proto\";\nmessage A { B b = 1; }",
+    )
+    .unwrap();
+    std::fs::write(
+        protos.join("common/b.proto"),
+        "syntax = \"proto3\";\nmessage B { int32 b = 1; }",
+    )
+    .unwrap();
+
+    let mut config = Config::new();
+    config
+        .include(&protos)
+        .rust_out(&out)
+        .descriptor_set_out(out.join("set.pb"))
+        .emit_rerun_if_changed(false);
+    let schemas = config.load(&["a.proto"]).unwrap();
+    assert_eq!(schemas.schemas.len(), 1);
+    assert_eq!(schemas.schemas[0].parse_tree.imports, ["common/b.proto"]);
+    // Types of imported files resolve, their code isn't generated.
+    assert_eq!(schemas.imports.len(), 1);
+    assert_eq!(
+        schemas.schemas[0].serial_tree.messages[0].fields[0].ftype,
+        "B"
+    );
+    assert_eq!(
+        schemas.schemas[0].serial_tree.messages[0].fields[0].kind,
+        crate::serializable_tree::FieldKind::Message
+    );
+    config.generate(&schemas).unwrap();
+    assert!(std::fs::read_to_string(out.join("a.rs"))
+        .unwrap()
+        .contains("pub struct A"));
+    assert!(!out.join("b.rs").exists());
+    assert!(out.join("set.pb").exists());
+
+    // Only direct imports are visible.
+    std::fs::write(
+        protos.join("c.proto"),
+        "syntax = \"proto3\";\nimport \"a.proto\";\nmessage C { B b = 1; }",
+    )
+    .unwrap();
+    match config.load(&["c.proto"]) {
+        Err(err @ Error::Parse { .. }) => assert_eq!(
+            err.to_string(),
+            format!(
+                "{}: Unknown type B of field C.b",
+                protos.join("c.proto").display()
+            )
+        ),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+
+    std::fs::write(protos.join("bad.proto"), "syntax = \"proto3\";\nmessage {}").unwrap();
+    match config.compile(&["bad.proto"]) {
+        Err(Error::Parse { path, error }) => {
+            assert_eq!(path, protos.join("bad.proto"));
+            assert_eq!(error.to_string(), "2:9: Expected a name, found {");
+        }
+        res => panic!("{:?}", res),
+    }
+    assert!(matches!(
+        config.compile(&["missing.proto"]),
+        Err(Error::Io { .. })
+    ));
+    assert!(matches!(
+        Config::new().out("go", &out).compile(&["missing.proto"]),
+        Err(Error::Config(_))
+    ));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_descriptor_set_names() {
+    use protogen_runtime::wire::Decoder;
+
+    let dir = std::env::temp_dir().join(format!("protogen-names-{}", std::process::id()));
+    let protos = dir.join("protos");
+    std::fs::create_dir_all(protos.join("common")).unwrap();
+    std::fs::write(
+        protos.join("a.proto"),
+        "syntax = \"proto3\";\nimport \"common/b.proto\";\nmessage A { B b = 1; }",
+    )
+    .unwrap();
+    std::fs::write(
+        protos.join("common/b.proto"),
+        "syntax = \"proto3\";\nmessage B { int32 b = 1; }",
+    )
+    .unwrap();
+
+    let mut config = Config::new();
+    config
+        .include(&protos)
+        .descriptor_set_out(dir.join("set.pb"))
+        .emit_rerun_if_changed(false);
+    // Named relative to the include dir, however they were given.
+    let schemas = config.load(&[protos.join("a.proto")]).unwrap();
+    assert_eq!(schemas.schemas[0].path, protos.join("a.proto"));
+    assert_eq!(schemas.schemas[0].name, "a.proto");
+    assert_eq!(schemas.imports[0].name, "common/b.proto");
+    config.generate(&schemas).unwrap();
+
+    // Each file follows the files it depends on, by the names they have.
+    let set = std::fs::read(dir.join("set.pb")).unwrap();
+    let mut files = Vec::new();
+    for field in Decoder::new(&set) {
+        let (mut name, mut deps) = ("", Vec::new());
+        for field in Decoder::new(field.unwrap().1.bytes(1).unwrap()) {
+            match field.unwrap() {
+                (1, v) => name = v.str(1).unwrap(),
+                (3, v) => deps.push(v.str(3).unwrap()),
+                _ => (),
+            }
+        }
+        files.push((name, deps));
+    }
+    assert_eq!(
+        files,
+        [
+            ("common/b.proto", vec![]),
+            ("a.proto", vec!["common/b.proto"])
+        ]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
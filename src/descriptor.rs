@@ -0,0 +1,396 @@
+// Encodes parsed schemas as `google.protobuf.FileDescriptorProto`, the form
+// protoc hands schemas to plugins in.
+
+use protogen_runtime::{
+    descriptor::{METHOD_ID_OPTION, REQUIRED_OPTION},
+    wire::{self, EncodeError, Encoder, Varint},
+};
+
+use crate::parser::{Enum, FieldType, Message, ParseTree, Service, SourceInfo};
+
+// FieldDescriptorProto.Label
+const LABEL_OPTIONAL: u32 = 1;
+const LABEL_REPEATED: u32 = 3;
+
+// FieldDescriptorProto.Type
+const TYPE_INT64: u32 = 3;
+const TYPE_UINT64: u32 = 4;
+const TYPE_INT32: u32 = 5;
+const TYPE_STRING: u32 = 9;
+const TYPE_MESSAGE: u32 = 11;
+const TYPE_UINT32: u32 = 13;
+const TYPE_ENUM: u32 = 14;
+
+/// Encode `tree` as a `FileDescriptorProto` named `name`, the path other
+/// files would import it by. With `source_info` it includes where each
+/// declaration was written and its leading comments.
+pub fn file_descriptor(name: &str, tree: &ParseTree, source_info: bool) -> Vec<u8> {
+    let mut file = FileEncoder {
+        tree,
+        path: Vec::new(),
+        locations: Vec::new(),
+    };
+    let mut e = FieldWriter::default();
+    e.string(1, name);
+    for import in tree.imports.iter() {
+        e.string(3, import);
+    }
+    for (i, msg) in tree.messages.iter().enumerate() {
+        file.nested(&[4, i as i32], |file| {
+            e.message(4, |e| file.message(e, msg))
+        });
+    }
+    for (i, enum_) in tree.enums.iter().enumerate() {
+        file.nested(&[5, i as i32], |file| {
+            e.message(5, |e| file.enum_type(e, enum_))
+        });
+    }
+    for (i, service) in tree.services.iter().enumerate() {
+        file.nested(&[6, i as i32], |file| {
+            e.message(6, |e| file.service(e, service))
+        });
+    }
+    if source_info {
+        e.message(9, |e| {
+            for (path, source) in file.locations.iter() {
+                e.message(1, |e| location(e, path, source));
+            }
+        });
+    }
+    e.string(12, "proto3");
+    e.into_bytes()
+}
+
+/// Encode a `FileDescriptorSet` of all `files`, as `(name, tree)` pairs.
+pub fn file_descriptor_set<'t>(
+    files: impl IntoIterator<Item = (&'t str, &'t ParseTree)>,
+    source_info: bool,
+) -> Vec<u8> {
+    let mut e = FieldWriter::default();
+    for (name, tree) in files {
+        e.bytes(1, &file_descriptor(name, tree, source_info));
+    }
+    e.into_bytes()
+}
+
+/// Fields of a message in the runtime's wire format, written to a buffer
+/// that grows with them. Unlike generated messages, descriptors and plugin
+/// requests aren't sized before they're encoded.
+#[derive(Debug, Default)]
+pub(crate) struct FieldWriter {
+    buf: Vec<u8>,
+}
+
+impl FieldWriter {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    fn put(&mut self, size: usize, f: impl FnOnce(&mut Encoder) -> Result<(), EncodeError>) {
+        let start = self.buf.len();
+        self.buf.resize(start + size, 0);
+        f(&mut Encoder::new(&mut self.buf[start..])).expect("fields are sized exactly");
+    }
+
+    /// `int32` values are sign extended to 64 bits.
+    pub fn varint<T: Varint>(&mut self, field: u32, value: T) {
+        let size = wire::varint_field_size(field, value.to_varint());
+        self.put(size, |e| e.varint(field, value));
+    }
+    pub fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.put(wire::len_field_size(field, value.len()), |e| {
+            e.bytes(field, value)
+        });
+    }
+    pub fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+    /// A packed repeated varint field, nothing if it's empty.
+    pub fn packed<T: Varint>(&mut self, field: u32, values: &[T]) {
+        let values = values.iter().copied();
+        self.put(wire::packed_field_size(field, values.clone()), |e| {
+            e.packed(field, values)
+        });
+    }
+    /// A nested message, written by `f`.
+    pub fn message(&mut self, field: u32, f: impl FnOnce(&mut FieldWriter)) {
+        let mut nested = FieldWriter::default();
+        f(&mut nested);
+        self.bytes(field, &nested.buf);
+    }
+}
+
+struct FileEncoder<'t> {
+    tree: &'t ParseTree,
+    // Field numbers and indices leading from the file to the current
+    // declaration, as in `SourceCodeInfo.Location.path`.
+    path: Vec<i32>,
+    locations: Vec<(Vec<i32>, &'t SourceInfo)>,
+}
+
+impl<'t> FileEncoder<'t> {
+    fn nested<R>(&mut self, path: &[i32], f: impl FnOnce(&mut Self) -> R) -> R {
+        self.path.extend_from_slice(path);
+        let res = f(self);
+        self.path.truncate(self.path.len() - path.len());
+        res
+    }
+
+    fn locate(&mut self, source: &'t SourceInfo) {
+        self.locations.push((self.path.clone(), source));
+    }
+
+    fn message(&mut self, e: &mut FieldWriter, msg: &'t Message) {
+        let tree = self.tree;
+        self.locate(&msg.source);
+        e.string(1, &tree.get_str(msg.name));
+        // proto3 `optional` fields each live in a synthetic oneof named after
+        // the field, declared after any real oneofs.
+        let mut oneofs = Vec::new();
+        for (i, field) in msg.fields.iter().enumerate() {
+            self.nested(&[2, i as i32], |file| file.locate(&field.source));
+            let name = tree.get_str(field.name);
+            e.message(2, |e| {
+                e.string(1, &name);
+                e.varint(3, field.idx);
+                e.varint(
+                    4,
+                    if field.repeated {
+                        LABEL_REPEATED
+                    } else {
+                        LABEL_OPTIONAL
+                    },
+                );
+                let (ty, type_name) = match field.ftype {
+                    FieldType::Int32 => (TYPE_INT32, None),
+                    FieldType::Int64 => (TYPE_INT64, None),
+                    FieldType::Uint32 => (TYPE_UINT32, None),
+                    FieldType::Uint64 => (TYPE_UINT64, None),
+                    FieldType::String => (TYPE_STRING, None),
+                    FieldType::Enum(id) => (TYPE_ENUM, Some(id)),
+                    FieldType::Message(id) => (TYPE_MESSAGE, Some(id)),
+                    FieldType::Undef => unreachable!("Field types are resolved while parsing"),
+                };
+                e.varint(5, ty);
+                if let Some(id) = type_name {
+                    e.string(6, &format!(".{}", tree.get_str(id)));
+                }
+                if field.required {
+                    e.message(8, |e| e.varint(REQUIRED_OPTION, 1u32));
+                }
+                if field.optional {
+                    e.varint(9, oneofs.len() as i32);
+                    oneofs.push(format!("_{}", name));
+                }
+                e.string(10, &json_name(&name));
+                if field.optional {
+                    e.varint(17, 1u32);
+                }
+            });
+        }
+        for (i, nested) in msg.messages.iter().enumerate() {
+            self.nested(&[3, i as i32], |file| {
+                e.message(3, |e| file.message(e, nested))
+            });
+        }
+        for (i, enum_) in msg.enums.iter().enumerate() {
+            self.nested(&[4, i as i32], |file| {
+                e.message(4, |e| file.enum_type(e, enum_))
+            });
+        }
+        for oneof in oneofs.iter() {
+            e.message(8, |e| e.string(1, oneof));
+        }
+    }
+
+    fn enum_type(&mut self, e: &mut FieldWriter, enum_: &'t Enum) {
+        self.locate(&enum_.source);
+        e.string(1, &self.tree.get_str(enum_.name));
+        for (i, variant) in enum_.variants.iter().enumerate() {
+            self.nested(&[2, i as i32], |file| file.locate(&variant.source));
+            e.message(2, |e| {
+                e.string(1, &self.tree.get_str(variant.name));
+                e.varint(2, variant.id as i32);
+            });
+        }
+    }
+
+    fn service(&mut self, e: &mut FieldWriter, service: &'t Service) {
+        self.locate(&service.source);
+        e.string(1, &self.tree.get_str(service.name));
+        for (i, rpc) in service.rpcs.iter().enumerate() {
+            self.nested(&[2, i as i32], |file| file.locate(&rpc.source));
+            e.message(2, |e| {
+                e.string(1, &self.tree.get_str(rpc.name));
+                e.string(2, &format!(".{}", self.tree.get_str(rpc.arg_type)));
+                e.string(3, &format!(".{}", self.tree.get_str(rpc.ret_type)));
+                if let Some(id) = rpc.method_id {
+                    e.message(4, |e| e.varint(METHOD_ID_OPTION, id));
+                }
+            });
+        }
+    }
+}
+
+// A `SourceCodeInfo.Location`, spans leave out the end line when it's the
+// start line.
+fn location(e: &mut FieldWriter, path: &[i32], source: &SourceInfo) {
+    e.packed(1, path);
+    let (start, end) = (source.start, source.end);
+    let span = if start.0 == end.0 {
+        vec![start.0 as i32, start.1 as i32, end.1 as i32]
+    } else {
+        vec![start.0 as i32, start.1 as i32, end.0 as i32, end.1 as i32]
+    };
+    e.packed(2, &span);
+    if !source.leading_comments.is_empty() {
+        e.string(3, &source.leading_comments);
+    }
+}
+
+/// The JSON name protoc derives from a field name: underscores dropped and
+/// the letter following each capitalized.
+fn json_name(name: &str) -> String {
+    let mut json = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            json.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            json.push(c);
+        }
+    }
+    json
+}
+
+#[test]
+fn test_file_descriptor() {
+    use protogen_runtime::wire::{Decoder, Value};
+
+    let text = "syntax = \"proto3\";
+        message Outer {
+            message Inner { repeated string tags = 1; }
+            optional Inner inner_msg = 2;
+        }
+        service Svc { rpc Call(Outer) returns (Outer.Inner); }";
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
+    let bytes = file_descriptor("a.proto", &tree, false);
+
+    let fields = |buf| Decoder::new(buf).collect::<Result<Vec<_>, _>>().unwrap();
+    let file = fields(&bytes);
+    assert_eq!(file[0], (1, Value::Len(b"a.proto")));
+    assert_eq!(file.last().unwrap(), &(12, Value::Len(b"proto3")));
+
+    let outer = fields(file[1].1.bytes(4).unwrap());
+    assert_eq!(outer[0], (1, Value::Len(b"Outer")));
+    let inner_msg = fields(outer[1].1.bytes(2).unwrap());
+    assert_eq!(
+        inner_msg,
+        [
+            (1, Value::Len(b"inner_msg")),
+            (3, Value::Varint(2)),
+            (4, Value::Varint(LABEL_OPTIONAL.into())),
+            (5, Value::Varint(TYPE_MESSAGE.into())),
+            (6, Value::Len(b".Outer.Inner")),
+            (9, Value::Varint(0)),
+            (10, Value::Len(b"innerMsg")),
+            (17, Value::Varint(1)),
+        ]
+    );
+    assert_eq!(outer[2].0, 3);
+    assert_eq!(
+        fields(outer[3].1.bytes(8).unwrap()),
+        [(1, Value::Len(b"_inner_msg"))]
+    );
+
+    let svc = fields(file[2].1.bytes(6).unwrap());
+    let call = fields(svc[1].1.bytes(2).unwrap());
+    assert_eq!(call[2], (3, Value::Len(b".Outer.Inner")));
+}
+
+#[test]
+fn test_source_info() {
+    use protogen_runtime::wire::{Decoder, Value};
+
+    let text = "syntax = \"proto3\";\n// About A\nmessage A {\n    int32 a = 1;\n}";
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
+    let without = file_descriptor("a.proto", &tree, false);
+    assert!(Decoder::new(&without).all(|f| f.unwrap().0 != 9));
+
+    let bytes = file_descriptor("a.proto", &tree, true);
+    let info = Decoder::new(&bytes)
+        .map(|f| f.unwrap())
+        .find(|(field, _)| *field == 9)
+        .unwrap();
+    let locations: Vec<_> = Decoder::new(info.1.bytes(9).unwrap())
+        .map(|f| f.unwrap().1.bytes(1).unwrap())
+        .collect();
+    // path [4, 0], span lines 2 to 4, comment.
+    let message = Decoder::new(locations[0])
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        message,
+        [
+            (1, Value::Len(&[4, 0])),
+            (2, Value::Len(&[2, 0, 4, 1])),
+            (3, Value::Len(b" About A\n")),
+        ]
+    );
+    // path [4, 0, 2, 0], single line span.
+    let field = Decoder::new(locations[1])
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        field,
+        [(1, Value::Len(&[4, 0, 2, 0])), (2, Value::Len(&[3, 4, 16]))]
+    );
+}
+
+#[test]
+fn test_load_at_runtime() {
+    use protogen_runtime::{
+        descriptor::{FileDescriptor, Label},
+        dynamic::{DynamicMessage, DynamicValue},
+        rpc::ProtoMessage,
+    };
+
+    let text = "syntax = \"proto3\";
+        message Outer {
+            message Inner { repeated string tags = 1; }
+            enum Kind { A = 0; B = 1; }
+            optional Inner inner = 2;
+            Kind kind = 3;
+            repeated Outer children = 4;
+            string id = 5 [(required) = true];
+        }
+        service Svc {
+            rpc Call(Outer) returns (Outer.Inner);
+            rpc Fixed(Outer) returns (Outer) { option (method_id) = 7; }
+        }";
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
+    let set = file_descriptor_set([("a.proto", &tree)], false);
+    let file = FileDescriptor::decode_set(&set).unwrap();
+
+    let outer = file.message("Outer").unwrap();
+    assert_eq!(outer.field("inner").unwrap().label, Label::Optional);
+    assert_eq!(outer.field("kind").unwrap().label, Label::Singular);
+    assert_eq!(outer.field("id").unwrap().label, Label::Required);
+    let svc = file.service("Svc").unwrap();
+    let call_id = tree.method_id(&tree.services[0], &tree.services[0].rpcs[0]);
+    assert_eq!(svc.message(call_id << 1 | 1), Some("Outer.Inner"));
+    assert_eq!(svc.message(7 << 1), Some("Outer"));
+
+    // inner { tags: "a" } kind: B children { kind: B }
+    let bytes = [0x12, 3, 0x0a, 1, b'a', 0x18, 1, 0x22, 2, 0x18, 1];
+    let msg = DynamicMessage::decode(&file, outer, &bytes).unwrap();
+    assert_eq!(msg.get("kind"), Some(&DynamicValue::Enum(1)));
+    assert_eq!(
+        msg.to_string(),
+        r#"inner { tags: "a" } kind: B children { kind: B }"#
+    );
+    assert_eq!(msg.to_bytes(), bytes);
+}
@@ -0,0 +1,370 @@
+//! Messages of a schema loaded at run time, for `protogen decode` and
+//! `protogen encode`.
+//!
+//! They're the runtime's [`DynamicMessage`]s, of the [`FileDescriptor`]
+//! [`file_descriptor`] builds from the parsed schemas, so they encode and
+//! print like the generated messages. [`Json`] and [`merge_json`] add the
+//! JSON the generated serde code reads and writes.
+
+use std::fmt::Write;
+
+use protogen_runtime::{
+    descriptor::{
+        Cow, EnumDescriptor, EnumValueDescriptor, FieldDescriptor, FieldType, FileDescriptor,
+        Label, MessageDescriptor, RpcDescriptor, ServiceDescriptor,
+    },
+    dynamic::{DynamicMessage, DynamicValue},
+    wire::{DecodeError, Decoder, Value},
+};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::serializable_tree::{Enum, FieldKind, Message, SerializeTree};
+
+/// Descriptors of everything in `trees`, types resolve across them.
+pub fn file_descriptor<'t>(trees: impl IntoIterator<Item = &'t SerializeTree>) -> FileDescriptor {
+    fn enum_type(enum_: &Enum) -> EnumDescriptor {
+        EnumDescriptor {
+            name: Cow::Owned(enum_.name.clone()),
+            values: enum_
+                .variants
+                .iter()
+                .map(|v| EnumValueDescriptor {
+                    name: Cow::Owned(v.name.clone()),
+                    number: v.id as i32,
+                })
+                .collect(),
+        }
+    }
+    // Nested messages and enums follow their parent, like in generated code.
+    fn add_message(
+        msg: &Message,
+        messages: &mut Vec<MessageDescriptor>,
+        enums: &mut Vec<EnumDescriptor>,
+    ) {
+        let fields = msg.fields.iter().map(|field| FieldDescriptor {
+            name: Cow::Owned(field.name.clone()),
+            number: field.idx,
+            ty: match field.kind {
+                FieldKind::Int32 => FieldType::Int32,
+                FieldKind::Int64 => FieldType::Int64,
+                FieldKind::Uint32 => FieldType::Uint32,
+                FieldKind::Uint64 => FieldType::Uint64,
+                FieldKind::String => FieldType::String,
+                FieldKind::Enum => FieldType::Enum(Cow::Owned(field.ftype.clone())),
+                FieldKind::Message => FieldType::Message(Cow::Owned(field.ftype.clone())),
+            },
+            label: if field.repeated {
+                Label::Repeated
+            } else if field.optional {
+                Label::Optional
+            } else if field.required {
+                Label::Required
+            } else {
+                Label::Singular
+            },
+        });
+        messages.push(MessageDescriptor {
+            name: Cow::Owned(msg.name.clone()),
+            fields: fields.collect(),
+        });
+        enums.extend(msg.enums.iter().map(enum_type));
+        for nested in msg.messages.iter() {
+            add_message(nested, messages, enums);
+        }
+    }
+
+    let (mut messages, mut enums, mut services) = (Vec::new(), Vec::new(), Vec::new());
+    for tree in trees {
+        enums.extend(tree.enums.iter().map(enum_type));
+        for msg in tree.messages.iter() {
+            add_message(msg, &mut messages, &mut enums);
+        }
+        services.extend(tree.services.iter().map(|service| {
+            ServiceDescriptor {
+                name: Cow::Owned(service.name.clone()),
+                rpcs: service
+                    .rpcs
+                    .iter()
+                    .map(|rpc| RpcDescriptor {
+                        name: Cow::Owned(rpc.name.clone()),
+                        arg_id: rpc.arg_id,
+                        ret_id: rpc.ret_id,
+                        arg: Cow::Owned(rpc.arg_type.clone()),
+                        ret: Cow::Owned(rpc.ret_type.clone()),
+                    })
+                    .collect(),
+            }
+        }));
+    }
+    FileDescriptor {
+        messages: Cow::Owned(messages),
+        enums: Cow::Owned(enums),
+        services: Cow::Owned(services),
+    }
+}
+
+/// A message as JSON, like the generated serde code writes it: every field
+/// in schema order, unset optional fields `null` and unset singular ones
+/// their default. Unknown fields are left out.
+pub struct Json<'m, 'd>(pub &'m DynamicMessage<'d>);
+
+// A value with its field, enums are written by name.
+struct JsonValue<'m, 'd> {
+    file: &'d FileDescriptor,
+    ty: &'d FieldType,
+    value: &'m DynamicValue<'d>,
+}
+
+impl Serialize for JsonValue<'_, '_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            DynamicValue::Int32(v) => s.serialize_i32(*v),
+            DynamicValue::Int64(v) => s.serialize_i64(*v),
+            DynamicValue::Uint32(v) => s.serialize_u32(*v),
+            DynamicValue::Uint64(v) => s.serialize_u64(*v),
+            DynamicValue::String(v) => s.serialize_str(v),
+            DynamicValue::Enum(v) => {
+                let FieldType::Enum(name) = self.ty else {
+                    unreachable!("enum values are only in enum fields")
+                };
+                match self.file.enum_(name).and_then(|e| e.name_of(*v)) {
+                    Some(name) => s.serialize_str(name),
+                    None => s.serialize_i32(*v),
+                }
+            }
+            DynamicValue::Message(v) => Json(v).serialize(s),
+        }
+    }
+}
+
+impl Serialize for Json<'_, '_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let (msg, file) = (self.0, self.0.file());
+        let fields = &msg.descriptor().fields;
+        let mut map = s.serialize_map(Some(fields.len()))?;
+        for field in fields.iter() {
+            let json = |value| JsonValue {
+                file,
+                ty: &field.ty,
+                value,
+            };
+            let values = msg.get_all(&field.name);
+            if field.label == Label::Repeated {
+                let items: Vec<_> = values.iter().map(json).collect();
+                map.serialize_entry(&field.name, &items)?;
+            } else if let Some(value) = values.first() {
+                map.serialize_entry(&field.name, &json(value))?;
+            } else if field.label == Label::Optional {
+                map.serialize_entry(&field.name, &())?;
+            } else {
+                let default = match &field.ty {
+                    FieldType::Int32 => DynamicValue::Int32(0),
+                    FieldType::Int64 => DynamicValue::Int64(0),
+                    FieldType::Uint32 => DynamicValue::Uint32(0),
+                    FieldType::Uint64 => DynamicValue::Uint64(0),
+                    FieldType::String => DynamicValue::String(String::new()),
+                    FieldType::Enum(_) => DynamicValue::Enum(0),
+                    FieldType::Message(name) => {
+                        DynamicValue::Message(DynamicMessage::new(file, message_type(file, name)))
+                    }
+                };
+                map.serialize_entry(&field.name, &json(&default))?;
+            }
+        }
+        map.end()
+    }
+}
+
+// Field types resolve in the file a dynamic message was made with.
+fn message_type<'d>(file: &'d FileDescriptor, name: &str) -> &'d MessageDescriptor {
+    file.message(name)
+        .expect("message types of fields are in their file")
+}
+
+/// Set the fields named in a JSON object, as written by [`Json`]. `null`
+/// clears a field.
+pub fn merge_json(msg: &mut DynamicMessage, json: &serde_json::Value) -> Result<(), String> {
+    let descriptor = msg.descriptor();
+    let serde_json::Value::Object(object) = json else {
+        return Err(format!("Expected an object for {}", descriptor.name));
+    };
+    for (name, json) in object.iter() {
+        let field = descriptor
+            .field(name)
+            .ok_or_else(|| format!("Unknown field {}.{}", descriptor.name, name))?;
+        msg.clear(name);
+        match json {
+            serde_json::Value::Null => (),
+            serde_json::Value::Array(items) if field.label == Label::Repeated => {
+                for json in items.iter() {
+                    let value = json_value(msg.file(), field, json)?;
+                    msg.push(name, value);
+                }
+            }
+            _ if field.label == Label::Repeated => {
+                return Err(format!("Expected an array for {}", field.name))
+            }
+            json => {
+                let value = json_value(msg.file(), field, json)?;
+                msg.set(name, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn json_value<'d>(
+    file: &'d FileDescriptor,
+    field: &FieldDescriptor,
+    json: &serde_json::Value,
+) -> Result<DynamicValue<'d>, String> {
+    let expected = |what: &str| format!("Expected {} for {}, got {}", what, field.name, json);
+    let int = |what: &str| json.as_i64().ok_or_else(|| expected(what));
+    let uint = |what: &str| json.as_u64().ok_or_else(|| expected(what));
+    Ok(match &field.ty {
+        FieldType::Int32 => DynamicValue::Int32(
+            int("an int32").and_then(|v| v.try_into().map_err(|_| expected("an int32")))?,
+        ),
+        FieldType::Int64 => DynamicValue::Int64(int("an int64")?),
+        FieldType::Uint32 => DynamicValue::Uint32(
+            uint("a uint32").and_then(|v| v.try_into().map_err(|_| expected("a uint32")))?,
+        ),
+        FieldType::Uint64 => DynamicValue::Uint64(uint("a uint64")?),
+        FieldType::String => DynamicValue::String(
+            json.as_str()
+                .ok_or_else(|| expected("a string"))?
+                .to_string(),
+        ),
+        FieldType::Enum(name) => {
+            let enum_ = file
+                .enum_(name)
+                .expect("enum types of fields are in their file");
+            let number = match json {
+                serde_json::Value::String(name) => enum_.number_of(name),
+                json => json.as_i64().and_then(|v| i32::try_from(v).ok()),
+            };
+            DynamicValue::Enum(number.ok_or_else(|| expected(&format!("a {}", enum_.name)))?)
+        }
+        FieldType::Message(name) => {
+            let mut msg = DynamicMessage::new(file, message_type(file, name));
+            merge_json(&mut msg, json)?;
+            DynamicValue::Message(msg)
+        }
+    })
+}
+
+/// Print the fields of an encoded message without a schema, one per line
+/// as `number wire-type value`, like `protoc --decode_raw`. Length delimited
+/// values are shown as a string if they're printable, as a nested message
+/// if they parse as one and in hex otherwise.
+pub fn decode_raw(buf: &[u8]) -> Result<String, DecodeError> {
+    fn write(out: &mut String, buf: &[u8], indent: usize) -> Result<(), DecodeError> {
+        for field in Decoder::new(buf) {
+            let (number, value) = field?;
+            let _ = write!(out, "{:indent$}{} ", "", number);
+            let _ = match value {
+                Value::Varint(v) if v > i64::MAX as u64 => {
+                    writeln!(out, "varint {} ({})", v, v as i64)
+                }
+                Value::Varint(v) => writeln!(out, "varint {}", v),
+                Value::Fixed64(v) => writeln!(out, "fixed64 {:#018x}", v),
+                Value::Fixed32(v) => writeln!(out, "fixed32 {:#010x}", v),
+                Value::Len(bytes) => match std::str::from_utf8(bytes) {
+                    Ok(s) if s.chars().all(|c| !c.is_control() || c == '\n' || c == '\t') => {
+                        writeln!(out, "len {:?}", s)
+                    }
+                    _ if Decoder::new(bytes).all(|f| f.is_ok()) => {
+                        out.push_str("len {\n");
+                        write(out, bytes, indent + 2)?;
+                        writeln!(out, "{:indent$}}}", "")
+                    }
+                    _ => {
+                        out.push_str("len 0x");
+                        for b in bytes {
+                            let _ = write!(out, "{:02x}", b);
+                        }
+                        writeln!(out)
+                    }
+                },
+            };
+        }
+        Ok(())
+    }
+    let mut out = String::new();
+    write(&mut out, buf, 0)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+fn test_schema() -> FileDescriptor {
+    let text = "syntax = \"proto3\";
+        message Tree {
+            enum Color { RED = 0; GREEN = 1; }
+            string name = 1;
+            optional int32 weight = 2;
+            repeated Tree children = 3;
+            Color color = 4;
+            repeated uint64 ids = 5;
+        }";
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
+    file_descriptor([&SerializeTree::from_parse_tree(&tree)])
+}
+
+#[test]
+fn test_json_round_trip() {
+    use protogen_runtime::rpc::ProtoMessage;
+
+    let file = test_schema();
+    let tree = file.message("Tree").unwrap();
+    let text = r#"name: "root" weight: -3 children { name: "leaf" color: GREEN } ids: 1 ids: 2"#;
+    let msg = DynamicMessage::parse_text(&file, tree, text).unwrap();
+    assert_eq!(msg.to_string(), text);
+    assert_eq!(
+        DynamicMessage::decode(&file, tree, &msg.to_bytes()),
+        Ok(msg.clone())
+    );
+
+    let json = serde_json::to_value(Json(&msg)).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "name": "root",
+            "weight": -3,
+            "children": [
+                {"name": "leaf", "weight": null, "children": [], "color": "GREEN", "ids": []}
+            ],
+            "color": "RED",
+            "ids": [1, 2],
+        })
+    );
+    let mut parsed = DynamicMessage::new(&file, tree);
+    merge_json(&mut parsed, &json).unwrap();
+    assert_eq!(parsed.to_bytes(), msg.to_bytes());
+
+    let err = merge_json(&mut parsed, &serde_json::json!({"weight": 1u64 << 40})).unwrap_err();
+    assert_eq!(err, "Expected an int32 for weight, got 1099511627776");
+}
+
+#[test]
+fn test_decode_raw() {
+    use protogen_runtime::rpc::ProtoMessage;
+
+    let file = test_schema();
+    let msg = DynamicMessage::parse_text(
+        &file,
+        file.message("Tree").unwrap(),
+        r#"name: "a" weight: -1 children { color: GREEN }"#,
+    )
+    .unwrap();
+    let mut buf = msg.to_bytes();
+    buf.extend([0x31, 1, 0, 0, 0, 0, 0, 0, 0, 0x3a, 2, 0xff, 0xff]);
+    assert_eq!(
+        decode_raw(&buf).unwrap(),
+        "1 len \"a\"\n\
+         2 varint 18446744073709551615 (-1)\n\
+         3 len {\n  4 varint 1\n}\n\
+         6 fixed64 0x0000000000000001\n\
+         7 len 0xffff\n"
+    );
+    assert!(decode_raw(&[0x0a, 5]).is_err());
+}
@@ -18,7 +18,7 @@ impl StringIntern {
             }
         }
     }
-    pub fn get_str(&self, id: StringId) -> Option<Cow<String>> {
+    pub fn get_str(&self, id: StringId) -> Option<Cow<'_, String>> {
         Some(Cow::Borrowed(self.map.get_index(id)?))
     }
 }
@@ -0,0 +1,19 @@
+//! Generates IPC interfaces from protobuf schemas.
+//!
+//! [`Config`] runs the whole pipeline, e.g. from a build script. The modules
+//! below it are the pieces: parsing, descriptors and the code generators.
+
+pub mod codegen;
+pub mod codegen_csharp;
+pub mod codegen_rust;
+pub mod config;
+pub mod descriptor;
+pub mod inspect;
+pub mod intern;
+pub mod parse_tree;
+pub mod parser;
+pub mod plugin;
+pub mod serializable_tree;
+pub mod templates;
+
+pub use config::{Config, Error};
@@ -1,96 +1,267 @@
-pub mod codegen_csharp;
-pub mod codegen_rust;
-pub mod intern;
-pub mod parse_tree;
-pub mod parser;
-pub mod serializable_tree;
-
 use std::{
-    fs::File,
-    io::{BufReader, BufWriter, Read},
-    path::{Path, PathBuf},
+    io::{Read, Write},
+    path::PathBuf,
 };
 
-use clap::{Parser, Subcommand};
+use clap::{error::ErrorKind, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use protogen::inspect::{self, Json};
+use protogen_runtime::{
+    descriptor::FileDescriptor, dynamic::DynamicMessage, rpc::ProtoMessage, text,
+};
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     // Paths for input files.
     proto_files: Vec<PathBuf>,
 
-    // Output formats
+    /// Directory to look up input files and imports in, may be repeated.
+    #[arg(short = 'I', long = "include", value_name = "DIR")]
+    includes: Vec<PathBuf>,
+
+    /// Generate code with a backend into a directory, e.g. `--out rust=src/gen`.
+    #[arg(long = "out", value_name = "LANG=DIR", value_parser = parse_out)]
+    outputs: Vec<(String, PathBuf)>,
+    /// Set a backend option, e.g. `--opt rust:async` or `--opt rust:async=false`.
+    #[arg(long = "opt", value_name = "LANG:KEY[=VALUE]", value_parser = parse_opt)]
+    options: Vec<(String, String, String)>,
+    /// Run an external protoc compatible generator as backend NAME, e.g.
+    /// `--plugin ts=./protoc-gen-ts` then `--out ts=gen`. Its parameters are set
+    /// with `--opt NAME:KEY[=VALUE]`.
+    #[arg(long = "plugin", value_name = "NAME=PATH", value_parser = parse_plugin)]
+    plugins: Vec<(String, PathBuf)>,
+    /// Write the parsed schemas as a binary `google.protobuf.FileDescriptorSet`.
+    #[arg(long, value_name = "FILE")]
+    descriptor_set_out: Option<PathBuf>,
+    /// Include source positions and comments in `--descriptor-set-out`.
+    #[arg(long, requires = "descriptor_set_out")]
+    include_source_info: bool,
+    /// Directory of templates replacing the built-in ones with the same file name.
+    #[arg(long)]
+    template_dir: Option<PathBuf>,
+
+    /// Same as `--out rust=DIR`.
     #[arg(long)]
     rust_out: Option<PathBuf>,
+    /// Same as `--out csharp=DIR`.
     #[arg(long)]
     csharp_out: Option<PathBuf>,
+    /// Same as `--opt rust:async`.
+    #[arg(long)]
+    rust_async: bool,
+    /// Same as `--opt rust:no_std`.
+    #[arg(long, conflicts_with = "rust_async")]
+    rust_no_std: bool,
 }
 
-fn validate_out_dir(lang: &str, path: &Option<PathBuf>) {
-    if let Some(ref path) = path {
-        if !path.is_dir() {
-            panic!(
-                "{} output dir invalid {}",
-                lang,
-                path.as_os_str().to_str().unwrap()
-            );
-        }
+#[derive(Subcommand)]
+enum Command {
+    /// Read an encoded message from stdin and print it, like `protoc --decode`.
+    Decode(DecodeArgs),
+    /// Read a message in the text format or JSON from stdin and write it encoded.
+    Encode(EncodeArgs),
+}
+
+#[derive(Args)]
+struct DecodeArgs {
+    /// Schema defining the message.
+    #[arg(required_unless_present = "raw")]
+    proto_file: Option<PathBuf>,
+    /// Qualified message name, e.g. `Outer.Inner`.
+    #[arg(required_unless_present = "raw")]
+    message: Option<String>,
+    /// Print field numbers, wire types and values without a schema.
+    #[arg(long, conflicts_with_all = ["proto_file", "message", "format"])]
+    raw: bool,
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Directory to look up the schema in, may be repeated.
+    #[arg(short = 'I', long = "include", value_name = "DIR")]
+    includes: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct EncodeArgs {
+    /// Schema defining the message.
+    proto_file: PathBuf,
+    /// Qualified message name, e.g. `Outer.Inner`.
+    message: String,
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Directory to look up the schema in, may be repeated.
+    #[arg(short = 'I', long = "include", value_name = "DIR")]
+    includes: Vec<PathBuf>,
+}
+
+/// How messages are printed by `decode` and read by `encode`.
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// The protobuf text format, like generated `Display` impls.
+    Text,
+    /// JSON, like generated serde impls.
+    Json,
+}
+
+fn parse_out(s: &str) -> Result<(String, PathBuf), String> {
+    let (lang, dir) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected LANG=DIR, got {}", s))?;
+    Ok((lang.to_string(), PathBuf::from(dir)))
+}
+
+fn parse_plugin(s: &str) -> Result<(String, PathBuf), String> {
+    let (name, path) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=PATH, got {}", s))?;
+    // Accept protoc's naming, `--plugin protoc-gen-ts=...` registers `ts`.
+    let name = name.strip_prefix("protoc-gen-").unwrap_or(name);
+    Ok((name.to_string(), PathBuf::from(path)))
+}
+
+fn parse_opt(s: &str) -> Result<(String, String, String), String> {
+    let (lang, opt) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected LANG:KEY[=VALUE], got {}", s))?;
+    let (key, value) = opt.split_once('=').unwrap_or((opt, ""));
+    Ok((lang.to_string(), key.to_string(), value.to_string()))
+}
+
+fn fail(msg: String) -> ! {
+    Cli::command().error(ErrorKind::InvalidValue, msg).exit()
+}
+
+fn exit_with(e: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", e);
+    std::process::exit(1);
+}
+
+// Descriptors of `proto` and the files it imports, which define `message`.
+fn load_schema(proto: PathBuf, includes: Vec<PathBuf>, message: &str) -> FileDescriptor {
+    let mut config = protogen::Config::new();
+    config.emit_rerun_if_changed(false);
+    for dir in includes {
+        config.include(dir);
+    }
+    let schemas = match config.load(&[proto]) {
+        Ok(schemas) => schemas,
+        Err(protogen::Error::Config(msg)) => fail(msg),
+        Err(e) => exit_with(e),
+    };
+    let all = schemas.schemas.iter().chain(schemas.imports.iter());
+    let file = inspect::file_descriptor(all.map(|s| &s.serial_tree));
+    if file.message(message).is_none() {
+        fail(format!("Unknown message {}", message));
+    }
+    file
+}
+
+fn read_stdin() -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Err(e) = std::io::stdin().read_to_end(&mut buf) {
+        exit_with(e);
+    }
+    buf
+}
+
+fn decode(args: DecodeArgs) {
+    let input = read_stdin();
+    if args.raw {
+        print!(
+            "{}",
+            protogen::inspect::decode_raw(&input).unwrap_or_else(|e| exit_with(e))
+        );
+        return;
+    }
+    let name = args.message.unwrap();
+    let file = load_schema(args.proto_file.unwrap(), args.includes, &name);
+    let msg = DynamicMessage::decode(&file, file.message(&name).unwrap(), &input)
+        .unwrap_or_else(|e| exit_with(e));
+    match args.format {
+        Format::Text => println!("{:#}", msg),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&Json(&msg)).unwrap()),
+    }
+}
+
+fn encode(args: EncodeArgs) {
+    let file = load_schema(args.proto_file, args.includes, &args.message);
+    let mut msg = DynamicMessage::new(&file, file.message(&args.message).unwrap());
+    let input = String::from_utf8(read_stdin()).unwrap_or_else(|e| exit_with(e));
+    let res = match args.format {
+        Format::Text => text::merge(&input, &mut msg).map_err(|e| e.to_string()),
+        Format::Json => serde_json::from_str(&input)
+            .map_err(|e| e.to_string())
+            .and_then(|json| inspect::merge_json(&mut msg, &json)),
+    };
+    if let Err(e) = res {
+        exit_with(e);
+    }
+    if let Err(e) = std::io::stdout().write_all(&msg.to_bytes()) {
+        exit_with(e);
     }
 }
 
 fn main() {
     let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Decode(args)) => return decode(args),
+        Some(Command::Encode(args)) => return encode(args),
+        None => (),
+    }
+    let mut config = protogen::Config::new();
+    config
+        .emit_rerun_if_changed(false)
+        .include_source_info(cli.include_source_info);
+    for dir in cli.includes {
+        config.include(dir);
+    }
+    for (name, path) in cli.plugins {
+        config.plugin(name, path);
+    }
+    for (lang, dir) in cli.outputs {
+        config.out(lang, dir);
+    }
+    for (lang, key, value) in cli.options {
+        config.opt(lang, key, value);
+    }
+    if let Some(dir) = cli.rust_out {
+        config.rust_out(dir);
+    }
+    if let Some(dir) = cli.csharp_out {
+        config.csharp_out(dir);
+    }
+    if cli.rust_async {
+        config.opt("rust", "async", "");
+    }
+    if cli.rust_no_std {
+        config.opt("rust", "no_std", "");
+    }
+    if let Some(dir) = cli.template_dir {
+        config.template_dir(dir);
+    }
+    if let Some(path) = cli.descriptor_set_out {
+        config.descriptor_set_out(path);
+    }
 
-    validate_out_dir("Rust", &cli.rust_out);
-    validate_out_dir("C#", &cli.csharp_out);
-
-    // Crawl input files
-    for file in cli.proto_files.iter() {
-        for entry in walkdir::WalkDir::new(file) {
-            let entry = entry.unwrap();
-            if entry.file_type().is_file() || entry.file_type().is_symlink() {
-                if entry.path().exists()
-                    && entry.path().extension().is_some_and(|ext| ext == "proto")
-                {
-                    // Parse and generate for each input file.
-                    let f = File::open(entry.path()).unwrap();
-                    let reader = BufReader::new(f);
-                    // Attempt parse. TODO/FIXME: Only supports ascii
-                    let mut p =
-                        crate::parser::Parser::new(reader.bytes().map(|b| char::from(b.unwrap())));
-                    let res = p.parse();
-                    let parse_tree = res.unwrap();
-                    let serial_tree =
-                        serializable_tree::SerializeTree::from_parse_tree(&parse_tree);
-
-                    let mut opts = File::options();
-                    let gen_file_opts = opts.create(true).write(true).truncate(true);
-                    if let Some(ref path) = cli.rust_out {
-                        let mut out_f = path.join(entry.path().file_stem().unwrap());
-                        out_f.set_extension("rs");
-                        let f = gen_file_opts.clone().open(out_f).unwrap();
-                        let mut writer = BufWriter::new(f);
-                        codegen_rust::RustCodeGen::gen(&mut writer, &parse_tree, &serial_tree)
-                            .unwrap();
-                    }
-                    if let Some(ref path) = cli.csharp_out {
-                        let mut out_f = path.join(entry.path().file_stem().unwrap());
-                        out_f.set_extension("cs");
-                        let f = gen_file_opts.clone().open(out_f).unwrap();
-                        let mut writer = BufWriter::new(f);
-                        codegen_csharp::CsharpCodeGen::gen(&mut writer, &parse_tree, &serial_tree)
-                            .unwrap();
-                    }
-                    println!("{}", entry.path().display());
-                }
-            }
+    let res = config.load(&cli.proto_files).and_then(|schemas| {
+        for schema in schemas.schemas.iter() {
+            println!("{}", schema.path.display());
         }
+        config.generate(&schemas)
+    });
+    match res {
+        Ok(()) => (),
+        Err(protogen::Error::Config(msg)) => fail(msg),
+        Err(e) => exit_with(e),
     }
 }
 
 // Used to build the file path. Verify it will work as expected.
 #[test]
 fn test_stem() {
+    use std::path::Path;
     let p = Path::new("dir/f.stem");
     let dir = Path::new("csharp/");
     let new = dir.join(p.file_stem().unwrap());
@@ -1,11 +1,18 @@
-use std::{borrow::Cow, collections::HashMap, str::FromStr};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+use protogen_runtime::descriptor::derived_method_id;
+pub use protogen_runtime::rpc::MAX_METHOD_ID;
 
 use crate::intern::{StringId, StringIntern};
 
-type MessageId = String;
+/// Field numbers are 29 bits, the key varint keeps three for the wire type.
+pub const MAX_FIELD_NUMBER: u32 = (1 << 29) - 1;
 
 // This one will likely be in a separate file and pub.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum FieldType {
     Int32,
     Int64,
@@ -15,33 +22,53 @@ pub enum FieldType {
     Enum(StringId),
     Message(StringId),
 
+    #[default]
     Undef,
 }
 
-impl Default for FieldType {
-    fn default() -> Self {
-        FieldType::Undef
+#[derive(Debug, PartialEq, Default)]
+pub struct Package {}
+
+/// Where a declaration was written, zero based `(line, column)` from its first
+/// token to just past its last, plus the comments directly above it.
+///
+/// Source positions don't change what a declaration means, so all
+/// `SourceInfo`s compare equal.
+#[derive(Debug, Clone, Default)]
+pub struct SourceInfo {
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+    pub leading_comments: String,
+}
+
+impl PartialEq for SourceInfo {
+    fn eq(&self, _other: &Self) -> bool {
+        true
     }
 }
 
-#[derive(Debug, PartialEq, Default)]
-pub struct Package {}
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Message {
     pub name: StringId,
     pub fields: Vec<Field>,
     pub messages: Vec<Message>,
     pub enums: Vec<Enum>,
+    /// From `option (drop_unknown_fields) = true;`, decoding discards fields
+    /// of newer schema versions instead of keeping them for re-encoding.
+    pub drop_unknown_fields: bool,
+    pub source: SourceInfo,
 }
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct Enum {
     pub name: StringId,
     pub variants: Vec<EnumVariant>,
+    pub source: SourceInfo,
 }
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct EnumVariant {
     pub name: StringId,
     pub id: u32,
+    pub source: SourceInfo,
 }
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct Field {
@@ -49,35 +76,55 @@ pub struct Field {
     pub idx: u32,
     pub ftype: FieldType,
     pub optional: bool,
+    pub repeated: bool,
+    /// Not a proto3 label, generated builders insist on these being set.
+    pub required: bool,
+    pub source: SourceInfo,
 }
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct Service {
     pub name: StringId,
     pub rpcs: Vec<Rpc>,
+    pub source: SourceInfo,
 }
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct Rpc {
     pub name: StringId,
     pub arg_type: StringId,
     pub ret_type: StringId,
+    /// Explicit id from `option (method_id) = N;`, derived from the name otherwise.
+    pub method_id: Option<u32>,
+    pub source: SourceInfo,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum TopLevelParse {
     SyntaxStatement, // Ignore for now...
+    Import(String),
     Package(Package),
     Service(Service),
     Message(Message),
     Enum(Enum),
+    MaxFrameSize(u32),
 }
 
 pub struct Parser<I: Iterator<Item = char>> {
     // TODO: Rather than use individual copies of strings change to IDs and use this intern struct.
     intern: StringIntern,
+    // Position of the next char to be read from `iterator`.
     linenum: u32,
     colnum: u32,
     iterator: I,
-    next_char: Option<char>,
+    // A peeked or returned char and its position.
+    next_char: Option<(char, (u32, u32))>,
+    // Position of the last char returned by `next_char`.
+    char_pos: (u32, u32),
+    // Position just past the last consumed char.
+    end_pos: (u32, u32),
+    // Start of the last token returned by `next_token`.
+    token_pos: (u32, u32),
+    // Comments directly above the last token returned by `next_non_ws_token`.
+    leading_comments: String,
 }
 
 #[derive(Debug, PartialEq)]
@@ -88,6 +135,9 @@ pub enum Token {
     BraceClose,
     ParensOpen,
     ParensClose,
+    BracketOpen,
+    BracketClose,
+    Comma,
     Quote,
     Equals,
     Number(String),
@@ -97,6 +147,27 @@ pub enum Token {
     Error(String),
 }
 
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Ident(s) | Token::Number(s) | Token::Comment(s) | Token::Error(s) => {
+                write!(f, "{}", s)
+            }
+            Token::Semicolon => write!(f, ";"),
+            Token::BraceOpen => write!(f, "{{"),
+            Token::BraceClose => write!(f, "}}"),
+            Token::ParensOpen => write!(f, "("),
+            Token::ParensClose => write!(f, ")"),
+            Token::BracketOpen => write!(f, "["),
+            Token::BracketClose => write!(f, "]"),
+            Token::Comma => write!(f, ","),
+            Token::Quote => write!(f, "\""),
+            Token::Equals => write!(f, "="),
+            Token::Whitespace => write!(f, "whitespace"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParseError {
     msg: String,
@@ -109,12 +180,15 @@ impl ParseError {
 
 #[derive(Default, Debug)]
 pub struct ParseTree {
+    /// Paths of the imported files, as written.
+    pub imports: Vec<String>,
     pub messages: Vec<Message>,
     pub enums: Vec<Enum>,
     pub services: Vec<Service>,
+    /// From `option (max_frame_size) = N;`, the largest frame generated
+    /// clients and dispatchers send or accept.
+    pub max_frame_size: Option<u32>,
     pub intern: StringIntern,
-    // TODO/Optiimization: Rather than duplicate, switch to a CoW topology of messages.
-    message_cache: HashMap<StringId, Message>,
 }
 
 impl std::fmt::Display for ParseError {
@@ -123,12 +197,10 @@ impl std::fmt::Display for ParseError {
     }
 }
 
-struct StagingParseTree {
-    str_cache: StringIntern,
-}
+impl std::error::Error for ParseError {}
 
 impl ParseTree {
-    pub fn get_str(&self, id: StringId) -> Cow<String> {
+    pub fn get_str(&self, id: StringId) -> Cow<'_, String> {
         // NOTE: Assumes that only one will ever be instantiated.
         self.intern.get_str(id).unwrap()
     }
@@ -138,42 +210,266 @@ impl ParseTree {
     //    self.message_cache.get(&id).and_then(|m| Some((*m).clone()))
     //}
 
+    /// Numeric id of an RPC, unique within its service.
+    ///
+    /// Uses the explicit `method_id` option when given, otherwise a stable hash
+    /// of `Service.Method` so that ids don't shift when RPCs are reordered.
+    pub fn method_id(&self, service: &Service, rpc: &Rpc) -> u32 {
+        match rpc.method_id {
+            Some(id) => id,
+            None => derived_method_id(&self.get_str(service.name), &self.get_str(rpc.name)),
+        }
+    }
+
+    /// Point field and rpc types at the fully qualified (`Outer.Inner`) name
+    /// of the message or enum they refer to, declared in this file or in
+    /// `imports`.
+    ///
+    /// Like protoc, names are looked up from the innermost enclosing message
+    /// outwards.
+    pub fn resolve_types(&mut self, imports: &[&ParseTree]) -> Result<(), ParseError> {
+        let mut types = HashMap::new();
+        for tree in imports.iter().copied().chain([&*self]) {
+            collect_types(&tree.intern, "", &tree.messages, &tree.enums, &mut types);
+        }
+
+        let mut messages = std::mem::take(&mut self.messages);
+        let res = self.resolve_message_types(&types, "", &mut messages);
+        self.messages = messages;
+        res?;
+
+        let mut services = std::mem::take(&mut self.services);
+        let res = services.iter_mut().try_for_each(|service| {
+            service.rpcs.iter_mut().try_for_each(|rpc| {
+                for ty in [&mut rpc.arg_type, &mut rpc.ret_type] {
+                    let name = self.get_str(*ty).as_ref().clone();
+                    match resolve_type(&types, "", &name) {
+                        Some((qualified, false)) => *ty = self.intern.get_id(&qualified),
+                        Some((_, true)) => {
+                            return Err(ParseError::new(format!(
+                                "Rpc {} uses enum {}, rpcs take and return messages",
+                                self.get_str(rpc.name),
+                                name
+                            )))
+                        }
+                        None => return Err(ParseError::new(format!("Unknown type {}", name))),
+                    }
+                }
+                Ok(())
+            })
+        });
+        self.services = services;
+        res
+    }
+
+    fn resolve_message_types(
+        &mut self,
+        types: &HashMap<String, bool>,
+        scope: &str,
+        messages: &mut [Message],
+    ) -> Result<(), ParseError> {
+        for msg in messages.iter_mut() {
+            let scope = qualify(scope, &self.get_str(msg.name));
+            for field in msg.fields.iter_mut() {
+                if let FieldType::Message(id) = field.ftype {
+                    let name = self.get_str(id).as_ref().clone();
+                    field.ftype = match resolve_type(types, &scope, &name) {
+                        Some((qualified, true)) => FieldType::Enum(self.intern.get_id(&qualified)),
+                        Some((qualified, false)) => {
+                            FieldType::Message(self.intern.get_id(&qualified))
+                        }
+                        None => {
+                            return Err(ParseError::new(format!(
+                                "Unknown type {} of field {}.{}",
+                                name,
+                                scope,
+                                self.get_str(field.name)
+                            )))
+                        }
+                    };
+                }
+            }
+            self.resolve_message_types(types, &scope, &mut msg.messages)?;
+        }
+        Ok(())
+    }
+
     fn validate(&self) -> Result<(), ParseError> {
-        // TODO: Validate RPCs use toplevel message types.
-        // TODO: Validate Messages use Messages within scope.
+        self.validate_numbers(&self.messages, &self.enums)?;
+        for service in self.services.iter() {
+            let mut ids = HashSet::new();
+            for rpc in service.rpcs.iter() {
+                if !ids.insert(self.method_id(service, rpc)) {
+                    return Err(ParseError::new(format!(
+                        "Duplicate method id for rpc {}.{}, set a unique `option (method_id)`",
+                        self.get_str(service.name),
+                        self.get_str(rpc.name)
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Field numbers and enum values the way protoc checks them, descriptor
+    // sets are read by tools that expect as much.
+    fn validate_numbers(&self, messages: &[Message], enums: &[Enum]) -> Result<(), ParseError> {
+        for enum_ in enums.iter() {
+            if let Some(first) = enum_.variants.first().filter(|v| v.id != 0) {
+                return Err(error_at(
+                    &first.source,
+                    format!("The first enum value must be 0, not {}", first.id),
+                ));
+            }
+            for (i, var) in enum_.variants.iter().enumerate() {
+                if let Some(other) = enum_.variants[..i].iter().find(|v| v.id == var.id) {
+                    return Err(error_at(
+                        &var.source,
+                        format!(
+                            "Enum value {} is already used by {}",
+                            var.id,
+                            self.get_str(other.name)
+                        ),
+                    ));
+                }
+            }
+        }
+        for msg in messages.iter() {
+            for (i, field) in msg.fields.iter().enumerate() {
+                let error = match field.idx {
+                    0 => Some("Field numbers start at 1".to_string()),
+                    19000..=19999 => Some(format!(
+                        "Field number {} is in the reserved range 19000 to 19999",
+                        field.idx
+                    )),
+                    idx if idx > MAX_FIELD_NUMBER => Some(format!(
+                        "Field number {} is above the maximum {}",
+                        idx, MAX_FIELD_NUMBER
+                    )),
+                    idx => msg.fields[..i].iter().find(|f| f.idx == idx).map(|other| {
+                        format!(
+                            "Field number {} is already used by {}",
+                            idx,
+                            self.get_str(other.name)
+                        )
+                    }),
+                };
+                if let Some(error) = error {
+                    return Err(error_at(&field.source, error));
+                }
+            }
+            self.validate_numbers(&msg.messages, &msg.enums)?;
+        }
         Ok(())
     }
 }
 
+// An error at the start of what `source` describes.
+fn error_at(source: &SourceInfo, msg: String) -> ParseError {
+    let (line, col) = source.start;
+    ParseError::new(format!("{}:{}: {}", line + 1, col + 1, msg))
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+// Map the qualified name of every message and enum to whether it's an enum.
+fn collect_types(
+    intern: &StringIntern,
+    scope: &str,
+    messages: &[Message],
+    enums: &[Enum],
+    types: &mut HashMap<String, bool>,
+) {
+    for enum_ in enums.iter() {
+        types.insert(qualify(scope, &intern.get_str(enum_.name).unwrap()), true);
+    }
+    for msg in messages.iter() {
+        let name = qualify(scope, &intern.get_str(msg.name).unwrap());
+        collect_types(intern, &name, &msg.messages, &msg.enums, types);
+        types.insert(name, false);
+    }
+}
+
+fn resolve_type(types: &HashMap<String, bool>, scope: &str, name: &str) -> Option<(String, bool)> {
+    let mut scope = scope;
+    loop {
+        let qualified = qualify(scope, name);
+        if let Some(is_enum) = types.get(&qualified) {
+            return Some((qualified, *is_enum));
+        }
+        if scope.is_empty() {
+            return None;
+        }
+        scope = scope.rfind('.').map_or("", |i| &scope[..i]);
+    }
+}
+
+/// Bounds of the `max_frame_size` option. Frames need room for a header and
+/// a status, and C# indexes them with an `int`.
+pub const MIN_FRAME_SIZE: u32 = 16;
+pub const MAX_FRAME_SIZE: u32 = i32::MAX as u32;
+
 const LINE_END: [char; 1] = ['\n'];
 
+// Like protoc, only comments directly above a declaration, without blank lines
+// in between, lead it. Comment markers are stripped.
+fn leading_comments(line: u32, comments: &[(u32, u32, String)]) -> String {
+    let mut first = comments.len();
+    let mut next_line = line;
+    while first > 0 && comments[first - 1].1 + 1 >= next_line {
+        first -= 1;
+        next_line = comments[first].0;
+    }
+    let mut text = String::new();
+    for (_, _, comment) in comments[first..].iter() {
+        match comment.strip_prefix("//") {
+            Some(line) => {
+                text.push_str(line);
+                text.push('\n');
+            }
+            None => text.push_str(comment.trim_start_matches("/*").trim_end_matches("*/")),
+        }
+    }
+    text
+}
+
 impl<I: Iterator<Item = char>> Parser<I> {
     pub fn unnext_char(&mut self, c: Option<char>) {
         assert_eq!(self.next_char, None);
-        self.next_char = c;
+        self.next_char = c.map(|c| (c, self.char_pos));
+        self.end_pos = self.char_pos;
     }
     pub fn next_char(&mut self) -> Option<char> {
-        let c;
-        if let Some(k) = self.next_char.take() {
-            return Some(k);
-        } else if let Some(k) = self.iterator.next() {
-            c = k;
-        } else {
-            return None;
+        let (c, pos) = match self.next_char.take() {
+            Some(next) => next,
+            None => self.read_char()?,
+        };
+        self.char_pos = pos;
+        self.end_pos = (pos.0, pos.1 + 1);
+        Some(c)
+    }
+    pub fn peek_char(&mut self) -> Option<char> {
+        if self.next_char.is_none() {
+            self.next_char = self.read_char();
         }
+        self.next_char.map(|(c, _)| c)
+    }
+    fn read_char(&mut self) -> Option<(char, (u32, u32))> {
+        let c = self.iterator.next()?;
+        let pos = (self.linenum, self.colnum);
         if LINE_END.contains(&c) {
             self.linenum += 1;
             self.colnum = 0;
+        } else {
+            self.colnum += 1;
         }
-        self.colnum += 1;
-        Some(c)
-    }
-    pub fn peek_char(&mut self) -> Option<char> {
-        if let Some(c) = self.next_char {
-            return Some(c);
-        }
-        self.next_char = self.iterator.next();
-        self.next_char
+        Some((c, pos))
     }
     pub fn try_next_char<F>(&mut self, cb: F) -> bool
     where
@@ -196,27 +492,91 @@ impl<I: Iterator<Item = char>> Parser<I> {
             colnum: 0,
             iterator: i,
             next_char: None,
+            char_pos: (0, 0),
+            end_pos: (0, 0),
+            token_pos: (0, 0),
+            leading_comments: String::new(),
         }
     }
 
     pub fn next_non_ws_token(&mut self) -> Option<Token> {
+        // Comments on the line the previous token ended on trail it instead.
+        let prev_line = (self.end_pos != (0, 0)).then_some(self.end_pos.0);
+        // (first line, last line, text) of the comments passed on the way.
+        let mut comments = Vec::new();
         loop {
             match self.next_token() {
                 Some(Token::Whitespace) => continue,
-                Some(Token::Comment(_)) => continue,
-                other => return other,
+                Some(Token::Comment(_)) if Some(self.token_pos.0) == prev_line => {
+                    continue;
+                }
+                Some(Token::Comment(text)) => {
+                    comments.push((self.token_pos.0, self.end_pos.0, text));
+                    continue;
+                }
+                other => {
+                    self.leading_comments = leading_comments(self.token_pos.0, &comments);
+                    return other;
+                }
             }
         }
     }
 
+    // Source info of a declaration whose first token was the last one returned
+    // by `next_non_ws_token`, completed with `finish_source`.
+    fn start_source(&mut self) -> SourceInfo {
+        SourceInfo {
+            start: self.token_pos,
+            end: self.token_pos,
+            leading_comments: std::mem::take(&mut self.leading_comments),
+        }
+    }
+    fn finish_source(&self, source: &mut SourceInfo) {
+        source.end = self.end_pos;
+    }
+
+    // An error at the start of the last token.
+    fn error(&self, msg: String) -> ParseError {
+        let (line, col) = self.token_pos;
+        ParseError::new(format!("{}:{}: {}", line + 1, col + 1, msg))
+    }
+    fn unexpected(&self, tok: Option<Token>, expected: &str) -> ParseError {
+        match tok {
+            Some(tok) => self.error(format!("Expected {}, found {}", expected, tok)),
+            None => self.error(format!("Expected {}, found end of file", expected)),
+        }
+    }
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        match self.next_non_ws_token() {
+            Some(tok) if tok == expected => Ok(()),
+            tok => Err(self.unexpected(tok, &expected.to_string())),
+        }
+    }
+    fn expect_ident(&mut self) -> Result<String, ParseError> {
+        match self.next_non_ws_token() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            tok => Err(self.unexpected(tok, "a name")),
+        }
+    }
+    fn expect_number(&mut self, what: &str) -> Result<u32, ParseError> {
+        match self.next_non_ws_token() {
+            Some(Token::Number(num)) => num
+                .parse()
+                .map_err(|_| self.error(format!("Invalid {} {}", what, num))),
+            tok => Err(self.unexpected(tok, what)),
+        }
+    }
+
     pub fn next_token(&mut self) -> Option<Token> {
-        while let Some(c) = self.next_char() {
+        if let Some(c) = self.next_char() {
+            self.token_pos = self.char_pos;
             // Ident.
             if c.is_ascii_alphabetic() || c == '_' {
                 let mut s = String::new();
                 s.push(c);
                 while let Some(c) = self.next_char() {
-                    if !(c == '_' || c.is_ascii_alphanumeric()) {
+                    // Dots join the parts of qualified names like `Outer.Inner`.
+                    if !(c == '_' || c == '.' || c.is_ascii_alphanumeric()) {
                         self.unnext_char(Some(c));
                         break;
                     }
@@ -286,29 +646,52 @@ impl<I: Iterator<Item = char>> Parser<I> {
                 '}' => Token::BraceClose,
                 '(' => Token::ParensOpen,
                 ')' => Token::ParensClose,
+                '[' => Token::BracketOpen,
+                ']' => Token::BracketClose,
+                ',' => Token::Comma,
                 '"' => Token::Quote,
                 '=' => Token::Equals,
-                _ => Token::Error(format!("Unexpected char")),
+                _ => Token::Error(String::from("Unexpected char")),
             });
         }
         None
     }
 
+    /// Parse a file that doesn't refer to types of other files.
     pub fn parse(&mut self) -> Result<ParseTree, ParseError> {
+        let mut tree = self.parse_unresolved()?;
+        tree.resolve_types(&[])?;
+        Ok(tree)
+    }
+
+    /// Parse a file, leaving the types of fields and rpcs as written until
+    /// [`ParseTree::resolve_types`] is called with the files it imports.
+    pub fn parse_unresolved(&mut self) -> Result<ParseTree, ParseError> {
         let mut tree = ParseTree::default();
         match self.next_parse() {
             Some(Ok(TopLevelParse::SyntaxStatement)) => (),
             Some(Err(e)) => return Err(e),
-            _ => todo!(), // Error - Empty file or missing syntx statement before other defs.
+            _ => return Err(self.error("Expected syntax = \"proto3\"; first".into())),
         }
         // TODO: Package support: parse
         loop {
             match self.next_parse() {
                 Some(Ok(item)) => match item {
+                    TopLevelParse::Import(path) => tree.imports.push(path),
                     TopLevelParse::Service(s) => tree.services.push(s),
                     TopLevelParse::Message(m) => tree.messages.push(m),
                     TopLevelParse::Enum(e) => tree.enums.push(e),
-                    _ => todo!(), // Error
+                    TopLevelParse::MaxFrameSize(size) => {
+                        if tree.max_frame_size.replace(size).is_some() {
+                            return Err(self.error("Duplicate max_frame_size option".into()));
+                        }
+                    }
+                    TopLevelParse::SyntaxStatement => {
+                        return Err(self.error("Duplicate syntax statement".into()))
+                    }
+                    TopLevelParse::Package(_) => {
+                        return Err(self.error("Packages are not supported".into()))
+                    }
                 },
                 Some(Err(e)) => return Err(e),
                 None => break,
@@ -322,122 +705,185 @@ impl<I: Iterator<Item = char>> Parser<I> {
 
     // TODO: Parser will need to do multiple passes:
     pub fn next_parse(&mut self) -> Option<Result<TopLevelParse, ParseError>> {
-        while let Some(tok) = self.next_non_ws_token() {
+        if let Some(tok) = self.next_non_ws_token() {
             return match tok {
                 Token::Ident(ident) => match ident.as_str() {
                     "syntax" => Some(self.parse_syntax()),
+                    "import" => Some(self.parse_import()),
                     "service" => Some(self.parse_service()),
-                    "enum" => Some(
-                        self.parse_enum()
-                            .and_then(|msg| (Ok(TopLevelParse::Enum(msg)))),
-                    ),
-                    "message" => Some(
-                        self.parse_message()
-                            .and_then(|msg| (Ok(TopLevelParse::Message(msg)))),
-                    ),
-                    _ => panic!("Unexpected token"), // TODO handle unexpected token nicely
+                    "enum" => Some(self.parse_enum().map(TopLevelParse::Enum)),
+                    "message" => Some(self.parse_message().map(TopLevelParse::Message)),
+                    "option" => Some(self.parse_file_option()),
+                    _ => Some(Err(self.error(format!("Unexpected {}", ident)))),
                 },
-                _ => panic!("Unexpected token"), // TODO handle unexpected token nicely
+                tok => Some(Err(self.error(format!("Unexpected {}", tok)))),
             };
         }
         None
     }
 
     pub fn parse_syntax(&mut self) -> Result<TopLevelParse, ParseError> {
-        if self.next_non_ws_token() != Some(Token::Equals) {
-            todo!() // Error
-        }
-        if self.next_non_ws_token() != Some(Token::Quote) {
-            todo!() // Error
-        }
+        self.expect(Token::Equals)?;
+        self.expect(Token::Quote)?;
         if self.next_token() != Some(Token::Ident(String::from("proto3"))) {
-            todo!() // Error
+            return Err(self.error("Only proto3 syntax is supported".into()));
         }
-        if self.next_non_ws_token() != Some(Token::Quote) {
-            todo!() // Error
+        self.expect(Token::Quote)?;
+        self.expect(Token::Semicolon)?;
+        Ok(TopLevelParse::SyntaxStatement)
+    }
+
+    // Entered after the `option` keyword at the top level.
+    fn parse_file_option(&mut self) -> Result<TopLevelParse, ParseError> {
+        let (name, value) = self.parse_option()?;
+        if name != "(max_frame_size)" {
+            return Err(self.error(format!("Unknown file option {}", name)));
         }
-        if self.next_non_ws_token() != Some(Token::Semicolon) {
-            todo!() // Error
+        value
+            .parse::<u32>()
+            .ok()
+            .filter(|size| (MIN_FRAME_SIZE..=MAX_FRAME_SIZE).contains(size))
+            .map(TopLevelParse::MaxFrameSize)
+            .ok_or_else(|| {
+                self.error(format!(
+                    "Invalid max_frame_size {}, expected a number from {} up to {}",
+                    value, MIN_FRAME_SIZE, MAX_FRAME_SIZE
+                ))
+            })
+    }
+
+    // Entered after the `import` keyword. Only plain imports of a path in
+    // double quotes are supported.
+    fn parse_import(&mut self) -> Result<TopLevelParse, ParseError> {
+        self.expect(Token::Quote)?;
+        let mut path = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') => break,
+                Some(c) if !LINE_END.contains(&c) => path.push(c),
+                _ => return Err(self.error("Unterminated import path".into())),
+            }
         }
-        return Ok(TopLevelParse::SyntaxStatement);
+        self.expect(Token::Semicolon)?;
+        Ok(TopLevelParse::Import(path))
     }
 
     fn parse_service(&mut self) -> Result<TopLevelParse, ParseError> {
-        let mut service = Service::default();
-        match self.next_non_ws_token() {
-            Some(Token::Ident(ident)) => service.name = self.intern.get_id(&ident),
-            _ => todo!(), // Error
-        }
-        if self.next_non_ws_token() != Some(Token::BraceOpen) {
-            todo!() // Error
-        }
+        let mut service = Service {
+            source: self.start_source(),
+            ..Default::default()
+        };
+        let ident = self.expect_ident()?;
+        service.name = self.intern.get_id(&ident);
+        self.expect(Token::BraceOpen)?;
         loop {
             // Now parse rpcs or braceclose
             let tok = self.next_non_ws_token();
             match tok {
-                Some(Token::Ident(maybe_rpc_ident)) => {
-                    if maybe_rpc_ident.as_str() != "rpc" {
-                        todo!() /*Error*/
-                    }
-                    service.rpcs.push(self.parse_rpc()?);
-                }
+                Some(Token::Ident(ident)) if ident == "rpc" => service.rpcs.push(self.parse_rpc()?),
                 Some(Token::BraceClose) => break, // Done parsing
-                _ => todo!(),                     // Error
+                tok => return Err(self.unexpected(tok, "rpc or }")),
             }
         }
+        self.finish_source(&mut service.source);
         Ok(TopLevelParse::Service(service))
     }
 
     fn parse_rpc(&mut self) -> Result<Rpc, ParseError> {
-        let mut rpc = Rpc::default();
+        let mut rpc = Rpc {
+            source: self.start_source(),
+            ..Default::default()
+        };
         // Entered after RPC has been parsed
-        match self.next_non_ws_token() {
-            Some(Token::Ident(rpc_name)) => rpc.name = self.intern.get_id(&rpc_name),
-            _ => todo!(), // Error
-        }
-        if self.next_non_ws_token() != Some(Token::ParensOpen) {
-            todo!() // Error
+        let rpc_name = self.expect_ident()?;
+        rpc.name = self.intern.get_id(&rpc_name);
+        self.expect(Token::ParensOpen)?;
+        let arg = self.expect_ident()?;
+        rpc.arg_type = self.intern.get_id(&arg);
+        self.expect(Token::ParensClose)?;
+        if self.expect_ident()? != "returns" {
+            return Err(self.error("Expected returns".into()));
         }
+        self.expect(Token::ParensOpen)?;
+        let ret = self.expect_ident()?;
+        rpc.ret_type = self.intern.get_id(&ret);
+        self.expect(Token::ParensClose)?;
         match self.next_non_ws_token() {
-            Some(Token::Ident(arg)) => rpc.arg_type = self.intern.get_id(&arg),
-            _ => todo!(), // Error
+            Some(Token::Semicolon) => (),
+            Some(Token::BraceOpen) => self.parse_rpc_options(&mut rpc)?,
+            tok => return Err(self.unexpected(tok, "; or {")),
         }
-        if self.next_non_ws_token() != Some(Token::ParensClose) {
-            todo!() // Error
-        }
-        match self.next_non_ws_token() {
-            Some(Token::Ident(returns_kw)) => {
-                if returns_kw.as_str() != "returns" {
-                    todo!() // Error
+        self.finish_source(&mut rpc.source);
+        Ok(rpc)
+    }
+
+    // Entered after the opening brace of an rpc body.
+    fn parse_rpc_options(&mut self, rpc: &mut Rpc) -> Result<(), ParseError> {
+        loop {
+            match self.next_non_ws_token() {
+                Some(Token::BraceClose) => return Ok(()),
+                Some(Token::Semicolon) => continue,
+                Some(Token::Ident(ident)) if ident == "option" => {
+                    let (name, value) = self.parse_option()?;
+                    match name.as_str() {
+                        "(method_id)" => {
+                            let id = value.parse::<u32>().ok().filter(|id| *id <= MAX_METHOD_ID);
+                            match id {
+                                Some(id) => rpc.method_id = Some(id),
+                                None => {
+                                    return Err(self.error(format!(
+                                        "Invalid method_id {}, expected a number up to {}",
+                                        value, MAX_METHOD_ID
+                                    )))
+                                }
+                            }
+                        }
+                        _ => return Err(self.error(format!("Unknown rpc option {}", name))),
+                    }
                 }
+                tok => return Err(self.unexpected(tok, "option or }")),
             }
-            _ => todo!(), // Error
-        }
-        if self.next_non_ws_token() != Some(Token::ParensOpen) {
-            todo!() // Error
-        }
-        match self.next_non_ws_token() {
-            Some(Token::Ident(ret)) => rpc.ret_type = self.intern.get_id(&ret),
-            _ => todo!(), // Error
-        }
-        if self.next_non_ws_token() != Some(Token::ParensClose) {
-            todo!() // Error
-        }
-        if self.next_non_ws_token() != Some(Token::Semicolon) {
-            todo!() // Error
         }
-        Ok(rpc)
+    }
+
+    // Parses `name = value;` or `(name) = value;` after the `option` keyword.
+    fn parse_option(&mut self) -> Result<(String, String), ParseError> {
+        let option = self.parse_option_value()?;
+        self.expect(Token::Semicolon)?;
+        Ok(option)
+    }
+
+    // `name = value` of an option statement or of the `[...]` list after a
+    // field number.
+    fn parse_option_value(&mut self) -> Result<(String, String), ParseError> {
+        let name = match self.next_non_ws_token() {
+            Some(Token::Ident(ident)) => ident,
+            Some(Token::ParensOpen) => {
+                let ident = match self.next_non_ws_token() {
+                    Some(Token::Ident(ident)) => ident,
+                    tok => return Err(self.unexpected(tok, "option name")),
+                };
+                self.expect(Token::ParensClose)?;
+                format!("({})", ident)
+            }
+            tok => return Err(self.unexpected(tok, "option name")),
+        };
+        self.expect(Token::Equals)?;
+        let value = match self.next_non_ws_token() {
+            Some(Token::Ident(v)) | Some(Token::Number(v)) => v,
+            tok => return Err(self.unexpected(tok, "option value")),
+        };
+        Ok((name, value))
     }
 
     fn parse_enum(&mut self) -> Result<Enum, ParseError> {
-        let mut enum_ = Enum::default();
-        match self.next_non_ws_token() {
-            Some(Token::Ident(ident)) => enum_.name = self.intern.get_id(&ident),
-            _ => todo!(), // Error
-        }
-        if self.next_non_ws_token() != Some(Token::BraceOpen) {
-            todo!() // Error
-        }
+        let mut enum_ = Enum {
+            source: self.start_source(),
+            ..Default::default()
+        };
+        let ident = self.expect_ident()?;
+        enum_.name = self.intern.get_id(&ident);
+        self.expect(Token::BraceOpen)?;
 
         // TODO: Now parse fields, messages or braceclose
         loop {
@@ -445,95 +891,128 @@ impl<I: Iterator<Item = char>> Parser<I> {
             match tok {
                 Some(Token::BraceClose) => break,
                 Some(Token::Ident(ident)) => enum_.variants.push(self.parse_enum_variant(ident)?),
-                _ => todo!(), // Error
+                tok => return Err(self.unexpected(tok, "enum value or }")),
             }
         }
+        self.finish_source(&mut enum_.source);
         Ok(enum_)
     }
 
     fn parse_message(&mut self) -> Result<Message, ParseError> {
-        let mut message = Message::default();
-        match self.next_non_ws_token() {
-            Some(Token::Ident(ident)) => message.name = self.intern.get_id(&ident),
-            _ => todo!(), // Error
-        }
-        if self.next_non_ws_token() != Some(Token::BraceOpen) {
-            todo!() // Error
-        }
+        let mut message = Message {
+            source: self.start_source(),
+            ..Default::default()
+        };
+        let ident = self.expect_ident()?;
+        message.name = self.intern.get_id(&ident);
+        self.expect(Token::BraceOpen)?;
 
         // TODO: Now parse fields, messages or braceclose
         loop {
             let tok = self.next_non_ws_token();
             match tok {
                 Some(Token::BraceClose) => break,
-                Some(Token::Ident(ident)) => {
-                    match ident.as_str() {
-                        "message" => message.messages.push(self.parse_message()?),
-                        "enum" => message.enums.push(self.parse_enum()?),
-                        "optional" => {
-                            if let Some(Token::Ident(ident)) = self.next_non_ws_token() {
-                                let mut field = self.parse_field_of_type(ident)?;
-                                field.optional = true;
-                                message.fields.push(field);
-                            } else {
-                                todo!() // Error
+                Some(Token::Ident(ident)) => match ident.as_str() {
+                    "message" => message.messages.push(self.parse_message()?),
+                    "enum" => message.enums.push(self.parse_enum()?),
+                    "option" => {
+                        let (name, value) = self.parse_option()?;
+                        match (name.as_str(), value.as_str()) {
+                            ("(drop_unknown_fields)", "true") => message.drop_unknown_fields = true,
+                            ("(drop_unknown_fields)", "false") => {
+                                message.drop_unknown_fields = false
+                            }
+                            _ => {
+                                return Err(self
+                                    .error(format!("Unknown message option {} = {}", name, value)))
                             }
                         }
-                        _ => message.fields.push(self.parse_field_of_type(ident)?),
                     }
-                }
-                _ => todo!(), // Error
+                    "required" => {
+                        return Err(self
+                            .error("proto3 has no required label, use [(required) = true]".into()))
+                    }
+                    label @ ("optional" | "repeated") => {
+                        let source = self.start_source();
+                        let ident = self.expect_ident()?;
+                        let mut field = self.parse_field_of_type(ident, label)?;
+                        field.source.start = source.start;
+                        field.source.leading_comments = source.leading_comments;
+                        message.fields.push(field);
+                    }
+                    _ => message.fields.push(self.parse_field_of_type(ident, "")?),
+                },
+                tok => return Err(self.unexpected(tok, "field, message, enum or }")),
             }
         }
+        self.finish_source(&mut message.source);
         Ok(message)
     }
 
     fn parse_enum_variant(&mut self, variant_name: String) -> Result<EnumVariant, ParseError> {
-        let mut var = EnumVariant::default();
-        var.name = self.intern.get_id(variant_name.as_str());
+        let mut var = EnumVariant {
+            name: self.intern.get_id(variant_name.as_str()),
+            source: self.start_source(),
+            ..Default::default()
+        };
         // TODO: equals, number, semicolon
-        if self.next_non_ws_token() != Some(Token::Equals) {
-            todo!() // Error
-        }
-        if let Some(Token::Number(num)) = self.next_non_ws_token() {
-            var.id = num
-                .parse::<u32>()
-                .or(Err(ParseError::new("Invalid number for enum.".into())))?;
-        } else {
-            todo!() // Error
-        }
-        if self.next_non_ws_token() != Some(Token::Semicolon) {
-            todo!() // Error
-        }
+        self.expect(Token::Equals)?;
+        var.id = self.expect_number("enum value")?;
+        self.expect(Token::Semicolon)?;
+        self.finish_source(&mut var.source);
         Ok(var)
     }
 
-    fn parse_field_of_type(&mut self, type_name: String) -> Result<Field, ParseError> {
-        let mut field = Field::default();
-        field.ftype = match type_name.as_str() {
-            "int32" => FieldType::Int32,
-            "int64" => FieldType::Int64,
-            "uint32" => FieldType::Uint32,
-            "uint64" => FieldType::Uint64,
-            "string" => FieldType::String,
-            ident => FieldType::Message(self.intern.get_id(&ident)), //Error
-        };
-        field.name = match self.next_non_ws_token() {
-            Some(Token::Ident(fname)) => self.intern.get_id(&fname),
-            _ => todo!(), // Error
+    // `label` is `optional`, `repeated` or empty.
+    fn parse_field_of_type(&mut self, type_name: String, label: &str) -> Result<Field, ParseError> {
+        let mut field = Field {
+            source: self.start_source(),
+            ftype: match type_name.as_str() {
+                "int32" => FieldType::Int32,
+                "int64" => FieldType::Int64,
+                "uint32" => FieldType::Uint32,
+                "uint64" => FieldType::Uint64,
+                "string" => FieldType::String,
+                ident => FieldType::Message(self.intern.get_id(ident)), //Error
+            },
+            ..Default::default()
         };
-        if self.next_non_ws_token() != Some(Token::Equals) {
-            todo!() // Error
+        let name = self.expect_ident()?;
+        field.name = self.intern.get_id(&name);
+        self.expect(Token::Equals)?;
+        field.idx = self.expect_number("field number")?;
+        field.optional = label == "optional";
+        field.repeated = label == "repeated";
+        match self.next_non_ws_token() {
+            Some(Token::Semicolon) => {}
+            Some(Token::BracketOpen) => {
+                self.parse_field_options(&mut field, label)?;
+                self.expect(Token::Semicolon)?;
+            }
+            tok => return Err(self.unexpected(tok, "; or [")),
         }
-        field.idx = match self.next_non_ws_token() {
-            Some(Token::Number(n)) => n.parse().unwrap(), // TODO: handle failure to convert to u32
+        self.finish_source(&mut field.source);
+        Ok(field)
+    }
 
-            _ => todo!(), // Error
-        };
-        if self.next_non_ws_token() != Some(Token::Semicolon) {
-            todo!() // Error
+    // Entered after the `[` following a field number, up to the closing `]`.
+    fn parse_field_options(&mut self, field: &mut Field, label: &str) -> Result<(), ParseError> {
+        loop {
+            let (name, value) = self.parse_option_value()?;
+            match (name.as_str(), value.as_str()) {
+                ("(required)", "true") if !label.is_empty() => {
+                    return Err(self.error(format!("{} fields can't be required", label)))
+                }
+                ("(required)", "true") => field.required = true,
+                ("(required)", "false") => field.required = false,
+                _ => return Err(self.error(format!("Unknown field option {} = {}", name, value))),
+            }
+            match self.next_non_ws_token() {
+                Some(Token::Comma) => {}
+                Some(Token::BracketClose) => return Ok(()),
+                tok => return Err(self.unexpected(tok, ", or ]")),
+            }
         }
-        Ok(field)
     }
 }
 
@@ -555,8 +1034,10 @@ fn solo_enum_test() {
     assert_eq!(
         p.next_parse(),
         Some(Ok(TopLevelParse::Enum(Enum {
+            source: SourceInfo::default(),
             name: p.intern.get_id("KeyCode"),
             variants: vec![EnumVariant {
+                source: SourceInfo::default(),
                 name: p.intern.get_id("Space"),
                 id: 1
             }]
@@ -575,11 +1056,14 @@ fn solo_service_test() {
     assert_eq!(
         p.next_parse(),
         Some(Ok(TopLevelParse::Service(Service {
+            source: SourceInfo::default(),
             name: p.intern.get_id("hi"),
             rpcs: vec![Rpc {
+                source: SourceInfo::default(),
                 name: p.intern.get_id("do"),
                 arg_type: p.intern.get_id("something"),
                 ret_type: p.intern.get_id("null"),
+                method_id: None,
             }]
         })))
     );
@@ -602,39 +1086,54 @@ fn solo_message_test() {
     assert_eq!(
         p.next_parse(),
         Some(Ok(TopLevelParse::Message(Message {
+            source: SourceInfo::default(),
             name: p.intern.get_id("HiReq"),
             fields: vec![
                 Field {
+                    source: SourceInfo::default(),
                     name: p.intern.get_id("msg"),
                     idx: 1,
                     ftype: FieldType::String,
                     optional: true,
+                    repeated: false,
+                    required: false,
                 },
                 Field {
+                    source: SourceInfo::default(),
                     name: p.intern.get_id("idx"),
                     idx: 2,
                     ftype: FieldType::Message(p.intern.get_id("inner")),
                     optional: false,
+                    repeated: false,
+                    required: false,
                 }
             ],
             messages: vec![Message {
+                source: SourceInfo::default(),
                 name: p.intern.get_id("inner"),
                 fields: vec![Field {
+                    source: SourceInfo::default(),
                     name: p.intern.get_id("inner_field"),
                     idx: 1,
                     ftype: FieldType::Int32,
                     optional: false,
+                    repeated: false,
+                    required: false,
                 },],
                 messages: vec![],
                 enums: vec![],
+                drop_unknown_fields: false,
             }],
             enums: vec![Enum {
+                source: SourceInfo::default(),
                 name: p.intern.get_id("KeyCode"),
                 variants: vec![EnumVariant {
+                    source: SourceInfo::default(),
                     name: p.intern.get_id("Space"),
                     id: 1
                 }]
             }],
+            drop_unknown_fields: false,
         })))
     );
     assert_eq!(p.next_parse(), None);
@@ -769,3 +1268,323 @@ service hi {
     assert_eq!(Some(Token::BraceClose), p.next_token());
     assert_eq!(None, p.next_token());
 }
+
+#[test]
+fn rpc_method_id_option_test() {
+    let src = "service hi {
+        rpc do(something) returns (null) {
+            option (method_id) = 7;
+        }
+        rpc other(something) returns (null);
+    }";
+    let mut p = Parser::new(src.chars());
+    assert_eq!(
+        p.next_parse(),
+        Some(Ok(TopLevelParse::Service(Service {
+            source: SourceInfo::default(),
+            name: p.intern.get_id("hi"),
+            rpcs: vec![
+                Rpc {
+                    source: SourceInfo::default(),
+                    name: p.intern.get_id("do"),
+                    arg_type: p.intern.get_id("something"),
+                    ret_type: p.intern.get_id("null"),
+                    method_id: Some(7),
+                },
+                Rpc {
+                    source: SourceInfo::default(),
+                    name: p.intern.get_id("other"),
+                    arg_type: p.intern.get_id("something"),
+                    ret_type: p.intern.get_id("null"),
+                    method_id: None,
+                }
+            ]
+        })))
+    );
+    assert_eq!(p.next_parse(), None);
+}
+
+#[test]
+fn derived_method_id_test() {
+    let src = "syntax = \"proto3\";
+    service A { rpc Do(X) returns (X); }
+    service B { rpc Do(X) returns (X); }
+    message X {}";
+    let tree = Parser::new(src.chars()).parse().unwrap();
+    let a = tree.method_id(&tree.services[0], &tree.services[0].rpcs[0]);
+    let b = tree.method_id(&tree.services[1], &tree.services[1].rpcs[0]);
+    assert_ne!(a, b);
+    assert!(a <= MAX_METHOD_ID && b <= MAX_METHOD_ID);
+    // Derived ids are part of the wire format and must never change.
+    assert_eq!(a, 1652886891);
+}
+
+#[test]
+fn duplicate_method_id_test() {
+    let src = "syntax = \"proto3\";
+    service A {
+        rpc Do(X) returns (X) { option (method_id) = 1; }
+        rpc Again(X) returns (X) { option (method_id) = 1; }
+    }
+    message X {}";
+    assert!(Parser::new(src.chars()).parse().is_err());
+}
+
+#[test]
+fn resolve_types_test() {
+    let src = "syntax = \"proto3\";
+    message Outer {
+        message Inner {
+            Kind kind = 1;
+        }
+        enum Kind {
+            A = 0;
+        }
+        repeated Inner inners = 1;
+        Outer.Kind kind = 2;
+    }
+    service S { rpc Do(Outer.Inner) returns (Outer); }";
+    let tree = Parser::new(src.chars()).parse().unwrap();
+    let mut intern = tree.intern.clone();
+    let outer = &tree.messages[0];
+    assert_eq!(
+        outer.fields[0].ftype,
+        FieldType::Message(intern.get_id("Outer.Inner"))
+    );
+    assert!(outer.fields[0].repeated);
+    assert_eq!(
+        outer.fields[1].ftype,
+        FieldType::Enum(intern.get_id("Outer.Kind"))
+    );
+    assert_eq!(
+        outer.messages[0].fields[0].ftype,
+        FieldType::Enum(intern.get_id("Outer.Kind"))
+    );
+    assert_eq!(
+        tree.services[0].rpcs[0].arg_type,
+        intern.get_id("Outer.Inner")
+    );
+}
+
+#[test]
+fn unknown_type_test() {
+    let src = "syntax = \"proto3\";
+    message A {
+        B b = 1;
+    }";
+    assert!(Parser::new(src.chars()).parse().is_err());
+    let src = "syntax = \"proto3\";
+    enum E {
+        X = 0;
+    }
+    service S { rpc Do(E) returns (E); }";
+    assert!(Parser::new(src.chars()).parse().is_err());
+}
+
+#[test]
+fn resolve_imported_types_test() {
+    let common = "syntax = \"proto3\";\nmessage Point { enum Axis { X = 0; } }";
+    let common = Parser::new(common.chars()).parse().unwrap();
+    let src = "syntax = \"proto3\";
+    import \"common.proto\";
+    message A { Point p = 1; Point.Axis axis = 2; }
+    service S { rpc Do(Point) returns (A); }";
+    let mut tree = Parser::new(src.chars()).parse_unresolved().unwrap();
+    assert!(tree.resolve_types(&[]).is_err());
+    let mut tree = Parser::new(src.chars()).parse_unresolved().unwrap();
+    tree.resolve_types(&[&common]).unwrap();
+    let mut intern = tree.intern.clone();
+    let a = &tree.messages[0];
+    assert_eq!(
+        a.fields[0].ftype,
+        FieldType::Message(intern.get_id("Point"))
+    );
+    assert_eq!(
+        a.fields[1].ftype,
+        FieldType::Enum(intern.get_id("Point.Axis"))
+    );
+    assert_eq!(tree.services[0].rpcs[0].arg_type, intern.get_id("Point"));
+}
+
+#[test]
+fn source_info_test() {
+    let src = "syntax = \"proto3\";
+// Detached.
+
+// About A
+message A {
+    int32 a = 1; // Trails a.
+    /* About b */ optional string b = 2;
+}";
+    let tree = Parser::new(src.chars()).parse().unwrap();
+    let msg = &tree.messages[0];
+    assert_eq!(msg.source.start, (4, 0));
+    assert_eq!(msg.source.end, (7, 1));
+    assert_eq!(msg.source.leading_comments, " About A\n");
+    let (a, b) = (&msg.fields[0].source, &msg.fields[1].source);
+    assert_eq!((a.start, a.end), ((5, 4), (5, 16)));
+    assert_eq!(a.leading_comments, "");
+    assert_eq!((b.start, b.end), ((6, 18), (6, 40)));
+    assert_eq!(b.leading_comments, " About b ");
+}
+
+#[test]
+fn required_test() {
+    let src = "syntax = \"proto3\";
+message A { string a = 1 [(required) = true]; int32 b = 2 [(required) = false]; }";
+    let tree = Parser::new(src.chars()).parse().unwrap();
+    let fields = &tree.messages[0].fields;
+    assert!(fields[0].required && !fields[0].optional);
+    assert!(!fields[1].required);
+
+    let error = |src: &str| Parser::new(src.chars()).parse().unwrap_err().to_string();
+    assert_eq!(
+        error("syntax = \"proto3\";\nmessage A { required string a = 1; }"),
+        "2:13: proto3 has no required label, use [(required) = true]"
+    );
+    assert_eq!(
+        error("syntax = \"proto3\";\nmessage A { repeated string a = 1 [(required) = true]; }"),
+        "2:49: repeated fields can't be required"
+    );
+    assert_eq!(
+        error("syntax = \"proto3\";\nmessage A { string a = 1 [(packed) = true]; }"),
+        "2:38: Unknown field option (packed) = true"
+    );
+    assert_eq!(
+        error("syntax = \"proto3\";\nmessage A { string a = 1 [(required) = true; }"),
+        "2:44: Expected , or ], found ;"
+    );
+}
+
+#[test]
+fn drop_unknown_fields_option_test() {
+    let src = "syntax = \"proto3\";
+    message A { option (drop_unknown_fields) = true; int32 a = 1; }
+    message B { int32 b = 1; }";
+    let tree = Parser::new(src.chars()).parse().unwrap();
+    assert!(tree.messages[0].drop_unknown_fields);
+    assert!(!tree.messages[1].drop_unknown_fields);
+    let src = "syntax = \"proto3\";\nmessage A { option (packed) = true; }";
+    assert!(Parser::new(src.chars()).parse().is_err());
+}
+
+#[test]
+fn max_frame_size_option_test() {
+    let src = "syntax = \"proto3\";\noption (max_frame_size) = 1024;\nmessage A {}";
+    let tree = Parser::new(src.chars()).parse().unwrap();
+    assert_eq!(tree.max_frame_size, Some(1024));
+    let src = "syntax = \"proto3\";\nmessage A {}";
+    assert_eq!(
+        Parser::new(src.chars()).parse().unwrap().max_frame_size,
+        None
+    );
+
+    let error = |src: &str| Parser::new(src.chars()).parse().unwrap_err().to_string();
+    assert!(error("syntax = \"proto3\";\noption (max_frame_size) = 8;")
+        .contains("Invalid max_frame_size 8"));
+    assert!(error(
+        "syntax = \"proto3\";\noption (max_frame_size) = 64;\noption (max_frame_size) = 64;"
+    )
+    .contains("Duplicate max_frame_size option"));
+}
+
+#[test]
+fn import_test() {
+    let src = "syntax = \"proto3\";\nimport \"common/types.proto\";\nmessage A {}";
+    let tree = Parser::new(src.chars()).parse().unwrap();
+    assert_eq!(tree.imports, ["common/types.proto"]);
+}
+
+#[test]
+fn parse_error_test() {
+    let error = |src: &str| Parser::new(src.chars()).parse().unwrap_err().to_string();
+    assert_eq!(error(""), "1:1: Expected syntax = \"proto3\"; first");
+    assert_eq!(
+        error("syntax = \"proto3\";\nmessage A {\n  int32 a = x;\n}"),
+        "3:13: Expected field number, found x"
+    );
+    assert_eq!(
+        error("syntax = \"proto3\";\nservice S { rpc A(B) returns (C) }"),
+        "2:34: Expected ; or {, found }"
+    );
+    assert_eq!(error("syntax = \"proto3\";\nfoo"), "2:1: Unexpected foo");
+}
+
+#[test]
+fn rpc_option_error_test() {
+    let src = "syntax = \"proto3\";
+service A {
+    rpc Do(X) returns (X) { option (method_id) = x; }
+    rpc Again(X) returns (X) { option (speed) = 1; }
+}";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "3:51: Invalid method_id x, expected a number up to {}",
+            MAX_METHOD_ID
+        )
+    );
+    let src = "syntax = \"proto3\";\nservice A { rpc Do(X) returns (X) { option (method_id) 1; } }";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(err.to_string(), "2:56: Expected =, found 1");
+}
+
+#[test]
+fn field_number_zero_test() {
+    let src = "syntax = \"proto3\";\nmessage A {\n  int32 a = 0;\n}";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(err.to_string(), "3:3: Field numbers start at 1");
+}
+
+#[test]
+fn duplicate_field_number_test() {
+    let src = "syntax = \"proto3\";\nmessage A {\n  int32 a = 1;\n  optional string b = 1;\n}";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(err.to_string(), "4:3: Field number 1 is already used by a");
+    // Nested messages number their fields on their own.
+    let src = "syntax = \"proto3\";\nmessage A {\n  int32 a = 1;\n  message B { int32 b = 1; }\n}";
+    assert!(Parser::new(src.chars()).parse().is_ok());
+    let src = "syntax = \"proto3\";\nmessage A {\n  message B {\n    int32 a = 1;\n    int32 b = 1;\n  }\n}";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(err.to_string(), "5:5: Field number 1 is already used by a");
+}
+
+#[test]
+fn max_field_number_test() {
+    let src = "syntax = \"proto3\";\nmessage A {\n  int32 a = 536870911;\n}";
+    assert!(Parser::new(src.chars()).parse().is_ok());
+    let src = "syntax = \"proto3\";\nmessage A {\n  int32 a = 536870912;\n}";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "3:3: Field number 536870912 is above the maximum 536870911"
+    );
+}
+
+#[test]
+fn reserved_field_number_test() {
+    let src = "syntax = \"proto3\";\nmessage A {\n  int32 a = 19500;\n}";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "3:3: Field number 19500 is in the reserved range 19000 to 19999"
+    );
+}
+
+#[test]
+fn duplicate_enum_value_test() {
+    let src = "syntax = \"proto3\";\nenum E {\n  A = 0;\n  B = 1;\n  C = 1;\n}";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(err.to_string(), "5:3: Enum value 1 is already used by B");
+}
+
+#[test]
+fn first_enum_value_test() {
+    let src = "syntax = \"proto3\";\nmessage A {\n  enum E {\n    A = 1;\n  }\n}";
+    let err = Parser::new(src.chars()).parse().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "4:5: The first enum value must be 0, not 1"
+    );
+}
@@ -0,0 +1,343 @@
+// Runs external generators speaking the protoc plugin protocol: a
+// `CodeGeneratorRequest` on stdin, a `CodeGeneratorResponse` on stdout.
+
+use std::{
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use protogen_runtime::wire::Decoder;
+
+use crate::{
+    codegen::{CodeGenerator, OutputFile, SchemaSet},
+    descriptor::{self, FieldWriter},
+    parser::Message,
+};
+
+// CodeGeneratorResponse.Feature
+const FEATURE_PROTO3_OPTIONAL: u64 = 1;
+
+/// An external generator, e.g. an existing `protoc-gen-*` executable.
+pub struct PluginGenerator {
+    name: String,
+    path: PathBuf,
+    /// Sent comma separated as the request parameter, like protoc does for
+    /// `--NAME_opt`.
+    parameters: Vec<String>,
+}
+
+impl PluginGenerator {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+            parameters: Vec::new(),
+        }
+    }
+
+    fn request(&self, schemas: &SchemaSet) -> Vec<u8> {
+        let mut e = FieldWriter::default();
+        for schema in schemas.schemas.iter() {
+            e.string(1, &schema.name);
+        }
+        if !self.parameters.is_empty() {
+            e.string(2, &self.parameters.join(","));
+        }
+        // protoc always hands plugins the source info, for doc comments, and
+        // every file after the files it imports.
+        for schema in schemas.dependency_order() {
+            let file = descriptor::file_descriptor(&schema.name, &schema.parse_tree, true);
+            e.bytes(15, &file);
+        }
+        e.into_bytes()
+    }
+
+    fn run(&self, request: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to run plugin {}: {}", self.path.display(), e),
+                )
+            })?;
+        // Write from another thread so a plugin answering before it read
+        // all of its input can't deadlock us.
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = std::thread::spawn(move || stdin.write_all(&request));
+        let mut response = Vec::new();
+        child.stdout.take().unwrap().read_to_end(&mut response)?;
+        let status = child.wait()?;
+        // A plugin may exit without reading its input, that's fine.
+        let _ = writer.join();
+        if !status.success() {
+            return Err(std::io::Error::other(format!(
+                "Plugin {} failed: {}",
+                self.name, status
+            )));
+        }
+        Ok(response)
+    }
+}
+
+/// Whether a message of `schemas` has proto3 `optional` fields, which
+/// plugins have to declare support for.
+fn uses_proto3_optional(schemas: &SchemaSet) -> bool {
+    fn any_optional(messages: &[Message]) -> bool {
+        messages
+            .iter()
+            .any(|msg| msg.fields.iter().any(|f| f.optional) || any_optional(&msg.messages))
+    }
+    schemas
+        .schemas
+        .iter()
+        .any(|schema| any_optional(&schema.parse_tree.messages))
+}
+
+/// Decode a `CodeGeneratorResponse` into the files to write. Like protoc,
+/// fails if the schemas use proto3 `optional` and the plugin doesn't say it
+/// supports them.
+fn parse_response(
+    name: &str,
+    response: &[u8],
+    proto3_optional: bool,
+) -> std::io::Result<Vec<OutputFile>> {
+    decode_response(response, proto3_optional)
+        .map_err(|e| std::io::Error::other(format!("Plugin {}: {}", name, e)))
+}
+
+// Plugins only write below the output directory, relative paths without `..`.
+fn below_output_dir(path: &Path) -> bool {
+    !path.has_root()
+        && path
+            .components()
+            .all(|c| !matches!(c, Component::ParentDir | Component::Prefix(_)))
+}
+
+fn decode_response(
+    response: &[u8],
+    proto3_optional: bool,
+) -> Result<Vec<OutputFile>, Box<dyn std::error::Error>> {
+    let mut files: Vec<OutputFile> = Vec::new();
+    let mut features = 0;
+    for field in Decoder::new(response) {
+        match field? {
+            (1, error) => return Err(error.str(1)?.into()),
+            (2, value) => features = value.varint::<u64>(2)?,
+            (15, file) => {
+                let (mut path, mut insertion_point, mut contents) = ("", "", "");
+                for field in Decoder::new(file.bytes(15)?) {
+                    match field? {
+                        (1, v) => path = v.str(1)?,
+                        (2, v) => insertion_point = v.str(2)?,
+                        (15, v) => contents = v.str(15)?,
+                        _ => (),
+                    }
+                }
+                if !insertion_point.is_empty() {
+                    return Err("insertion points are not supported".into());
+                }
+                // Files without a name continue the previous one.
+                match (path, files.last_mut()) {
+                    ("", Some(last)) => last.contents.push_str(contents),
+                    ("", None) => return Err("first file has no name".into()),
+                    _ if !below_output_dir(Path::new(path)) => {
+                        return Err(format!("{} is outside the output directory", path).into())
+                    }
+                    _ => files.push(OutputFile {
+                        path: PathBuf::from(path),
+                        contents: contents.to_string(),
+                    }),
+                }
+            }
+            // Anything newer.
+            _ => (),
+        }
+    }
+    if proto3_optional && features & FEATURE_PROTO3_OPTIONAL == 0 {
+        return Err(
+            "the schemas use proto3 optional fields, which the plugin doesn't support".into(),
+        );
+    }
+    Ok(files)
+}
+
+impl CodeGenerator for PluginGenerator {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn file_extension(&self) -> &'static str {
+        // Up to the plugin.
+        ""
+    }
+    fn options(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if value.is_empty() {
+            self.parameters.push(key.to_string());
+        } else {
+            self.parameters.push(format!("{}={}", key, value));
+        }
+        Ok(())
+    }
+    fn generate(&self, schemas: &SchemaSet) -> std::io::Result<Vec<OutputFile>> {
+        let response = self.run(self.request(schemas))?;
+        parse_response(&self.name, &response, uses_proto3_optional(schemas))
+    }
+}
+
+#[test]
+fn test_request() {
+    use protogen_runtime::wire::Value;
+
+    let text = "syntax = \"proto3\"; message A { int32 a = 1; }";
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
+    let schemas = SchemaSet {
+        schemas: vec![crate::codegen::Schema::new(
+            PathBuf::from("dir/a.proto"),
+            tree,
+        )],
+        ..Default::default()
+    };
+    let mut plugin = PluginGenerator::new("test", "protoc-gen-test");
+    plugin.set_option("paths", "source_relative").unwrap();
+    plugin.set_option("verbose", "").unwrap();
+
+    let request = plugin.request(&schemas);
+    let fields: Vec<_> = Decoder::new(&request).collect::<Result<_, _>>().unwrap();
+    assert_eq!(fields[0], (1, Value::Len(b"dir/a.proto")));
+    assert_eq!(fields[1], (2, Value::Len(b"paths=source_relative,verbose")));
+    assert_eq!(fields[2].0, 15);
+    let file = Decoder::new(fields[2].1.bytes(15).unwrap()).next().unwrap();
+    assert_eq!(file.unwrap(), (1, Value::Len(b"dir/a.proto")));
+}
+
+#[test]
+fn test_request_imports() {
+    use crate::codegen::Schema;
+
+    let b = "syntax = \"proto3\"; message B { int32 b = 1; }";
+    let a = "syntax = \"proto3\"; import \"common/b.proto\"; message A { B b = 1; }";
+    let b = crate::parser::Parser::new(b.chars()).parse().unwrap();
+    let mut a = crate::parser::Parser::new(a.chars())
+        .parse_unresolved()
+        .unwrap();
+    a.resolve_types(&[&b]).unwrap();
+    let schemas = SchemaSet {
+        schemas: vec![Schema::new(PathBuf::from("a.proto"), a)],
+        imports: vec![Schema::new(PathBuf::from("common/b.proto"), b)],
+    };
+
+    let request = PluginGenerator::new("test", "protoc-gen-test").request(&schemas);
+    let (mut generate, mut files) = (Vec::new(), Vec::new());
+    for field in Decoder::new(&request) {
+        match field.unwrap() {
+            (1, v) => generate.push(v.str(1).unwrap()),
+            (15, v) => {
+                let name = Decoder::new(v.bytes(15).unwrap()).find_map(|f| match f {
+                    Ok((1, v)) => v.str(1).ok(),
+                    _ => None,
+                });
+                files.push(name.unwrap());
+            }
+            _ => (),
+        }
+    }
+    // Imports are described for linking, not generated.
+    assert_eq!(generate, ["a.proto"]);
+    assert_eq!(files, ["common/b.proto", "a.proto"]);
+}
+
+#[test]
+fn test_response() {
+    let mut e = FieldWriter::default();
+    e.varint(2, 1u64);
+    e.message(15, |f| {
+        f.string(1, "a.txt");
+        f.string(15, "one ");
+    });
+    e.message(15, |f| f.string(15, "two"));
+    let files = parse_response("test", &e.into_bytes(), true).unwrap();
+    assert_eq!(
+        files,
+        [OutputFile {
+            path: PathBuf::from("a.txt"),
+            contents: "one two".to_string(),
+        }]
+    );
+
+    let mut e = FieldWriter::default();
+    e.string(1, "bad input");
+    let err = parse_response("test", &e.into_bytes(), false).unwrap_err();
+    assert_eq!(err.to_string(), "Plugin test: bad input");
+}
+
+#[test]
+fn test_response_outside_output_dir() {
+    let error = |path: &str| {
+        let mut e = FieldWriter::default();
+        e.message(15, |f| f.string(1, path));
+        parse_response("test", &e.into_bytes(), false)
+            .unwrap_err()
+            .to_string()
+    };
+    assert_eq!(
+        error("/etc/passwd"),
+        "Plugin test: /etc/passwd is outside the output directory"
+    );
+    assert_eq!(
+        error("gen/../../x.rs"),
+        "Plugin test: gen/../../x.rs is outside the output directory"
+    );
+}
+
+#[test]
+fn test_response_proto3_optional() {
+    let text = "syntax = \"proto3\"; message A { message B { optional int32 b = 1; } }";
+    let tree = crate::parser::Parser::new(text.chars()).parse().unwrap();
+    let schemas = SchemaSet {
+        schemas: vec![crate::codegen::Schema::new(PathBuf::from("a.proto"), tree)],
+        ..Default::default()
+    };
+    assert!(uses_proto3_optional(&schemas));
+    assert!(!uses_proto3_optional(&SchemaSet::default()));
+
+    let mut e = FieldWriter::default();
+    e.message(15, |f| f.string(1, "a.txt"));
+    let err = parse_response("test", &e.into_bytes(), true).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Plugin test: the schemas use proto3 optional fields, which the plugin doesn't support"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_plugin() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Replies with a single file out.txt containing "hi".
+    let dir = std::env::temp_dir().join(format!("protogen-plugin-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("protoc-gen-hi");
+    std::fs::write(
+        &script,
+        "#!/bin/sh\ncat >/dev/null\nprintf '\\172\\015\\012\\007out.txt\\172\\002hi'\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let plugin = PluginGenerator::new("hi", &script);
+    let files = plugin.generate(&SchemaSet::default()).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, PathBuf::from("out.txt"));
+    assert_eq!(files[0].contents, "hi");
+
+    let plugin = PluginGenerator::new("missing", dir.join("no-such-plugin"));
+    assert!(plugin.generate(&SchemaSet::default()).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
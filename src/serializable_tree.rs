@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use protogen_runtime::wire::{len_field_size, varint_size};
 use serde::Serialize;
 
 use crate::{
@@ -5,22 +8,33 @@ use crate::{
     parser::{FieldType, ParseTree},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SerializeTree {
     pub messages: Vec<Message>,
     pub enums: Vec<Enum>,
     pub services: Vec<Service>,
+    /// From the `max_frame_size` file option.
+    pub max_frame_size: Option<u32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Default, Serialize)]
 pub struct Message {
+    /// Fully qualified, e.g. `Outer.Inner` for nested messages.
     pub name: String,
     pub fields: Vec<Field>,
     pub messages: Vec<Message>,
     pub enums: Vec<Enum>,
+    /// Don't keep fields of newer schema versions, whatever the generator
+    /// options say.
+    pub drop_unknown_fields: bool,
+    /// Upper bound of the encoded length. Only known for messages that drop
+    /// unknown fields and have no strings or repeated fields, directly or in
+    /// nested messages.
+    pub max_size: Option<usize>,
 }
 #[derive(Debug, PartialEq, Default, Clone, Serialize)]
 pub struct Enum {
+    /// Fully qualified, e.g. `Outer.Kind` for enums nested in messages.
     pub name: String,
     pub variants: Vec<EnumVariant>,
 }
@@ -34,8 +48,43 @@ pub struct EnumVariant {
 pub struct Field {
     pub name: String,
     pub idx: u32,
+    /// The proto type, a scalar like `int32` or a qualified message or enum
+    /// name. Templates map it to a language type with `fmt_type`.
     pub ftype: String,
+    /// How the field is encoded, `ftype` doesn't tell messages from enums.
+    pub kind: FieldKind,
     pub optional: bool,
+    pub repeated: bool,
+    /// Builders insist on these being set, the encoding doesn't care.
+    pub required: bool,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    #[default]
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    String,
+    Enum,
+    Message,
+}
+
+impl FieldKind {
+    fn of(ft: &FieldType) -> Self {
+        match ft {
+            FieldType::Int32 => FieldKind::Int32,
+            FieldType::Int64 => FieldKind::Int64,
+            FieldType::Uint32 => FieldKind::Uint32,
+            FieldType::Uint64 => FieldKind::Uint64,
+            FieldType::String => FieldKind::String,
+            FieldType::Message(_) => FieldKind::Message,
+            FieldType::Enum(_) => FieldKind::Enum,
+            FieldType::Undef => unimplemented!(),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Default, Clone, Serialize)]
@@ -49,25 +98,41 @@ pub struct Rpc {
     pub name: String,
     pub arg_type: String,
     pub ret_type: String,
+    /// Wire id of the request message (`method_id << 1`).
+    pub arg_id: u32,
+    /// Wire id of the response message (`method_id << 1 | 1`).
+    pub ret_id: u32,
+    /// The response has no fields, callers may skip waiting for it.
+    pub ret_empty: bool,
 }
 
-fn field_type_to_rust_str(intern: &StringIntern, ft: &FieldType) -> String {
+fn field_type_to_proto_str(intern: &StringIntern, ft: &FieldType) -> String {
     match ft {
-        FieldType::Int32 => "i32".into(),
-        FieldType::Int64 => "i64".into(),
-        FieldType::Uint32 => "u32".into(),
-        FieldType::Uint64 => "u64".into(),
-        FieldType::String => "String".into(),
+        FieldType::Int32 => "int32".into(),
+        FieldType::Int64 => "int64".into(),
+        FieldType::Uint32 => "uint32".into(),
+        FieldType::Uint64 => "uint64".into(),
+        FieldType::String => "string".into(),
         FieldType::Message(m) => intern.get_str(*m).unwrap().as_ref().clone(),
         FieldType::Enum(e) => intern.get_str(*e).unwrap().as_ref().clone(),
         FieldType::Undef => unimplemented!(),
     }
 }
 
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
 impl SerializeTree {
-    fn rollup_enum(tree: &ParseTree, cur_enum: &crate::parser::Enum) -> Enum {
-        let mut enum_ = Enum::default();
-        enum_.name = tree.get_str(cur_enum.name).as_ref().clone();
+    fn rollup_enum(tree: &ParseTree, scope: &str, cur_enum: &crate::parser::Enum) -> Enum {
+        let mut enum_ = Enum {
+            name: qualify(scope, &tree.get_str(cur_enum.name)),
+            ..Default::default()
+        };
         for var in cur_enum.variants.iter() {
             enum_.variants.push(EnumVariant {
                 name: tree.get_str(var.name).as_ref().clone(),
@@ -76,68 +141,173 @@ impl SerializeTree {
         }
         enum_
     }
-    fn rollup_message(tree: &ParseTree, msg: &crate::parser::Message) -> Message {
+    fn rollup_message(tree: &ParseTree, scope: &str, msg: &crate::parser::Message) -> Message {
+        let name = qualify(scope, &tree.get_str(msg.name));
         let mut fields = Vec::new();
         for field in msg.fields.iter() {
             fields.push(Field {
                 name: tree.get_str(field.name).as_ref().clone(),
                 idx: field.idx,
-                ftype: field_type_to_rust_str(&tree.intern, &field.ftype),
+                ftype: field_type_to_proto_str(&tree.intern, &field.ftype),
+                kind: FieldKind::of(&field.ftype),
                 optional: field.optional,
+                repeated: field.repeated,
+                required: field.required,
             })
         }
         let mut messages = Vec::new();
         for message in msg.messages.iter() {
-            messages.push(SerializeTree::rollup_message(tree, message));
+            messages.push(SerializeTree::rollup_message(tree, &name, message));
         }
 
         let mut enums = Vec::new();
         for enum_ in msg.enums.iter() {
-            enums.push(SerializeTree::rollup_enum(tree, enum_));
+            enums.push(SerializeTree::rollup_enum(tree, &name, enum_));
         }
 
         Message {
-            name: tree.get_str(msg.name).as_ref().clone(),
+            name,
             fields,
             messages,
             enums,
+            drop_unknown_fields: msg.drop_unknown_fields,
+            max_size: None,
+        }
+    }
+    fn all_messages<'a>(messages: &'a [Message], all: &mut HashMap<&'a str, &'a Message>) {
+        for msg in messages.iter() {
+            all.insert(&msg.name, msg);
+            Self::all_messages(&msg.messages, all);
+        }
+    }
+    // `visiting` holds the messages `name` is nested in, recursive ones are
+    // unbounded.
+    fn max_size<'a>(
+        all: &HashMap<&'a str, &'a Message>,
+        name: &'a str,
+        visiting: &mut Vec<&'a str>,
+    ) -> Option<usize> {
+        let msg = all.get(name)?;
+        if !msg.drop_unknown_fields || visiting.contains(&name) {
+            return None;
+        }
+        visiting.push(name);
+        let mut size = 0;
+        for field in msg.fields.iter() {
+            let key = varint_size((field.idx as u64) << 3);
+            size += match field.kind {
+                _ if field.repeated => None,
+                FieldKind::String => None,
+                FieldKind::Uint32 => Some(key + 5),
+                // Negative int32 and enum values are sign extended.
+                FieldKind::Int32 | FieldKind::Int64 | FieldKind::Uint64 | FieldKind::Enum => {
+                    Some(key + 10)
+                }
+                FieldKind::Message => Self::max_size(all, &field.ftype, visiting)
+                    .map(|len| len_field_size(field.idx, len)),
+            }?;
+        }
+        visiting.pop();
+        Some(size)
+    }
+    fn set_max_sizes(messages: &mut [Message], sizes: &HashMap<String, Option<usize>>) {
+        for msg in messages.iter_mut() {
+            msg.max_size = sizes[&msg.name];
+            Self::set_max_sizes(&mut msg.messages, sizes);
+        }
+    }
+    fn compute_max_sizes(&mut self) {
+        let mut all = HashMap::new();
+        Self::all_messages(&self.messages, &mut all);
+        let sizes: HashMap<_, _> = all
+            .keys()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    Self::max_size(&all, name, &mut Vec::new()),
+                )
+            })
+            .collect();
+        Self::set_max_sizes(&mut self.messages, &sizes);
+    }
+    /// Qualified names of all messages, nested ones after their parent.
+    pub fn message_names(&self) -> Vec<&str> {
+        fn collect<'a>(messages: &'a [Message], names: &mut Vec<&'a str>) {
+            for msg in messages.iter() {
+                names.push(&msg.name);
+                collect(&msg.messages, names);
+            }
+        }
+        let mut names = Vec::new();
+        collect(&self.messages, &mut names);
+        names
+    }
+    /// Qualified names of all enums, top level ones first.
+    pub fn enum_names(&self) -> Vec<&str> {
+        fn collect<'a>(messages: &'a [Message], names: &mut Vec<&'a str>) {
+            for msg in messages.iter() {
+                names.extend(msg.enums.iter().map(|e| e.name.as_str()));
+                collect(&msg.messages, names);
+            }
+        }
+        let mut names: Vec<_> = self.enums.iter().map(|e| e.name.as_str()).collect();
+        collect(&self.messages, &mut names);
+        names
+    }
+    /// Drop unknown fields of every message, for the generator option.
+    pub fn drop_unknown_fields(&mut self) {
+        fn drop(messages: &mut [Message]) {
+            for msg in messages.iter_mut() {
+                msg.drop_unknown_fields = true;
+                drop(&mut msg.messages);
+            }
+        }
+        drop(&mut self.messages);
+        self.compute_max_sizes();
+    }
+    fn field_counts<'a>(messages: &'a [Message], counts: &mut HashMap<&'a str, usize>) {
+        for msg in messages.iter() {
+            counts.insert(&msg.name, msg.fields.len());
+            Self::field_counts(&msg.messages, counts);
         }
     }
     pub fn from_parse_tree(tree: &ParseTree) -> Self {
         let mut messages = Vec::new();
         for msg in tree.messages.iter() {
-            let mut fields = Vec::new();
-            for field in msg.fields.iter() {
-                fields.push(Field {
-                    name: tree.get_str(field.name).as_ref().clone(),
-                    idx: field.idx,
-                    ftype: field_type_to_rust_str(&tree.intern, &field.ftype),
-                    optional: field.optional,
-                })
-            }
-            messages.push(Self::rollup_message(tree, msg));
+            messages.push(Self::rollup_message(tree, "", msg));
         }
         let mut enums = Vec::new();
         for enum_ in tree.enums.iter() {
-            enums.push(SerializeTree::rollup_enum(tree, enum_));
+            enums.push(SerializeTree::rollup_enum(tree, "", enum_));
         }
+        let mut field_counts = HashMap::new();
+        Self::field_counts(&messages, &mut field_counts);
         let mut services = Vec::new();
         for svc in tree.services.iter() {
-            let mut service = Service::default();
-            service.name = tree.get_str(svc.name).as_ref().clone();
+            let mut service = Service {
+                name: tree.get_str(svc.name).as_ref().clone(),
+                ..Default::default()
+            };
             for rpc in svc.rpcs.iter() {
+                let method_id = tree.method_id(svc, rpc);
                 service.rpcs.push(Rpc {
                     name: tree.get_str(rpc.name).as_ref().clone(),
                     arg_type: tree.get_str(rpc.arg_type).as_ref().clone(),
                     ret_type: tree.get_str(rpc.ret_type).as_ref().clone(),
+                    arg_id: method_id << 1,
+                    ret_id: method_id << 1 | 1,
+                    ret_empty: field_counts.get(tree.get_str(rpc.ret_type).as_str()) == Some(&0),
                 });
             }
             services.push(service);
         }
-        Self {
+        let mut serial = Self {
             messages,
             services,
             enums,
-        }
+            max_frame_size: tree.max_frame_size,
+        };
+        serial.compute_max_sizes();
+        serial
     }
 }
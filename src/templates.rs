@@ -0,0 +1,44 @@
+// Loading of the built-in templates and user overrides.
+
+use std::path::Path;
+
+use tera::Tera;
+
+/// A template compiled into the binary, `(name, source)`.
+pub type Template = (&'static str, &'static str);
+
+/// Build a [`Tera`] from the built-in templates. Files in `override_dir`
+/// named like one of them replace it.
+pub fn load(builtin: &[Template], override_dir: Option<&Path>) -> std::io::Result<Tera> {
+    let mut sources = Vec::new();
+    for (name, source) in builtin.iter() {
+        let path = override_dir.map(|dir| dir.join(name));
+        match path {
+            Some(path) if path.is_file() => sources.push((*name, std::fs::read_to_string(path)?)),
+            _ => sources.push((*name, source.to_string())),
+        }
+    }
+    let mut tera = Tera::default();
+    tera.add_raw_templates(sources)
+        .map_err(std::io::Error::other)?;
+    Ok(tera)
+}
+
+#[test]
+fn test_override() {
+    let dir = std::env::temp_dir().join(format!("protogen-templates-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("b"), "custom {% include \"a\" %}").unwrap();
+    let builtin = [("a", "builtin a"), ("b", "builtin b")];
+
+    let tera = load(&builtin, None).unwrap();
+    let ctx = tera::Context::new();
+    assert_eq!(tera.render("b", &ctx).unwrap(), "builtin b");
+    let tera = load(&builtin, Some(&dir)).unwrap();
+    assert_eq!(tera.render("a", &ctx).unwrap(), "builtin a");
+    assert_eq!(tera.render("b", &ctx).unwrap(), "custom builtin a");
+
+    std::fs::write(dir.join("a"), "{% if %}").unwrap();
+    assert!(load(&builtin, Some(&dir)).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,67 +1,50 @@
+{%- if options.rust_no_std %}
+{# The crate including this file needs `extern crate alloc;`. #}
+#[allow(unused_imports)]
+use alloc::{format, string::String, vec, vec::Vec};
+pub use protogen_runtime::rpc::*;
+{%- elif options.rust_async %}
+pub use protogen_runtime::async_client::RpcClient;
+pub use protogen_runtime::rpc::*;
+pub use protogen_runtime::AsyncTransport;
+{%- else %}
+pub use protogen_runtime::client::RpcClient;
+pub use protogen_runtime::rpc::*;
+pub use protogen_runtime::Transport;
+{%- endif %}
+use protogen_runtime::wire::Varint as _;
+{%- if not options.rust_no_std %}
 use serde::{Deserialize, Serialize};
+{%- endif %}
 
-type MessageId = &'static str;
+{# Fails to compile against a protogen-runtime this code wasn't generated for. #}
+const _: () = protogen_runtime::VERSION_{{ runtime_version }};
 
-pub trait ProtoMessage
-where
-    Self: Sized,
-{
-    /// Serialize the message and a header into a buffer
-    /// TODO: Need to serialize RPCs, not Messages...
-    fn serialize_body_into(&self, buf: &mut [u8]) -> Result<(), ()>;
-    fn serialized_size(&self) -> usize;
-    fn try_deserialize_body(buf: &[u8]) -> Option<Self>;
-    fn deserialize_body(buf: &[u8]) -> Self {
-        Self::try_deserialize_body(buf).unwrap()
-    }
-}
-pub trait ProtoRpcArg
-where
-    Self: Sized,
-{
-    type Arg: ProtoMessage;
-    fn serialize_rpc_msg_into(&self, buf: &mut [u8]) -> Result<(), ()>;
-    fn serialized_size(&self) -> usize;
-    fn try_deserialize_body(buf: &[u8]) -> Option<<Self as ProtoRpcArg>::Arg> {
-        Self::Arg::try_deserialize_body(buf)
-    }
-}
+{%- if max_frame_size %}
+/// Largest frame clients and dispatchers send or accept, from the `max_frame_size` option.
+pub const MAX_FRAME_SIZE: usize = {{ max_frame_size }};
+{%- else %}
+/// Largest frame clients and dispatchers send or accept.
+pub const MAX_FRAME_SIZE: usize = FRAME_BUFFER_SIZE;
+{%- endif %}
+/// Largest encoded message, leaving room for the header and status of a reply.
+pub const MAX_MESSAGE_SIZE: usize = MAX_FRAME_SIZE - REPLY_OVERHEAD;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct RpcHeader {
-    msg_id: String,
-}
-
-impl ProtoMessage for RpcHeader {
-    fn serialize_body_into(&self, buf: &mut [u8]) -> Result<(), ()> {
-        // Not supported for headers. Nothing to serialize.
-        Err(())
-    }
-    fn serialized_size(&self) -> usize {
-        serde_json::to_string(self).unwrap().len()
-    }
-    fn try_deserialize_body(buf: &[u8]) -> Option<Self> {
-        let bounds = find_struct_bounds(buf)?;
-        serde_json::from_slice(&buf[..bounds]).ok()
-    }
-}
-
-// Required for Serde JSON serialization
-pub fn find_struct_bounds(r: &[u8]) -> Option<usize> {
-    let mut iter = r.iter();
-    if iter.next() == Some(&('{'.to_ascii_lowercase() as u8)) {
-        let mut count = 1;
-        let mut indent = 1;
-        for b in iter {
-            count += 1;
-            // TODO/FIXME: Need to support detecting if inside a string.
-            if &('}'.to_ascii_lowercase() as u8) == b {
-                indent -= 1;
-                if indent == 0 {
-                    return Some(count);
-                }
-            }
-        }
-    }
-    None
-}
+/// Descriptors of everything in this file, to look types up by name.
+pub static FILE_DESCRIPTOR: protogen_runtime::descriptor::FileDescriptor = protogen_runtime::descriptor::FileDescriptor {
+    messages: protogen_runtime::descriptor::Cow::Borrowed(&[
+    {%- for name in message_names %}
+        {{fmt_const(name=fmt_struct(name=name) ~ "Descriptor")}},
+    {%- endfor %}
+    ]),
+    enums: protogen_runtime::descriptor::Cow::Borrowed(&[
+    {%- for name in enum_names %}
+        {{fmt_const(name=fmt_struct(name=name) ~ "Descriptor")}},
+    {%- endfor %}
+    ]),
+    services: protogen_runtime::descriptor::Cow::Borrowed(&[
+    {%- for service in services %}
+        {{fmt_const(name=fmt_struct(name=service.name) ~ "Descriptor")}},
+    {%- endfor %}
+    ]),
+};
@@ -0,0 +1,203 @@
+// Generates code for every schema in tests/fixtures, and for the schema the
+// examples share, and compares it with the expected output checked in to
+// tests/fixtures. Run with `PROTOGEN_BLESS=1` to
+// write the current output as the expected one instead, then review the diff.
+
+use std::path::{Path, PathBuf};
+
+use protogen::{
+    codegen::{Registry, Schema, SchemaSet},
+    parser::Parser,
+};
+
+/// `(key, value)` pairs as given to `--opt`.
+type Options = &'static [(&'static str, &'static str)];
+
+/// Generator, its options and the extension of the expected output.
+const VARIANTS: &[(&str, Options, &str)] = &[
+    ("rust", &[], "rs"),
+    ("rust", &[("async", "")], "async.rs"),
+    ("rust", &[("no_std", "")], "no_std.rs"),
+    ("rust", &[("builders", "")], "builders.rs"),
+    ("csharp", &[], "cs"),
+    ("csharp", &[("builders", "")], "builders.cs"),
+];
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn fixtures() -> Vec<PathBuf> {
+    let mut protos: Vec<_> = std::fs::read_dir(fixture_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "proto"))
+        .chain([Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/hello.proto")])
+        .collect();
+    protos.sort_by_key(|path| path.file_name().map(ToOwned::to_owned));
+    protos
+}
+
+/// The outputs of all variants for `proto`, named like the expected files in
+/// tests/fixtures.
+fn generate(proto: &Path) -> Vec<(PathBuf, String)> {
+    let text = std::fs::read_to_string(proto).unwrap();
+    let tree = Parser::new(text.chars())
+        .parse()
+        .unwrap_or_else(|e| panic!("{}: {}", proto.display(), e));
+    let schemas = SchemaSet {
+        // Named as if generated from within the fixture dir.
+        schemas: vec![Schema::new(proto.file_name().unwrap().into(), tree)],
+        ..Default::default()
+    };
+    let mut outputs = Vec::new();
+    for (name, options, extension) in VARIANTS {
+        let mut registry = Registry::with_builtin();
+        let generator = registry.get_mut(name).unwrap();
+        for (key, value) in options.iter() {
+            generator.set_option(key, value).unwrap();
+        }
+        let mut files = generator.generate(&schemas).unwrap();
+        assert_eq!(files.len(), 1);
+        let expected = fixture_dir().join(proto.file_name().unwrap());
+        outputs.push((
+            expected.with_extension(extension),
+            files.pop().unwrap().contents,
+        ));
+    }
+    outputs
+}
+
+#[test]
+fn test_fixtures() {
+    let bless = std::env::var_os("PROTOGEN_BLESS").is_some();
+    let mut mismatched = Vec::new();
+    for proto in fixtures() {
+        for (path, contents) in generate(&proto) {
+            if bless {
+                std::fs::write(&path, contents).unwrap();
+            } else if std::fs::read_to_string(&path).ok().as_ref() != Some(&contents) {
+                mismatched.push(path.display().to_string());
+            }
+        }
+    }
+    assert!(
+        mismatched.is_empty(),
+        "Generated code differs from {}, rerun with PROTOGEN_BLESS=1 to update them",
+        mismatched.join(", ")
+    );
+}
+
+/// Tests of the generated Rust in tests/fixtures/checks, one file per
+/// feature. They `use crate::nested::*` and the like, no_std crates alias
+/// their modules to those names.
+const CHECKS: &[&str] = &[
+    "samples",
+    "wire",
+    "views",
+    "unknown_fields",
+    "size_limits",
+    "text_format",
+    "descriptors",
+];
+
+// Builds generated Rust in a scratch crate and runs the tests generated along
+// with it. The crate lives next to the build output with its own target dir,
+// so it doesn't wait on the lock held by the outer cargo. Only files for which
+// `include` returns true go in, after the `lib_prelude`, then the `checks`.
+fn check_crate(
+    name: &str,
+    dependencies: &str,
+    lib_prelude: &str,
+    include: fn(&str) -> bool,
+    checks: &[&str],
+) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dir = root.join("target").join(name);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]
+name = {:?}
+version = \"0.0.0\"
+edition = \"2021\"
+
+[dependencies]
+{}
+
+[workspace]
+",
+            name,
+            dependencies.replace("RUNTIME", &format!("{:?}", root.join("runtime")))
+        ),
+    )
+    .unwrap();
+    let mut lib = lib_prelude.to_string();
+    for proto in fixtures() {
+        for (path, contents) in generate(&proto) {
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            let Some(stem) = file_name.strip_suffix(".rs") else {
+                continue;
+            };
+            if !include(stem) {
+                continue;
+            }
+            let module = stem.replace('.', "_");
+            std::fs::write(dir.join("src").join(&module).with_extension("rs"), contents).unwrap();
+            lib.push_str(&format!("pub mod {};\n", module));
+            if let Some(base) = module.strip_suffix("_no_std") {
+                lib.push_str(&format!("pub use {} as {};\n", module, base));
+            }
+        }
+    }
+    for check in checks {
+        let module = format!("check_{}", check);
+        let path = fixture_dir()
+            .join("checks")
+            .join(check)
+            .with_extension("rs");
+        std::fs::copy(&path, dir.join("src").join(&module).with_extension("rs")).unwrap();
+        lib.push_str(&format!("#[cfg(test)]\nmod {};\n", module));
+    }
+    std::fs::write(dir.join("src/lib.rs"), lib).unwrap();
+
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    // The library alone is built without std, the tests with it.
+    for command in ["build", "test"] {
+        let output = std::process::Command::new(&cargo)
+            .args([command, "--offline", "--quiet"])
+            .env("RUSTFLAGS", "-D warnings")
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn test_fixtures_compile() {
+    check_crate(
+        "fixture-check",
+        "serde = { version = \"1\", features = [\"derive\"] }
+protogen-runtime = { path = RUNTIME, features = [\"tokio\"] }",
+        "",
+        |stem| !stem.ends_with(".no_std"),
+        &[CHECKS, &["builders"]].concat(),
+    );
+}
+
+#[test]
+fn test_fixtures_compile_no_std() {
+    check_crate(
+        "fixture-check-no-std",
+        "protogen-runtime = { path = RUNTIME, default-features = false }",
+        "#![cfg_attr(not(test), no_std)]\n\nextern crate alloc;\n",
+        |stem| stem.ends_with(".no_std"),
+        CHECKS,
+    );
+}
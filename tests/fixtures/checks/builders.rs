@@ -0,0 +1,23 @@
+// Builders of the `builders` option insist on `(required)` fields.
+
+use crate::fields_builders::*;
+
+#[test]
+fn test_builders() {
+    let point = AllFieldsPoint::builder().x(1).build();
+    assert_eq!(point.x, 1);
+    let msg = AllFields::builder()
+        .name("name")
+        .opt_point(point.clone())
+        .names(["a".to_string()])
+        .id("id")
+        .build()
+        .unwrap();
+    assert_eq!(msg.name, "name");
+    assert_eq!(msg.opt_point, Some(point));
+    assert_eq!(msg.names, ["a"]);
+    assert_eq!(
+        AllFields::builder().name("name").build(),
+        Err(protogen_runtime::builder::MissingField("AllFields.id"))
+    );
+}
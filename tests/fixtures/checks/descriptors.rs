@@ -0,0 +1,44 @@
+// Generated descriptors drive dynamic messages, which encode and print like
+// the generated ones.
+
+use protogen_runtime::descriptor::Describe;
+use protogen_runtime::dynamic::{DynamicMessage, DynamicValue};
+
+use crate::check_samples::*;
+
+#[test]
+fn test_nested_descriptors() {
+    use crate::nested::*;
+    // Descriptors tell the message types of frames apart by id.
+    let service = FILE_DESCRIPTOR.service("NestedService").unwrap();
+    assert_eq!(
+        service.message(NESTED_SERVICE_GET_OUTER_RET_ID),
+        Some("Outer")
+    );
+    assert_eq!(
+        service.message(NESTED_SERVICE_DO_THING_ARG_ID),
+        Some("Outer.Inner")
+    );
+
+    let outer_type = FILE_DESCRIPTOR.message("Outer").unwrap();
+    let dynamic = DynamicMessage::decode(&FILE_DESCRIPTOR, outer_type, &OUTER_BYTES).unwrap();
+    assert_eq!(dynamic.get("PlainID"), Some(&DynamicValue::Uint32(300)));
+    assert_eq!(dynamic.to_bytes(), OUTER_BYTES);
+    assert_eq!(dynamic.to_string(), outer().to_string());
+}
+
+#[test]
+fn test_fields_descriptors() {
+    use crate::fields::*;
+    let msg = all_fields();
+    let dynamic = DynamicMessage::from_message(&msg);
+    assert_eq!(dynamic.to_bytes(), msg.to_bytes());
+    assert_eq!(dynamic.to_string(), msg.to_string());
+    let text = format!("{:#}", dynamic);
+    let reparsed = DynamicMessage::parse_text(&FILE_DESCRIPTOR, AllFields::descriptor(), &text);
+    assert_eq!(reparsed, Ok(dynamic));
+
+    assert!(FILE_DESCRIPTOR.message("AllFields.Point").is_some());
+    let color = FILE_DESCRIPTOR.enum_("AllFields.Color").unwrap();
+    assert_eq!(color.name_of(1), Some("GREEN"));
+}
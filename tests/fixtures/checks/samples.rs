@@ -0,0 +1,55 @@
+// Messages shared by the checks, with their encoding where it was worked
+// out by hand from the protobuf encoding guide.
+
+use crate::fields::*;
+use crate::nested::*;
+
+pub fn outer() -> Outer {
+    Outer {
+        inners: vec![OuterInner {
+            kind: OuterKind::SecondKind,
+            tags: vec!["a".into()],
+            ..Default::default()
+        }],
+        maybe_count: Some(-1),
+        kind: OuterKind::SecondKind,
+        plain_id: 300,
+        ..Default::default()
+    }
+}
+
+#[rustfmt::skip]
+pub const OUTER_BYTES: [u8; 23] = [
+    0x0a, 5, 0x08, 1, 0x12, 1, b'a',
+    0x10, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+    0x18, 1,
+    0x20, 0xac, 0x02,
+];
+
+/// Every field set, most of them to edge values.
+pub fn all_fields() -> AllFields {
+    let point = AllFieldsPoint {
+        x: -3,
+        y: 4,
+        ..Default::default()
+    };
+    AllFields {
+        small: -1,
+        big: i64::MIN,
+        count: u32::MAX,
+        total: u64::MAX,
+        name: "name".into(),
+        color: AllFieldsColor::Green,
+        point: point.clone(),
+        opt_small: Some(0),
+        opt_name: Some("".into()),
+        opt_color: Some(AllFieldsColor::Red),
+        opt_point: Some(AllFieldsPoint::default()),
+        bigs: vec![0, -1, 300],
+        names: vec!["a".into(), "".into()],
+        colors: vec![AllFieldsColor::Red, AllFieldsColor::Green],
+        points: vec![point, AllFieldsPoint::default()],
+        id: "id".into(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,78 @@
+// The `max_frame_size` file option bounds what messages encode and decode.
+
+use protogen_runtime::wire::{DecodeError, EncodeError};
+
+use crate::fields::*;
+
+#[test]
+fn test_max_encoded_len() {
+    assert_eq!(MAX_MESSAGE_SIZE, 512 - 12);
+    let bounded = Bounded {
+        small: -1,
+        compact: Compact { value: u32::MAX },
+        big: Some(i64::MIN),
+    };
+    assert_eq!(bounded.serialized_size(), Bounded::MAX_ENCODED_LEN);
+}
+
+#[test]
+fn test_message_too_large() {
+    let large = OnlyRepeated {
+        values: vec![u32::MAX; 100],
+        ..Default::default()
+    };
+    assert_eq!(
+        large.serialize_body_into(&mut vec![0; 1024]),
+        Err(EncodeError::MessageTooLarge {
+            size: 503,
+            max: 500
+        })
+    );
+    assert_eq!(
+        OnlyRepeated::try_deserialize_body(&large.to_bytes()),
+        Err(DecodeError::MessageTooLarge {
+            size: 503,
+            max: 500
+        })
+    );
+}
+
+#[test]
+fn test_reply_too_large() {
+    use crate::nested::*;
+    use protogen_runtime::rpc::{serialize_frame_into, RpcError, RpcHeader, RpcStatus};
+
+    struct LargeService;
+    impl NestedService for LargeService {
+        fn do_thing(&mut self, _req: OuterInner) -> Result<EmptyReply, RpcError> {
+            Ok(EmptyReply::default())
+        }
+        fn get_outer(&mut self, _req: EmptyReply) -> Result<Outer, RpcError> {
+            Ok(Outer {
+                inners: vec![OuterInner {
+                    tags: vec!["a".repeat(MAX_FRAME_SIZE)],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+        }
+    }
+    // The request is still answered, with the error in place of the reply.
+    let mut dispatcher = NestedServiceDispatcher::new(LargeService);
+    let (mut frame, mut reply) = (vec![0; MAX_FRAME_SIZE], vec![0; MAX_FRAME_SIZE]);
+    let header = RpcHeader {
+        msg_id: NESTED_SERVICE_GET_OUTER_ARG_ID,
+        request_id: 3,
+    };
+    let len = serialize_frame_into(&header, &EmptyReply::default(), &mut frame).unwrap();
+    let len = dispatcher.dispatch(&frame[..len], &mut reply).unwrap();
+    assert_eq!(
+        RpcHeader::try_deserialize(&reply[..len]),
+        Some(RpcHeader {
+            msg_id: NESTED_SERVICE_GET_OUTER_RET_ID,
+            request_id: 3,
+        })
+    );
+    let error = RpcError::try_deserialize(&reply[RpcHeader::SIZE..len]).unwrap();
+    assert_eq!(error.status, RpcStatus::ResourceExhausted);
+}
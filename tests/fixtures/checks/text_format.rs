@@ -0,0 +1,27 @@
+// The text format round-trips every kind of field, leaving out defaults.
+
+use crate::check_samples::*;
+use crate::fields::*;
+
+#[test]
+fn test_text_round_trip() {
+    let msg = all_fields();
+    assert_eq!(msg.to_string().parse::<AllFields>(), Ok(msg.clone()));
+    assert_eq!(format!("{:#}", msg).parse::<AllFields>(), Ok(msg));
+}
+
+#[test]
+fn test_text_parse() {
+    let text = "small: -1 point { x: 2 } opt_color: GREEN bigs: [1, 2] names: \"a\" id: \"id\"";
+    let parsed: AllFields = text.parse().unwrap();
+    assert_eq!(parsed.opt_color, Some(AllFieldsColor::Green));
+    assert_eq!(parsed.bigs, [1, 2]);
+    assert_eq!(
+        parsed.to_string(),
+        "small: -1 point { x: 2 } opt_color: GREEN bigs: 1 bigs: 2 names: \"a\" id: \"id\""
+    );
+    assert_eq!(
+        "color: BLUE".parse::<AllFields>().unwrap_err().to_string(),
+        "1:8: Unknown enum value BLUE"
+    );
+}
@@ -0,0 +1,30 @@
+// Fields of a newer schema survive decoding and encoding again, unless the
+// message drops them.
+
+use crate::fields::*;
+use crate::nested::*;
+
+#[test]
+fn test_unknown_fields_kept() {
+    let newer = [0x08, 0x96, 0x01, 0x15, 1, 2, 3, 4];
+    let reply = EmptyReply::try_deserialize_body(&newer).unwrap();
+    assert_eq!(reply.serialized_size(), newer.len());
+    assert_eq!(reply.to_bytes(), newer);
+}
+
+#[test]
+fn test_unknown_fields_order() {
+    // Unknown fields go after the known ones, wherever they were decoded.
+    let newer = [0x1a, 2, b'h', b'i', 0x08, 0x02];
+    let point = AllFieldsPoint::try_deserialize_body(&newer).unwrap();
+    assert_eq!(point.x, 2);
+    assert_eq!(point.to_bytes(), [0x08, 0x02, 0x1a, 2, b'h', b'i']);
+    assert_eq!(AllFieldsPointRef::new(&newer).unwrap().to_owned(), point);
+}
+
+#[test]
+fn test_unknown_fields_dropped() {
+    let newer = [0x1a, 2, b'h', b'i', 0x08, 0x02];
+    let compact = Compact::try_deserialize_body(&newer).unwrap();
+    assert_eq!(compact.to_bytes(), [0x08, 0x02]);
+}
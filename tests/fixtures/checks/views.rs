@@ -0,0 +1,45 @@
+// Borrowing `XxxRef` views decode like the owned messages.
+
+use crate::check_samples::*;
+use crate::fields::*;
+use crate::nested::*;
+
+#[test]
+fn test_nested_view() {
+    let view = OuterRef::new(&OUTER_BYTES).unwrap();
+    assert_eq!(view.maybe_count, Some(-1));
+    assert!(view.inners().next().unwrap().tags().eq(["a"]));
+    assert_eq!(view.to_owned(), outer());
+    assert!(OuterRef::new(&OUTER_BYTES[..OUTER_BYTES.len() - 1]).is_err());
+}
+
+#[test]
+fn test_fields_view() {
+    let msg = all_fields();
+    let bytes = msg.to_bytes();
+    let view = AllFieldsRef::new(&bytes).unwrap();
+    assert_eq!(view.name, "name");
+    assert_eq!(view.opt_point.map(|p| p.x), Some(0));
+    assert!(view.bigs().eq([0, -1, 300]));
+    assert_eq!(view.to_owned(), msg);
+
+    let only = OnlyRepeated {
+        values: vec![1, 2],
+        ..Default::default()
+    };
+    assert_eq!(
+        OnlyRepeatedRef::new(&only.to_bytes()).unwrap().to_owned(),
+        only
+    );
+}
+
+#[test]
+fn test_view_repeated_message_field() {
+    // point { x: 1 } point { y: 2 }, encoders may split a message like this.
+    let bytes = [0x3a, 2, 0x08, 1, 0x3a, 2, 0x10, 2];
+    let view = AllFieldsRef::new(&bytes).unwrap();
+    assert_eq!((view.point.x, view.point.y), (0, 2));
+    let owned = AllFields::try_deserialize_body(&bytes).unwrap();
+    assert_eq!((owned.point.x, owned.point.y), (1, 2));
+    assert_eq!(view.to_owned(), owned);
+}
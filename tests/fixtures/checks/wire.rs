@@ -0,0 +1,27 @@
+// Encoding and decoding of the owned messages.
+
+use crate::check_samples::*;
+use crate::fields::*;
+use crate::nested::*;
+
+#[test]
+fn test_nested_wire() {
+    let outer = outer();
+    assert_eq!(outer.to_bytes(), OUTER_BYTES);
+    assert_eq!(Outer::try_deserialize_body(&OUTER_BYTES), Ok(outer));
+    assert_eq!(Outer::default().to_bytes(), []);
+}
+
+#[test]
+fn test_fields_wire() {
+    let msg = all_fields();
+    let bytes = msg.to_bytes();
+    assert_eq!(bytes.len(), msg.serialized_size());
+    assert_eq!(AllFields::try_deserialize_body(&bytes), Ok(msg.clone()));
+
+    let mut buf = [0u8; 8];
+    assert_eq!(
+        msg.serialize_body_into(&mut buf),
+        Err(protogen_runtime::wire::EncodeError::BufferTooSmall)
+    );
+}
@@ -0,0 +1,1503 @@
+pub use protogen_runtime::client::RpcClient;
+pub use protogen_runtime::rpc::*;
+pub use protogen_runtime::Transport;
+use protogen_runtime::wire::Varint as _;
+use serde::{Deserialize, Serialize};
+
+const _: () = protogen_runtime::VERSION_0_2;
+
+/// Largest frame clients and dispatchers send or accept, from the `max_frame_size` option.
+pub const MAX_FRAME_SIZE: usize = 512;
+
+/// Largest encoded message, leaving room for the header and status of a reply.
+pub const MAX_MESSAGE_SIZE: usize = MAX_FRAME_SIZE - REPLY_OVERHEAD;
+
+/// Descriptors of everything in this file, to look types up by name.
+pub static FILE_DESCRIPTOR: protogen_runtime::descriptor::FileDescriptor = protogen_runtime::descriptor::FileDescriptor {
+    messages: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            ALL_FIELDS_DESCRIPTOR,
+            ALL_FIELDS_POINT_DESCRIPTOR,
+            ONLY_REPEATED_DESCRIPTOR,
+            COMPACT_DESCRIPTOR,
+            BOUNDED_DESCRIPTOR,
+        ],
+    ),
+    enums: protogen_runtime::descriptor::Cow::Borrowed(&[ALL_FIELDS_COLOR_DESCRIPTOR]),
+    services: protogen_runtime::descriptor::Cow::Borrowed(&[]),
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct AllFieldsPoint {
+    pub x: i32,
+    pub y: i32,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for AllFieldsPoint {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.x.to_varint() != 0 {
+            e.varint(1, self.x)?;
+        }
+        if self.y.to_varint() != 0 {
+            e.varint(2, self.y)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        match field {
+            1 => {
+                self.x = value.varint(field)?;
+            }
+            2 => {
+                self.y = value.varint(field)?;
+            }
+            _ => self.unknown_fields.push(field, value),
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.x.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.x.to_varint());
+        }
+        if self.y.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(2, self.y.to_varint());
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for AllFieldsPoint {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.x.to_varint() != 0 {
+            w.int("x", self.x)?;
+        }
+        if self.y.to_varint() != 0 {
+            w.int("y", self.y)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "x" => self.x = p.int()?,
+            "y" => self.y = p.int()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for AllFieldsPoint {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for AllFieldsPoint {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const ALL_FIELDS_POINT_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("AllFields.Point"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("x"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Int32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("y"),
+                number: 2,
+                ty: protogen_runtime::descriptor::FieldType::Int32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for AllFieldsPoint {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "AllFields.Point";
+}
+
+/// Borrowed view of an encoded [`AllFieldsPoint`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllFieldsPointRef<'a> {
+    buf: &'a [u8],
+    pub x: i32,
+    pub y: i32,
+}
+
+impl<'a> AllFieldsPointRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            match field {
+                1 => {
+                    view.x = value.varint(field)?;
+                }
+                2 => {
+                    view.y = value.varint(field)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> AllFieldsPoint {
+        AllFieldsPoint {
+            x: self.x,
+            y: self.y,
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(
+                self.buf,
+                &[1, 2],
+            ),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for AllFieldsPointRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+impl AllFieldsPoint {
+    pub fn builder() -> AllFieldsPointBuilder {
+        <AllFieldsPointBuilder as Default>::default()
+    }
+}
+
+/// Builds a [`AllFieldsPoint`] field by field, from [`AllFieldsPoint::builder`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct AllFieldsPointBuilder {
+    msg: AllFieldsPoint,
+}
+
+impl AllFieldsPointBuilder {
+    pub fn x(mut self, value: impl Into<i32>) -> Self {
+        self.msg.x = value.into();
+        self
+    }
+    pub fn y(mut self, value: impl Into<i32>) -> Self {
+        self.msg.y = value.into();
+        self
+    }
+    pub fn build(self) -> AllFieldsPoint {
+        self.msg
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AllFieldsColor {
+    #[default]
+    #[serde(rename = "RED")]
+    Red = 0,
+    #[serde(rename = "GREEN")]
+    Green = 1,
+}
+
+impl protogen_runtime::wire::Varint for AllFieldsColor {
+    fn to_varint(self) -> u64 {
+        self as i32 as i64 as u64
+    }
+    fn from_varint(value: u64) -> Self {
+        match value as i32 {
+            0 => Self::Red,
+            1 => Self::Green,
+            _ => Self::default(),
+        }
+    }
+}
+
+impl protogen_runtime::text::TextEnum for AllFieldsColor {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Red => "RED",
+            Self::Green => "GREEN",
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "RED" => Some(Self::Red),
+            "GREEN" => Some(Self::Green),
+            _ => None,
+        }
+    }
+}
+
+const ALL_FIELDS_COLOR_DESCRIPTOR: protogen_runtime::descriptor::EnumDescriptor = protogen_runtime::descriptor::EnumDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("AllFields.Color"),
+    values: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::EnumValueDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("RED"),
+                number: 0,
+            },
+            protogen_runtime::descriptor::EnumValueDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("GREEN"),
+                number: 1,
+            },
+        ],
+    ),
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct AllFields {
+    pub small: i32,
+    pub big: i64,
+    pub count: u32,
+    pub total: u64,
+    pub name: String,
+    pub color: AllFieldsColor,
+    pub point: AllFieldsPoint,
+    pub opt_small: Option<i32>,
+    pub opt_name: Option<String>,
+    pub opt_color: Option<AllFieldsColor>,
+    pub opt_point: Option<AllFieldsPoint>,
+    pub bigs: Vec<i64>,
+    pub names: Vec<String>,
+    pub colors: Vec<AllFieldsColor>,
+    pub points: Vec<AllFieldsPoint>,
+    pub id: String,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for AllFields {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.small.to_varint() != 0 {
+            e.varint(1, self.small)?;
+        }
+        if self.big.to_varint() != 0 {
+            e.varint(2, self.big)?;
+        }
+        if self.count.to_varint() != 0 {
+            e.varint(3, self.count)?;
+        }
+        if self.total.to_varint() != 0 {
+            e.varint(4, self.total)?;
+        }
+        if !self.name.is_empty() {
+            e.string(5, &self.name)?;
+        }
+        if self.color.to_varint() != 0 {
+            e.varint(6, self.color)?;
+        }
+        if self.point.serialized_size() != 0 {
+            e.message(7, &self.point)?;
+        }
+        if let Some(v) = self.opt_small {
+            e.varint(8, v)?;
+        }
+        if let Some(v) = &self.opt_name {
+            e.string(9, v)?;
+        }
+        if let Some(v) = self.opt_color {
+            e.varint(10, v)?;
+        }
+        if let Some(v) = &self.opt_point {
+            e.message(11, v)?;
+        }
+        e.packed(12, self.bigs.iter().copied())?;
+        for v in &self.names {
+            e.string(13, v)?;
+        }
+        e.packed(14, self.colors.iter().copied())?;
+        for v in &self.points {
+            e.message(15, v)?;
+        }
+        if !self.id.is_empty() {
+            e.string(16, &self.id)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        match field {
+            1 => {
+                self.small = value.varint(field)?;
+            }
+            2 => {
+                self.big = value.varint(field)?;
+            }
+            3 => {
+                self.count = value.varint(field)?;
+            }
+            4 => {
+                self.total = value.varint(field)?;
+            }
+            5 => {
+                self.name = value.str(field)?.into();
+            }
+            6 => {
+                self.color = value.varint(field)?;
+            }
+            7 => {
+                self.point.merge(value.bytes(field)?)?;
+            }
+            8 => {
+                self.opt_small = Some(value.varint(field)?);
+            }
+            9 => {
+                self.opt_name = Some(value.str(field)?.into());
+            }
+            10 => {
+                self.opt_color = Some(value.varint(field)?);
+            }
+            11 => {
+                self.opt_point
+                    .get_or_insert_with(Default::default)
+                    .merge(value.bytes(field)?)?;
+            }
+            12 => {
+                for v in value.varints(field)? {
+                    self.bigs.push(v?);
+                }
+            }
+            13 => {
+                self.names.push(value.str(field)?.into());
+            }
+            14 => {
+                for v in value.varints(field)? {
+                    self.colors.push(v?);
+                }
+            }
+            15 => {
+                self.points
+                    .push(AllFieldsPoint::try_deserialize_body(value.bytes(field)?)?);
+            }
+            16 => {
+                self.id = value.str(field)?.into();
+            }
+            _ => self.unknown_fields.push(field, value),
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.small.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.small.to_varint());
+        }
+        if self.big.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(2, self.big.to_varint());
+        }
+        if self.count.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(3, self.count.to_varint());
+        }
+        if self.total.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(4, self.total.to_varint());
+        }
+        if !self.name.is_empty() {
+            size += protogen_runtime::wire::len_field_size(5, self.name.len());
+        }
+        if self.color.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(6, self.color.to_varint());
+        }
+        match self.point.serialized_size() {
+            0 => {}
+            len => size += protogen_runtime::wire::len_field_size(7, len),
+        }
+        if let Some(v) = self.opt_small {
+            size += protogen_runtime::wire::varint_field_size(8, v.to_varint());
+        }
+        if let Some(v) = &self.opt_name {
+            size += protogen_runtime::wire::len_field_size(9, v.len());
+        }
+        if let Some(v) = self.opt_color {
+            size += protogen_runtime::wire::varint_field_size(10, v.to_varint());
+        }
+        if let Some(v) = &self.opt_point {
+            size += protogen_runtime::wire::len_field_size(11, v.serialized_size());
+        }
+        size += protogen_runtime::wire::packed_field_size(12, self.bigs.iter().copied());
+        for v in &self.names {
+            size += protogen_runtime::wire::len_field_size(13, v.len());
+        }
+        size
+            += protogen_runtime::wire::packed_field_size(
+                14,
+                self.colors.iter().copied(),
+            );
+        for v in &self.points {
+            size += protogen_runtime::wire::len_field_size(15, v.serialized_size());
+        }
+        if !self.id.is_empty() {
+            size += protogen_runtime::wire::len_field_size(16, self.id.len());
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for AllFields {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.small.to_varint() != 0 {
+            w.int("small", self.small)?;
+        }
+        if self.big.to_varint() != 0 {
+            w.int("big", self.big)?;
+        }
+        if self.count.to_varint() != 0 {
+            w.int("count", self.count)?;
+        }
+        if self.total.to_varint() != 0 {
+            w.int("total", self.total)?;
+        }
+        if !self.name.is_empty() {
+            w.string("name", &self.name)?;
+        }
+        if self.color.to_varint() != 0 {
+            w.enum_value("color", self.color)?;
+        }
+        if self.point.serialized_size() != 0 {
+            w.message("point", &self.point)?;
+        }
+        if let Some(v) = &self.opt_small {
+            w.int("opt_small", *v)?;
+        }
+        if let Some(v) = &self.opt_name {
+            w.string("opt_name", v)?;
+        }
+        if let Some(v) = &self.opt_color {
+            w.enum_value("opt_color", *v)?;
+        }
+        if let Some(v) = &self.opt_point {
+            w.message("opt_point", v)?;
+        }
+        for v in &self.bigs {
+            w.int("bigs", *v)?;
+        }
+        for v in &self.names {
+            w.string("names", v)?;
+        }
+        for v in &self.colors {
+            w.enum_value("colors", *v)?;
+        }
+        for v in &self.points {
+            w.message("points", v)?;
+        }
+        if !self.id.is_empty() {
+            w.string("id", &self.id)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "small" => self.small = p.int()?,
+            "big" => self.big = p.int()?,
+            "count" => self.count = p.int()?,
+            "total" => self.total = p.int()?,
+            "name" => self.name = p.string()?,
+            "color" => self.color = p.enum_value()?,
+            "point" => p.merge_message(&mut self.point)?,
+            "opt_small" => self.opt_small = Some(p.int()?),
+            "opt_name" => self.opt_name = Some(p.string()?),
+            "opt_color" => self.opt_color = Some(p.enum_value()?),
+            "opt_point" => {
+                p.merge_message(self.opt_point.get_or_insert_with(Default::default))?
+            }
+            "bigs" => {
+                p.repeated(|p| {
+                    self.bigs.push(p.int()?);
+                    Ok(())
+                })?
+            }
+            "names" => {
+                p.repeated(|p| {
+                    self.names.push(p.string()?);
+                    Ok(())
+                })?
+            }
+            "colors" => {
+                p.repeated(|p| {
+                    self.colors.push(p.enum_value()?);
+                    Ok(())
+                })?
+            }
+            "points" => {
+                p.repeated(|p| {
+                    self.points.push(p.message()?);
+                    Ok(())
+                })?
+            }
+            "id" => self.id = p.string()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for AllFields {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for AllFields {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const ALL_FIELDS_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("AllFields"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("small"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Int32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("big"),
+                number: 2,
+                ty: protogen_runtime::descriptor::FieldType::Int64,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("count"),
+                number: 3,
+                ty: protogen_runtime::descriptor::FieldType::Uint32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("total"),
+                number: 4,
+                ty: protogen_runtime::descriptor::FieldType::Uint64,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("name"),
+                number: 5,
+                ty: protogen_runtime::descriptor::FieldType::String,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("color"),
+                number: 6,
+                ty: protogen_runtime::descriptor::FieldType::Enum(
+                    protogen_runtime::descriptor::Cow::Borrowed("AllFields.Color"),
+                ),
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("point"),
+                number: 7,
+                ty: protogen_runtime::descriptor::FieldType::Message(
+                    protogen_runtime::descriptor::Cow::Borrowed("AllFields.Point"),
+                ),
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("opt_small"),
+                number: 8,
+                ty: protogen_runtime::descriptor::FieldType::Int32,
+                label: protogen_runtime::descriptor::Label::Optional,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("opt_name"),
+                number: 9,
+                ty: protogen_runtime::descriptor::FieldType::String,
+                label: protogen_runtime::descriptor::Label::Optional,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("opt_color"),
+                number: 10,
+                ty: protogen_runtime::descriptor::FieldType::Enum(
+                    protogen_runtime::descriptor::Cow::Borrowed("AllFields.Color"),
+                ),
+                label: protogen_runtime::descriptor::Label::Optional,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("opt_point"),
+                number: 11,
+                ty: protogen_runtime::descriptor::FieldType::Message(
+                    protogen_runtime::descriptor::Cow::Borrowed("AllFields.Point"),
+                ),
+                label: protogen_runtime::descriptor::Label::Optional,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("bigs"),
+                number: 12,
+                ty: protogen_runtime::descriptor::FieldType::Int64,
+                label: protogen_runtime::descriptor::Label::Repeated,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("names"),
+                number: 13,
+                ty: protogen_runtime::descriptor::FieldType::String,
+                label: protogen_runtime::descriptor::Label::Repeated,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("colors"),
+                number: 14,
+                ty: protogen_runtime::descriptor::FieldType::Enum(
+                    protogen_runtime::descriptor::Cow::Borrowed("AllFields.Color"),
+                ),
+                label: protogen_runtime::descriptor::Label::Repeated,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("points"),
+                number: 15,
+                ty: protogen_runtime::descriptor::FieldType::Message(
+                    protogen_runtime::descriptor::Cow::Borrowed("AllFields.Point"),
+                ),
+                label: protogen_runtime::descriptor::Label::Repeated,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("id"),
+                number: 16,
+                ty: protogen_runtime::descriptor::FieldType::String,
+                label: protogen_runtime::descriptor::Label::Required,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for AllFields {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "AllFields";
+}
+
+/// Borrowed view of an encoded [`AllFields`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllFieldsRef<'a> {
+    buf: &'a [u8],
+    pub small: i32,
+    pub big: i64,
+    pub count: u32,
+    pub total: u64,
+    pub name: &'a str,
+    pub color: AllFieldsColor,
+    pub point: AllFieldsPointRef<'a>,
+    pub opt_small: Option<i32>,
+    pub opt_name: Option<&'a str>,
+    pub opt_color: Option<AllFieldsColor>,
+    pub opt_point: Option<AllFieldsPointRef<'a>>,
+    pub id: &'a str,
+}
+
+impl<'a> AllFieldsRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            match field {
+                1 => {
+                    view.small = value.varint(field)?;
+                }
+                2 => {
+                    view.big = value.varint(field)?;
+                }
+                3 => {
+                    view.count = value.varint(field)?;
+                }
+                4 => {
+                    view.total = value.varint(field)?;
+                }
+                5 => {
+                    view.name = value.str(field)?;
+                }
+                6 => {
+                    view.color = value.varint(field)?;
+                }
+                7 => {
+                    view.point = AllFieldsPointRef::new(value.bytes(field)?)?;
+                }
+                8 => {
+                    view.opt_small = Some(value.varint(field)?);
+                }
+                9 => {
+                    view.opt_name = Some(value.str(field)?);
+                }
+                10 => {
+                    view.opt_color = Some(value.varint(field)?);
+                }
+                11 => {
+                    view.opt_point = Some(AllFieldsPointRef::new(value.bytes(field)?)?);
+                }
+                12 => {
+                    for v in value.varints::<i64>(field)? {
+                        v?;
+                    }
+                }
+                13 => {
+                    value.str(field)?;
+                }
+                14 => {
+                    for v in value.varints::<AllFieldsColor>(field)? {
+                        v?;
+                    }
+                }
+                15 => {
+                    AllFieldsPointRef::new(value.bytes(field)?)?;
+                }
+                16 => {
+                    view.id = value.str(field)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    pub fn bigs(&self) -> protogen_runtime::view::RepeatedVarints<'a, i64> {
+        protogen_runtime::view::RepeatedVarints::new(self.buf, 12)
+    }
+    pub fn names(&self) -> protogen_runtime::view::Repeated<'a, &'a str> {
+        protogen_runtime::view::Repeated::new(self.buf, 13)
+    }
+    pub fn colors(&self) -> protogen_runtime::view::RepeatedVarints<'a, AllFieldsColor> {
+        protogen_runtime::view::RepeatedVarints::new(self.buf, 14)
+    }
+    pub fn points(&self) -> protogen_runtime::view::Repeated<'a, AllFieldsPointRef<'a>> {
+        protogen_runtime::view::Repeated::new(self.buf, 15)
+    }
+    /// Copy the message out of the buffer.
+    ///
+    /// Message fields that occur more than once are merged like decoding does,
+    /// the view's fields hold the last occurrence.
+    pub fn to_owned(&self) -> AllFields {
+        AllFields {
+            small: self.small,
+            big: self.big,
+            count: self.count,
+            total: self.total,
+            name: self.name.into(),
+            color: self.color,
+            point: protogen_runtime::view::merged(self.buf, 7).unwrap_or_default(),
+            opt_small: self.opt_small,
+            opt_name: self.opt_name.map(Into::into),
+            opt_color: self.opt_color,
+            opt_point: protogen_runtime::view::merged(self.buf, 11),
+            bigs: self.bigs().collect(),
+            names: self.names().map(Into::into).collect(),
+            colors: self.colors().collect(),
+            points: self.points().map(|v| v.to_owned()).collect(),
+            id: self.id.into(),
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(
+                self.buf,
+                &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16],
+            ),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for AllFieldsRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+impl AllFields {
+    pub fn builder() -> AllFieldsBuilder {
+        <AllFieldsBuilder as Default>::default()
+    }
+}
+
+/// Builds a [`AllFields`] field by field, from [`AllFields::builder`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct AllFieldsBuilder {
+    msg: AllFields,
+    has_id: bool,
+}
+
+impl AllFieldsBuilder {
+    pub fn small(mut self, value: impl Into<i32>) -> Self {
+        self.msg.small = value.into();
+        self
+    }
+    pub fn big(mut self, value: impl Into<i64>) -> Self {
+        self.msg.big = value.into();
+        self
+    }
+    pub fn count(mut self, value: impl Into<u32>) -> Self {
+        self.msg.count = value.into();
+        self
+    }
+    pub fn total(mut self, value: impl Into<u64>) -> Self {
+        self.msg.total = value.into();
+        self
+    }
+    pub fn name(mut self, value: impl Into<String>) -> Self {
+        self.msg.name = value.into();
+        self
+    }
+    pub fn color(mut self, value: impl Into<AllFieldsColor>) -> Self {
+        self.msg.color = value.into();
+        self
+    }
+    pub fn point(mut self, value: impl Into<AllFieldsPoint>) -> Self {
+        self.msg.point = value.into();
+        self
+    }
+    pub fn opt_small(mut self, value: impl Into<i32>) -> Self {
+        self.msg.opt_small = Some(value.into());
+        self
+    }
+    pub fn opt_name(mut self, value: impl Into<String>) -> Self {
+        self.msg.opt_name = Some(value.into());
+        self
+    }
+    pub fn opt_color(mut self, value: impl Into<AllFieldsColor>) -> Self {
+        self.msg.opt_color = Some(value.into());
+        self
+    }
+    pub fn opt_point(mut self, value: impl Into<AllFieldsPoint>) -> Self {
+        self.msg.opt_point = Some(value.into());
+        self
+    }
+    pub fn bigs(mut self, values: impl IntoIterator<Item = i64>) -> Self {
+        self.msg.bigs = values.into_iter().collect();
+        self
+    }
+    pub fn names(mut self, values: impl IntoIterator<Item = String>) -> Self {
+        self.msg.names = values.into_iter().collect();
+        self
+    }
+    pub fn colors(mut self, values: impl IntoIterator<Item = AllFieldsColor>) -> Self {
+        self.msg.colors = values.into_iter().collect();
+        self
+    }
+    pub fn points(mut self, values: impl IntoIterator<Item = AllFieldsPoint>) -> Self {
+        self.msg.points = values.into_iter().collect();
+        self
+    }
+    pub fn id(mut self, value: impl Into<String>) -> Self {
+        self.msg.id = value.into();
+        self.has_id = true;
+        self
+    }
+    /// Fails if a `(required)` field wasn't set.
+    pub fn build(self) -> Result<AllFields, protogen_runtime::builder::MissingField> {
+        if !self.has_id {
+            return Err(protogen_runtime::builder::MissingField("AllFields.id"));
+        }
+        Ok(self.msg)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct OnlyRepeated {
+    pub values: Vec<u32>,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for OnlyRepeated {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        e.packed(1, self.values.iter().copied())?;
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        if field == 1 {
+            for v in value.varints(field)? {
+                self.values.push(v?);
+            }
+        } else {
+            self.unknown_fields.push(field, value);
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        size
+            += protogen_runtime::wire::packed_field_size(1, self.values.iter().copied());
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for OnlyRepeated {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        for v in &self.values {
+            w.int("values", *v)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "values" => {
+                p.repeated(|p| {
+                    self.values.push(p.int()?);
+                    Ok(())
+                })?
+            }
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for OnlyRepeated {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for OnlyRepeated {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const ONLY_REPEATED_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("OnlyRepeated"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("values"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Uint32,
+                label: protogen_runtime::descriptor::Label::Repeated,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for OnlyRepeated {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "OnlyRepeated";
+}
+
+/// Borrowed view of an encoded [`OnlyRepeated`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OnlyRepeatedRef<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> OnlyRepeatedRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            if field == 1 {
+                for v in value.varints::<u32>(field)? {
+                    v?;
+                }
+            }
+        }
+        Ok(Self { buf })
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    pub fn values(&self) -> protogen_runtime::view::RepeatedVarints<'a, u32> {
+        protogen_runtime::view::RepeatedVarints::new(self.buf, 1)
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> OnlyRepeated {
+        OnlyRepeated {
+            values: self.values().collect(),
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(self.buf, &[1]),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for OnlyRepeatedRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+impl OnlyRepeated {
+    pub fn builder() -> OnlyRepeatedBuilder {
+        <OnlyRepeatedBuilder as Default>::default()
+    }
+}
+
+/// Builds a [`OnlyRepeated`] field by field, from [`OnlyRepeated::builder`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct OnlyRepeatedBuilder {
+    msg: OnlyRepeated,
+}
+
+impl OnlyRepeatedBuilder {
+    pub fn values(mut self, values: impl IntoIterator<Item = u32>) -> Self {
+        self.msg.values = values.into_iter().collect();
+        self
+    }
+    pub fn build(self) -> OnlyRepeated {
+        self.msg
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct Compact {
+    pub value: u32,
+}
+
+impl ProtoMessage for Compact {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.value.to_varint() != 0 {
+            e.varint(1, self.value)?;
+        }
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        if field == 1 {
+            self.value = value.varint(field)?;
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.value.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.value.to_varint());
+        }
+        size
+    }
+}
+
+impl Compact {
+    /// Upper bound of the encoded length, for buffers sized at compile time.
+    pub const MAX_ENCODED_LEN: usize = 6;
+}
+
+impl protogen_runtime::text::TextMessage for Compact {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.value.to_varint() != 0 {
+            w.int("value", self.value)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "value" => self.value = p.int()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for Compact {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for Compact {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const COMPACT_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Compact"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("value"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Uint32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for Compact {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "Compact";
+}
+
+/// Borrowed view of an encoded [`Compact`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactRef<'a> {
+    buf: &'a [u8],
+    pub value: u32,
+}
+
+impl<'a> CompactRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            if field == 1 {
+                view.value = value.varint(field)?;
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> Compact {
+        Compact { value: self.value }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for CompactRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+impl Compact {
+    pub fn builder() -> CompactBuilder {
+        <CompactBuilder as Default>::default()
+    }
+}
+
+/// Builds a [`Compact`] field by field, from [`Compact::builder`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct CompactBuilder {
+    msg: Compact,
+}
+
+impl CompactBuilder {
+    pub fn value(mut self, value: impl Into<u32>) -> Self {
+        self.msg.value = value.into();
+        self
+    }
+    pub fn build(self) -> Compact {
+        self.msg
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct Bounded {
+    pub small: i32,
+    pub compact: Compact,
+    pub big: Option<i64>,
+}
+
+impl ProtoMessage for Bounded {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.small.to_varint() != 0 {
+            e.varint(1, self.small)?;
+        }
+        if self.compact.serialized_size() != 0 {
+            e.message(2, &self.compact)?;
+        }
+        if let Some(v) = self.big {
+            e.varint(3, v)?;
+        }
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        match field {
+            1 => {
+                self.small = value.varint(field)?;
+            }
+            2 => {
+                self.compact.merge(value.bytes(field)?)?;
+            }
+            3 => {
+                self.big = Some(value.varint(field)?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.small.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.small.to_varint());
+        }
+        match self.compact.serialized_size() {
+            0 => {}
+            len => size += protogen_runtime::wire::len_field_size(2, len),
+        }
+        if let Some(v) = self.big {
+            size += protogen_runtime::wire::varint_field_size(3, v.to_varint());
+        }
+        size
+    }
+}
+
+impl Bounded {
+    /// Upper bound of the encoded length, for buffers sized at compile time.
+    pub const MAX_ENCODED_LEN: usize = 30;
+}
+
+impl protogen_runtime::text::TextMessage for Bounded {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.small.to_varint() != 0 {
+            w.int("small", self.small)?;
+        }
+        if self.compact.serialized_size() != 0 {
+            w.message("compact", &self.compact)?;
+        }
+        if let Some(v) = &self.big {
+            w.int("big", *v)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "small" => self.small = p.int()?,
+            "compact" => p.merge_message(&mut self.compact)?,
+            "big" => self.big = Some(p.int()?),
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for Bounded {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for Bounded {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const BOUNDED_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Bounded"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("small"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Int32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("compact"),
+                number: 2,
+                ty: protogen_runtime::descriptor::FieldType::Message(
+                    protogen_runtime::descriptor::Cow::Borrowed("Compact"),
+                ),
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("big"),
+                number: 3,
+                ty: protogen_runtime::descriptor::FieldType::Int64,
+                label: protogen_runtime::descriptor::Label::Optional,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for Bounded {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "Bounded";
+}
+
+/// Borrowed view of an encoded [`Bounded`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoundedRef<'a> {
+    buf: &'a [u8],
+    pub small: i32,
+    pub compact: CompactRef<'a>,
+    pub big: Option<i64>,
+}
+
+impl<'a> BoundedRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            match field {
+                1 => {
+                    view.small = value.varint(field)?;
+                }
+                2 => {
+                    view.compact = CompactRef::new(value.bytes(field)?)?;
+                }
+                3 => {
+                    view.big = Some(value.varint(field)?);
+                }
+                _ => {}
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    ///
+    /// Message fields that occur more than once are merged like decoding does,
+    /// the view's fields hold the last occurrence.
+    pub fn to_owned(&self) -> Bounded {
+        Bounded {
+            small: self.small,
+            compact: protogen_runtime::view::merged(self.buf, 2).unwrap_or_default(),
+            big: self.big,
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for BoundedRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+impl Bounded {
+    pub fn builder() -> BoundedBuilder {
+        <BoundedBuilder as Default>::default()
+    }
+}
+
+/// Builds a [`Bounded`] field by field, from [`Bounded::builder`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct BoundedBuilder {
+    msg: Bounded,
+}
+
+impl BoundedBuilder {
+    pub fn small(mut self, value: impl Into<i32>) -> Self {
+        self.msg.small = value.into();
+        self
+    }
+    pub fn compact(mut self, value: impl Into<Compact>) -> Self {
+        self.msg.compact = value.into();
+        self
+    }
+    pub fn big(mut self, value: impl Into<i64>) -> Self {
+        self.msg.big = Some(value.into());
+        self
+    }
+    pub fn build(self) -> Bounded {
+        self.msg
+    }
+}
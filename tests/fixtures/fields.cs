@@ -0,0 +1,1914 @@
+using System;
+using System.Collections.Generic;
+using System.Buffers.Binary;
+using System.Diagnostics;
+using System.Diagnostics.CodeAnalysis;
+using System.Globalization;
+using System.IO;
+using System.Linq;
+using System.Net;
+using System.Net.Sockets;
+using System.Runtime.InteropServices;
+using System.Text;
+using System.Threading;
+using System.Threading.Tasks;
+
+namespace Proto {
+    public class Builtin {
+        // Writes protobuf encoded fields into a fixed buffer. Once a write
+        // doesn't fit, Overflow is set and nothing else is written.
+        public class WireWriter {
+            private byte[] bytes;
+
+            public int Position { get; private set; }
+            public bool Overflow { get; private set; }
+
+            public WireWriter(byte[] bytes) {
+                this.bytes = bytes;
+            }
+
+            private void Put(byte b) {
+                if (Position >= bytes.Length) {
+                    Overflow = true;
+                } else if (!Overflow) {
+                    bytes[Position++] = b;
+                }
+            }
+
+            private void RawVarint(UInt64 value) {
+                while (value >= 0x80) {
+                    Put((byte)(value | 0x80));
+                    value >>= 7;
+                }
+                Put((byte)value);
+            }
+
+            private void Key(int field, int wireType) {
+                RawVarint((UInt64)field << 3 | (UInt64)wireType);
+            }
+
+            // Any varint field, int32 and enum values are sign extended to 64
+            // bits by the caller.
+            public void Varint(int field, Int64 value) {
+                Key(field, WireVarint);
+                RawVarint(unchecked((UInt64)value));
+            }
+
+            public void Bytes(int field, byte[] value) {
+                Key(field, WireLen);
+                RawVarint((UInt64)value.Length);
+                foreach (var b in value) {
+                    Put(b);
+                }
+            }
+
+            public void String(int field, string value) {
+                Bytes(field, Encoding.UTF8.GetBytes(value));
+            }
+
+            // A packed repeated varint field, nothing if it's empty.
+            public void Packed(int field, IEnumerable<Int64> values) {
+                var len = values.Sum(v => VarintSize(unchecked((UInt64)v)));
+                if (len == 0) {
+                    return;
+                }
+                Key(field, WireLen);
+                RawVarint((UInt64)len);
+                foreach (var v in values) {
+                    RawVarint(unchecked((UInt64)v));
+                }
+            }
+
+            // Already encoded fields, like the unknown ones returned by SkipRaw.
+            public void Raw(byte[] fields) {
+                foreach (var b in fields) {
+                    Put(b);
+                }
+            }
+
+            public void Message(int field, IProtoMessage msg) {
+                Key(field, WireLen);
+                RawVarint((UInt64)msg.serializedSize());
+                msg.encodeFields(this);
+            }
+        }
+
+        // Iterates over the fields of an encoded message. Reading a value of the
+        // wrong wire type or past the end sets Failed, Next then returns false.
+        public class WireReader {
+            private byte[] bytes;
+            private int pos;
+            private int fieldStart;
+            private int wireType;
+
+            public bool Failed { get; private set; }
+
+            public WireReader(byte[] bytes) {
+                this.bytes = bytes;
+            }
+
+            // Move to the next field, false at the end or after a failure.
+            public bool Next(out int field) {
+                field = 0;
+                if (Failed || pos == bytes.Length) {
+                    return false;
+                }
+                fieldStart = pos;
+                var key = RawVarint();
+                wireType = (int)(key & 7);
+                if (key >> 3 == 0 || key >> 3 > Int32.MaxValue) {
+                    Failed = true;
+                    return false;
+                }
+                field = (int)(key >> 3);
+                return !Failed;
+            }
+
+            private UInt64 RawVarint() {
+                UInt64 value = 0;
+                for (var i = 0; i < 10; i++) {
+                    if (pos == bytes.Length) {
+                        break;
+                    }
+                    var b = bytes[pos++];
+                    value |= (UInt64)(b & 0x7f) << (7 * i);
+                    if ((b & 0x80) == 0) {
+                        return value;
+                    }
+                }
+                Failed = true;
+                return 0;
+            }
+
+            private byte[] Take(int len) {
+                if (len < 0 || len > bytes.Length - pos) {
+                    Failed = true;
+                    return Array.Empty<byte>();
+                }
+                pos += len;
+                return bytes[(pos - len)..pos];
+            }
+
+            private bool Expect(int expected) {
+                if (wireType != expected) {
+                    Failed = true;
+                }
+                return !Failed;
+            }
+
+            public UInt64 Varint() {
+                return Expect(WireVarint) ? RawVarint() : 0;
+            }
+
+            public byte[] Bytes() {
+                if (!Expect(WireLen)) {
+                    return Array.Empty<byte>();
+                }
+                var len = RawVarint();
+                return Take(len > Int32.MaxValue ? -1 : (int)len);
+            }
+
+            public string String() {
+                try {
+                    return new UTF8Encoding(false, true).GetString(Bytes());
+                } catch (DecoderFallbackException) {
+                    Failed = true;
+                    return "";
+                }
+            }
+
+            // The elements of a repeated varint field, packed or not.
+            public List<UInt64> Varints() {
+                var values = new List<UInt64>();
+                if (wireType == WireVarint) {
+                    values.Add(RawVarint());
+                    return values;
+                }
+                var packed = new WireReader(Bytes());
+                while (!Failed && packed.pos < packed.bytes.Length) {
+                    values.Add(packed.RawVarint());
+                    Failed = packed.Failed;
+                }
+                return values;
+            }
+
+            // Skip the value of a field the message doesn't know.
+            public void Skip() {
+                switch (wireType) {
+                    case WireVarint: RawVarint(); break;
+                    case WireFixed64: Take(8); break;
+                    case WireLen: Bytes(); break;
+                    case WireFixed32: Take(4); break;
+                    default: Failed = true; break;
+                }
+            }
+
+            // Skip a field the message doesn't know, returns all of it, key
+            // included, to be written back as it was.
+            public byte[] SkipRaw() {
+                Skip();
+                return Failed ? Array.Empty<byte>() : bytes[fieldStart..pos];
+            }
+        }
+
+        // Wire types of a field key.
+        public const int WireVarint = 0;
+        public const int WireFixed64 = 1;
+        public const int WireLen = 2;
+        public const int WireFixed32 = 5;
+
+        // Encoded length of value as a varint.
+        public static int VarintSize(UInt64 value) {
+            var size = 1;
+            while (value >= 0x80) {
+                value >>= 7;
+                size++;
+            }
+            return size;
+        }
+
+        public static int VarintFieldSize(int field, Int64 value) {
+            return VarintSize((UInt64)field << 3) + VarintSize(unchecked((UInt64)value));
+        }
+
+        public static int LenFieldSize(int field, int len) {
+            return VarintSize((UInt64)field << 3) + VarintSize((UInt64)len) + len;
+        }
+
+        // Encoded length of a packed repeated field, nothing if it's empty.
+        public static int PackedFieldSize(int field, IEnumerable<Int64> values) {
+            var len = values.Sum(v => VarintSize(unchecked((UInt64)v)));
+            return len == 0 ? 0 : LenFieldSize(field, len);
+        }
+
+        // Fixed size header in front of every RPC message, little endian.
+        public struct RpcHeader : IMessage {
+            public const int Size = 8;
+
+            // method_id << 1 for requests, method_id << 1 | 1 for responses.
+            public UInt32 msg_id {get;set;}
+            // Correlates a reply with its request, zero for one-way requests.
+            public UInt32 request_id {get;set;}
+
+            public static (RpcHeader?, int) tryDeserialize(byte[] bytes) {
+                if (bytes.Length < Size) {
+                    return (null, 0);
+                }
+                RpcHeader header = new RpcHeader();
+                header.msg_id = BinaryPrimitives.ReadUInt32LittleEndian(bytes);
+                header.request_id = BinaryPrimitives.ReadUInt32LittleEndian(bytes.AsSpan(4));
+                return (header, Size);
+            }
+
+            public int? serializeInto(byte[] bytes) {
+                if (bytes.Length < Size) {
+                    return null;
+                }
+                BinaryPrimitives.WriteUInt32LittleEndian(bytes, msg_id);
+                BinaryPrimitives.WriteUInt32LittleEndian(bytes.AsSpan(4), request_id);
+                return Size;
+            }
+        }
+
+        // Frame size for schemas without a max_frame_size option.
+        public const int FrameBufferSize = 4096;
+        // Largest frame the generated clients and dispatchers will produce.
+        public const int MaxFrameSize = 512;
+        // Largest encoded message, leaving room for the header and status of a reply.
+        public const int MaxMessageSize = MaxFrameSize - RpcHeader.Size - 4;
+
+        public static byte[] SerializeFrame(RpcHeader header, IMessage msg) {
+            var bytes = new byte[MaxFrameSize];
+            var amt = header.serializeInto(bytes)!.Value;
+            // Ranges copy arrays, serialize the body separately and copy it back.
+            var body = new byte[bytes.Length - amt];
+            var bodyAmt = msg.serializeInto(body);
+            if (bodyAmt == null) {
+                throw new RpcException(RpcStatus.ResourceExhausted, "Message doesn't fit in a frame");
+            }
+            Array.Copy(body, 0, bytes, amt, bodyAmt.Value);
+            return bytes[..(amt + bodyAmt.Value)];
+        }
+
+        // Reply frame for a request, null if the request was one-way.
+        //
+        // The header is followed by a little endian UInt32 status. A successful
+        // reply continues with the body of the returned message, a failed one
+        // with the rest of the RpcException.
+        public static byte[]? SerializeReply(UInt32 msgId, UInt32 requestId, IMessage ret) {
+            if (requestId == 0) {
+                return null;
+            }
+            var header = new RpcHeader();
+            header.msg_id = msgId;
+            header.request_id = requestId;
+            var bytes = new byte[MaxFrameSize];
+            var amt = header.serializeInto(bytes)!.Value;
+            BinaryPrimitives.WriteUInt32LittleEndian(bytes.AsSpan(amt), (UInt32)RpcStatus.Ok);
+            amt += 4;
+            // Ranges copy arrays, serialize the body separately and copy it back.
+            var body = new byte[bytes.Length - amt];
+            var bodyAmt = ret.serializeInto(body);
+            if (bodyAmt == null) {
+                throw new RpcException(RpcStatus.ResourceExhausted, "Reply doesn't fit in a frame");
+            }
+            Array.Copy(body, 0, bytes, amt, bodyAmt.Value);
+            return bytes[..(amt + bodyAmt.Value)];
+        }
+
+        // Error reply frame for a request, null if the request was one-way.
+        public static byte[]? SerializeErrorReply(UInt32 msgId, UInt32 requestId, RpcException error) {
+            if (requestId == 0) {
+                return null;
+            }
+            var header = new RpcHeader();
+            header.msg_id = msgId;
+            header.request_id = requestId;
+            return SerializeFrame(header, error);
+        }
+
+        // Decode what follows the header of a reply frame. Either the message or
+        // the error is set, a malformed frame becomes a DataLoss error.
+        public static (TRet?, RpcException?) TryDeserializeReply<TRet>(byte[] bytes, Func<byte[], (TRet?, int)> parse) where TRet : struct {
+            var malformed = new RpcException(RpcStatus.DataLoss, "Malformed reply");
+            if (bytes.Length < 4) {
+                return (null, malformed);
+            }
+            if (BinaryPrimitives.ReadUInt32LittleEndian(bytes) == (UInt32)RpcStatus.Ok) {
+                var (ret, _) = parse(bytes[4..]);
+                return ret == null ? (null, malformed) : (ret, null);
+            }
+            return (null, RpcException.tryDeserialize(bytes) ?? malformed);
+        }
+
+        // Writes messages in the protobuf text format, `name: "a" point { x: 1 }`,
+        // on one line or, when pretty, one field per line with nesting indented.
+        public class TextFormatWriter {
+            private StringBuilder sb = new StringBuilder();
+            private bool pretty;
+            private int depth;
+            // Nothing written in the current message yet.
+            private bool first = true;
+
+            public TextFormatWriter(bool pretty) {
+                this.pretty = pretty;
+            }
+
+            public static string Write(ITextMessage msg, bool pretty) {
+                var w = new TextFormatWriter(pretty);
+                msg.writeText(w);
+                return w.sb.ToString();
+            }
+
+            private void Name(string name) {
+                if (!(first && depth == 0)) {
+                    sb.Append(pretty ? '\n' : ' ');
+                }
+                if (pretty) {
+                    sb.Append(' ', 2 * depth);
+                }
+                first = false;
+                sb.Append(name);
+            }
+
+            public void Int(string name, IFormattable value) {
+                Name(name);
+                sb.Append(": ").Append(value.ToString(null, CultureInfo.InvariantCulture));
+            }
+
+            public void String(string name, string value) {
+                Name(name);
+                sb.Append(": \"");
+                foreach (var c in value) {
+                    switch (c) {
+                        case '"': sb.Append("\\\""); break;
+                        case '\\': sb.Append("\\\\"); break;
+                        case '\n': sb.Append("\\n"); break;
+                        case '\r': sb.Append("\\r"); break;
+                        case '\t': sb.Append("\\t"); break;
+                        default:
+                            if (c < 0x20 || c == 0x7f) {
+                                sb.Append("\\x").Append(((int)c).ToString("x2"));
+                            } else {
+                                sb.Append(c);
+                            }
+                            break;
+                    }
+                }
+                sb.Append('"');
+            }
+
+            // Enum values are written by name, from the generated XxxText classes.
+            public void Enum(string name, string value) {
+                Name(name);
+                sb.Append(": ").Append(value);
+            }
+
+            public void Message(string name, ITextMessage msg) {
+                Name(name);
+                sb.Append(" {");
+                depth++;
+                first = true;
+                msg.writeText(this);
+                var empty = first;
+                depth--;
+                first = false;
+                if (empty) {
+                    sb.Append('}');
+                } else if (pretty) {
+                    sb.Append('\n').Append(' ', 2 * depth).Append('}');
+                } else {
+                    sb.Append(" }");
+                }
+            }
+        }
+
+        // Parses the protobuf text format. Errors throw FormatException with the
+        // line and column they were found at.
+        public class TextFormatReader {
+            private enum TokenKind { Ident, Number, Str, Punct, End }
+
+            private string src;
+            private int pos;
+            // Start of the last token read, where errors point.
+            private int tokenStart;
+
+            public TextFormatReader(string src) {
+                this.src = src;
+            }
+
+            public static T Parse<T>(string text) where T : struct, ITextMessage {
+                var r = new TextFormatReader(text);
+                var msg = new T();
+                r.MergeFields(ref msg, null);
+                return msg;
+            }
+
+            // An error at the start of the last token read.
+            public FormatException Error(string msg) {
+                var line = 1;
+                var lineStart = 0;
+                for (var i = 0; i < tokenStart; i++) {
+                    if (src[i] == '\n') {
+                        line++;
+                        lineStart = i + 1;
+                    }
+                }
+                return new FormatException($"{line}:{tokenStart - lineStart + 1}: {msg}");
+            }
+
+            public FormatException UnknownField(string name) {
+                return Error($"Unknown field {name}");
+            }
+
+            private void SkipSpace() {
+                while (pos < src.Length) {
+                    if (char.IsWhiteSpace(src[pos])) {
+                        pos++;
+                    } else if (src[pos] == '#') {
+                        while (pos < src.Length && src[pos] != '\n') {
+                            pos++;
+                        }
+                    } else {
+                        break;
+                    }
+                }
+            }
+
+            private static bool IsWord(char c) {
+                return c < 0x80 && (char.IsLetterOrDigit(c) || c == '_');
+            }
+
+            private (TokenKind, string) Next() {
+                SkipSpace();
+                tokenStart = pos;
+                if (pos == src.Length) {
+                    return (TokenKind.End, "end of input");
+                }
+                var c = src[pos];
+                var kind = TokenKind.Punct;
+                if (c < 0x80 && (char.IsLetter(c) || c == '_')) {
+                    kind = TokenKind.Ident;
+                } else if (c < 0x80 && char.IsDigit(c) || c == '-') {
+                    kind = TokenKind.Number;
+                }
+                if (kind != TokenKind.Punct) {
+                    pos++;
+                    while (pos < src.Length && IsWord(src[pos])) {
+                        pos++;
+                    }
+                } else if (c == '"' || c == '\'') {
+                    kind = TokenKind.Str;
+                    pos++;
+                    while (pos < src.Length && src[pos] != c && src[pos] != '\n') {
+                        pos += src[pos] == '\\' && pos + 1 < src.Length && src[pos + 1] != '\n' ? 2 : 1;
+                    }
+                    if (pos == src.Length || src[pos] != c) {
+                        pos = tokenStart;
+                        throw Error("Unterminated string");
+                    }
+                    pos++;
+                } else {
+                    pos++;
+                }
+                return (kind, src[tokenStart..pos]);
+            }
+
+            private (TokenKind, string) Peek() {
+                var (savedPos, savedStart) = (pos, tokenStart);
+                var token = Next();
+                (pos, tokenStart) = (savedPos, savedStart);
+                return token;
+            }
+
+            private FormatException Expected(string what, string found) {
+                return Error($"Expected {what}, found {found}");
+            }
+
+            // Fields up to close, or the end of the input at the top level.
+            private void MergeFields<T>(ref T msg, char? close) where T : ITextMessage {
+                while (true) {
+                    var (kind, text) = Next();
+                    if (kind == TokenKind.End && close == null || kind == TokenKind.Punct && text[0] == close) {
+                        return;
+                    }
+                    if (kind != TokenKind.Ident) {
+                        throw Expected("field name", text);
+                    }
+                    var nameStart = tokenStart;
+                    if (Peek() == (TokenKind.Punct, ":")) {
+                        Next();
+                    }
+                    // So unknown fields point at their name.
+                    tokenStart = nameStart;
+                    msg.mergeTextField(text, this);
+                    if (Peek() is (TokenKind.Punct, "," or ";")) {
+                        Next();
+                    }
+                }
+            }
+
+            // A `{ ... }` or `< ... >` block.
+            public T Message<T>() where T : struct, ITextMessage {
+                return Message(new T());
+            }
+
+            // A block merged into msg, for fields that occur more than once.
+            public T Message<T>(T msg) where T : struct, ITextMessage {
+                var (kind, text) = Next();
+                if (kind == TokenKind.Punct && (text == "{" || text == "<")) {
+                    MergeFields(ref msg, text == "{" ? '}' : '>');
+                    return msg;
+                }
+                throw Expected("{", text);
+            }
+
+            // The sign and magnitude of a decimal or 0x hexadecimal integer.
+            private (bool, UInt64) Number() {
+                var (kind, text) = Next();
+                if (kind != TokenKind.Number) {
+                    throw Expected("integer", text);
+                }
+                var negative = text.StartsWith("-");
+                var digits = negative ? text[1..] : text;
+                var hex = digits.StartsWith("0x") || digits.StartsWith("0X");
+                var ok = hex
+                    ? UInt64.TryParse(digits[2..], NumberStyles.AllowHexSpecifier, CultureInfo.InvariantCulture, out var magnitude)
+                    : UInt64.TryParse(digits, NumberStyles.None, CultureInfo.InvariantCulture, out magnitude);
+                if (!ok) {
+                    throw Expected("integer", text);
+                }
+                return (negative, magnitude);
+            }
+
+            public Int64 Signed(Int64 min, Int64 max) {
+                var (negative, magnitude) = Number();
+                if (!negative && magnitude <= (UInt64)max) {
+                    return (Int64)magnitude;
+                }
+                if (negative && magnitude <= (UInt64)(-(min + 1)) + 1) {
+                    return magnitude == 0 ? 0 : -(Int64)(magnitude - 1) - 1;
+                }
+                throw Error($"Integer {src[tokenStart..pos]} out of range");
+            }
+
+            public UInt64 Unsigned(UInt64 max) {
+                var (negative, magnitude) = Number();
+                if (magnitude <= max && (!negative || magnitude == 0)) {
+                    return magnitude;
+                }
+                throw Error($"Integer {src[tokenStart..pos]} out of range");
+            }
+
+            // A quoted string, adjacent ones are joined.
+            public string String() {
+                var (kind, text) = Next();
+                if (kind != TokenKind.Str) {
+                    throw Expected("string", text);
+                }
+                var bytes = new List<byte>();
+                Unescape(text[1..^1], bytes);
+                while (Peek().Item1 == TokenKind.Str) {
+                    Unescape(Next().Item2[1..^1], bytes);
+                }
+                try {
+                    return new UTF8Encoding(false, true).GetString(bytes.ToArray());
+                } catch (ArgumentException) {
+                    throw Error("Invalid UTF-8 in string");
+                }
+            }
+
+            private static bool IsOctal(char c) {
+                return c >= '0' && c <= '7';
+            }
+
+            private void Unescape(string s, List<byte> bytes) {
+                for (var i = 0; i < s.Length; i++) {
+                    if (s[i] != '\\') {
+                        var end = char.IsHighSurrogate(s[i]) && i + 1 < s.Length ? i + 2 : i + 1;
+                        bytes.AddRange(Encoding.UTF8.GetBytes(s[i..end]));
+                        i = end - 1;
+                        continue;
+                    }
+                    var c = s[++i];
+                    switch (c) {
+                        case 'n': bytes.Add((byte)'\n'); break;
+                        case 'r': bytes.Add((byte)'\r'); break;
+                        case 't': bytes.Add((byte)'\t'); break;
+                        case '"': case '\'': case '\\': bytes.Add((byte)c); break;
+                        case 'x':
+                            if (i + 2 >= s.Length || !byte.TryParse(s[(i + 1)..(i + 3)], NumberStyles.AllowHexSpecifier, CultureInfo.InvariantCulture, out var b)) {
+                                throw Error("Invalid \\x escape in string");
+                            }
+                            bytes.Add(b);
+                            i += 2;
+                            break;
+                        default:
+                            if (!IsOctal(c)) {
+                                throw Error("Invalid escape in string");
+                            }
+                            var value = c - '0';
+                            for (var n = 0; n < 2 && i + 1 < s.Length && IsOctal(s[i + 1]); n++) {
+                                value = value * 8 + (s[++i] - '0');
+                            }
+                            if (value > 0xff) {
+                                throw Error("Invalid octal escape in string");
+                            }
+                            bytes.Add((byte)value);
+                            break;
+                    }
+                }
+            }
+
+            // The name of an enum value, or its number for values this schema
+            // version doesn't know.
+            public T EnumValue<T>(Func<string, T?> parse) where T : struct, System.Enum {
+                var (kind, text) = Peek();
+                if (kind == TokenKind.Number) {
+                    return (T)System.Enum.ToObject(typeof(T), Unsigned(UInt32.MaxValue));
+                }
+                Next();
+                if (kind != TokenKind.Ident) {
+                    throw Expected("enum value", text);
+                }
+                return parse(text) ?? throw Error($"Unknown enum value {text}");
+            }
+
+            // The values of a repeated field, either one or a `[a, b]` list, each
+            // read by f.
+            public void Repeated(Action f) {
+                if (Peek() != (TokenKind.Punct, "[")) {
+                    f();
+                    return;
+                }
+                Next();
+                if (Peek() == (TokenKind.Punct, "]")) {
+                    Next();
+                    return;
+                }
+                while (true) {
+                    f();
+                    var (kind, text) = Next();
+                    if (kind == TokenKind.Punct && text == "]") {
+                        return;
+                    }
+                    if (kind != TokenKind.Punct || text != ",") {
+                        throw Expected(", or ]", text);
+                    }
+                }
+            }
+        }
+
+        }
+        public interface  IMessage {
+
+            public int? serializeInto(byte[] bytes);
+        }
+
+        // A message in the protobuf binary encoding, implemented by generated code.
+        public interface IProtoMessage : IMessage {
+            // Exact length of the encoded message.
+            public int serializedSize();
+            // Write all fields that aren't at their default value.
+            public void encodeFields(Builtin.WireWriter w);
+        }
+
+        // A message in the text format, implemented by generated code.
+        public interface ITextMessage {
+            // Write all fields that aren't at their default value.
+            public void writeText(Builtin.TextFormatWriter w);
+            // Parse the value of field name, its name and colon are already read.
+            public void mergeTextField(string name, Builtin.TextFormatReader r);
+        }
+
+        // Status codes carried by RPC errors, numbered like gRPC's.
+        public enum RpcStatus : UInt32 {
+            Ok = 0,
+            Cancelled = 1,
+            Unknown = 2,
+            InvalidArgument = 3,
+            DeadlineExceeded = 4,
+            NotFound = 5,
+            AlreadyExists = 6,
+            PermissionDenied = 7,
+            ResourceExhausted = 8,
+            FailedPrecondition = 9,
+            Aborted = 10,
+            OutOfRange = 11,
+            Unimplemented = 12,
+            Internal = 13,
+            Unavailable = 14,
+            DataLoss = 15,
+            Unauthenticated = 16,
+        }
+
+        // A failed RPC, sent back to the caller in place of the reply message.
+        public class RpcException : Exception, IMessage {
+            // Never RpcStatus.Ok.
+            public RpcStatus Status { get; }
+            // Application defined payload, e.g. a serialized message.
+            public byte[] Details { get; }
+
+            public RpcException(RpcStatus status, string message, byte[]? details = null) : base(message) {
+                Status = status;
+                Details = details ?? Array.Empty<byte>();
+            }
+
+            // Serialized as the little endian UInt32 status, the UInt32 length of
+            // the message, the UTF-8 message and finally the details, which run
+            // to the end of the frame. An error built with Ok goes out as Unknown,
+            // the peer would take it for a successful reply otherwise.
+            public int? serializeInto(byte[] bytes) {
+                var message = Encoding.UTF8.GetBytes(Message);
+                var size = 8 + message.Length + Details.Length;
+                if (bytes.Length < size) {
+                    return null;
+                }
+                var status = Status == RpcStatus.Ok ? RpcStatus.Unknown : Status;
+                BinaryPrimitives.WriteUInt32LittleEndian(bytes, (UInt32)status);
+                BinaryPrimitives.WriteUInt32LittleEndian(bytes.AsSpan(4), (UInt32)message.Length);
+                message.CopyTo(bytes, 8);
+                Details.CopyTo(bytes, 8 + message.Length);
+                return size;
+            }
+
+            public static RpcException? tryDeserialize(byte[] bytes) {
+                if (bytes.Length < 8) {
+                    return null;
+                }
+                var status = BinaryPrimitives.ReadUInt32LittleEndian(bytes);
+                var len = BinaryPrimitives.ReadUInt32LittleEndian(bytes.AsSpan(4));
+                if (len > bytes.Length - 8) {
+                    return null;
+                }
+                var end = 8 + (int)len;
+                // Codes this version doesn't know about become Unknown.
+                var known = Enum.IsDefined(typeof(RpcStatus), status) ? (RpcStatus)status : RpcStatus.Unknown;
+                return new RpcException(known, Encoding.UTF8.GetString(bytes, 8, (int)len), bytes[end..]);
+            }
+        }
+
+        // A message larger than MaxMessageSize, thrown when encoding or decoding it.
+        public class MessageTooLargeException : RpcException {
+            public int Size { get; }
+            public int Max { get; }
+
+            public MessageTooLargeException(int size, int max)
+                : base(RpcStatus.ResourceExhausted, $"Message of {size} bytes exceeds the {max} byte limit") {
+                Size = size;
+                Max = max;
+            }
+        }
+
+        // Moves whole frames, each one holding a single RpcHeader and message.
+        public interface ITransport : IDisposable {
+            Task SendAsync(byte[] frame, CancellationToken ct);
+            Task<byte[]> ReceiveAsync(CancellationToken ct);
+        }
+
+        // One frame per datagram. Unless connected, frames are sent to
+        // whoever sent the last received frame, which is what servers want.
+        public class UdpTransport : ITransport {
+            // Largest payload that avoids IP fragmentation on a 1500 byte MTU.
+            public const int DefaultMtu = 1472;
+
+            private UdpClient client;
+            private bool connected;
+            private IPEndPoint? peer;
+
+            public int Mtu { get; set; } = DefaultMtu;
+
+            public UdpTransport(UdpClient client, bool connected) {
+                this.client = client;
+                this.connected = connected;
+            }
+
+            public static UdpTransport Connect(string host, int port) {
+                var client = new UdpClient();
+                client.Connect(host, port);
+                return new UdpTransport(client, true);
+            }
+
+            public static UdpTransport Bind(int port) {
+                return new UdpTransport(new UdpClient(port), false);
+            }
+
+            public async Task SendAsync(byte[] frame, CancellationToken ct) {
+                if (frame.Length > Mtu) {
+                    throw new ArgumentException($"Frame of {frame.Length} bytes exceeds the {Mtu} byte MTU");
+                }
+                if (connected) {
+                    await client.SendAsync(frame, ct);
+                } else if (peer != null) {
+                    await client.SendAsync(frame, peer, ct);
+                } else {
+                    throw new InvalidOperationException("No peer to send to");
+                }
+            }
+
+            public async Task<byte[]> ReceiveAsync(CancellationToken ct) {
+                var result = await client.ReceiveAsync(ct);
+                if (!connected) {
+                    peer = result.RemoteEndPoint;
+                }
+                return result.Buffer;
+            }
+
+            public void Dispose() {
+                client.Dispose();
+            }
+        }
+
+        // Frames on a stream, each prefixed with its length as a little endian UInt32.
+        public class TcpTransport : ITransport {
+            public const int DefaultMaxFrameSize = 64 * 1024;
+
+            private TcpClient client;
+            private NetworkStream stream;
+
+            public int MaxFrameSize { get; set; } = DefaultMaxFrameSize;
+
+            public TcpTransport(TcpClient client) {
+                this.client = client;
+                client.NoDelay = true;
+                stream = client.GetStream();
+            }
+
+            public static async Task<TcpTransport> ConnectAsync(string host, int port, CancellationToken ct = default) {
+                var client = new TcpClient();
+                await client.ConnectAsync(host, port, ct);
+                return new TcpTransport(client);
+            }
+
+            public async Task SendAsync(byte[] frame, CancellationToken ct) {
+                if (frame.Length > MaxFrameSize) {
+                    throw new ArgumentException($"Frame of {frame.Length} bytes exceeds the {MaxFrameSize} byte limit");
+                }
+                var bytes = new byte[4 + frame.Length];
+                BinaryPrimitives.WriteUInt32LittleEndian(bytes, (UInt32)frame.Length);
+                frame.CopyTo(bytes, 4);
+                await stream.WriteAsync(bytes, ct);
+            }
+
+            // Cancelling part way through a frame leaves the stream out of sync,
+            // the transport should be disposed afterwards.
+            public async Task<byte[]> ReceiveAsync(CancellationToken ct) {
+                var prefix = new byte[4];
+                await ReadExactAsync(prefix, ct);
+                var len = BinaryPrimitives.ReadUInt32LittleEndian(prefix);
+                if (len > MaxFrameSize) {
+                    throw new InvalidDataException($"Frame of {len} bytes exceeds the {MaxFrameSize} byte limit");
+                }
+                var frame = new byte[len];
+                await ReadExactAsync(frame, ct);
+                return frame;
+            }
+
+            private async Task ReadExactAsync(byte[] bytes, CancellationToken ct) {
+                var offset = 0;
+                while (offset < bytes.Length) {
+                    var amt = await stream.ReadAsync(bytes.AsMemory(offset), ct);
+                    if (amt == 0) {
+                        throw new EndOfStreamException();
+                    }
+                    offset += amt;
+                }
+            }
+
+            public void Dispose() {
+                stream.Dispose();
+                client.Dispose();
+            }
+        }
+
+        // Sends requests and matches up replies, shared by the generated service clients.
+        public class RpcClient {
+            public static readonly TimeSpan DefaultTimeout = TimeSpan.FromSeconds(1);
+
+            private ITransport transport;
+            // One call at a time, replies are read off the same transport.
+            private SemaphoreSlim callLock = new SemaphoreSlim(1, 1);
+            private UInt32 nextRequestId = 1;
+
+            public TimeSpan Timeout { get; set; } = DefaultTimeout;
+            public ITransport Transport => transport;
+
+            public RpcClient(ITransport transport) {
+                this.transport = transport;
+            }
+
+            private UInt32 NextRequestId() {
+                var id = nextRequestId;
+                // Zero marks one-way requests.
+                nextRequestId = nextRequestId == UInt32.MaxValue ? 1 : nextRequestId + 1;
+                return id;
+            }
+
+            public async Task<TRet> CallAsync<TRet>(UInt32 argId, UInt32 retId, IMessage req, Func<byte[], (TRet?, int)> parse, CancellationToken ct) where TRet : struct {
+                await callLock.WaitAsync(ct);
+                try {
+                    var header = new Builtin.RpcHeader();
+                    header.msg_id = argId;
+                    header.request_id = NextRequestId();
+                    await transport.SendAsync(Builtin.SerializeFrame(header, req), ct);
+
+                    using var deadline = CancellationTokenSource.CreateLinkedTokenSource(ct);
+                    deadline.CancelAfter(Timeout);
+                    while (true) {
+                        byte[] frame;
+                        try {
+                            frame = await transport.ReceiveAsync(deadline.Token);
+                        } catch (OperationCanceledException) when (!ct.IsCancellationRequested) {
+                            throw new RpcException(RpcStatus.DeadlineExceeded, "Timed out waiting for a reply");
+                        }
+                        var (reply, amt) = Builtin.RpcHeader.tryDeserialize(frame);
+                        // Replies to earlier calls that timed out.
+                        if (reply == null || reply.Value.request_id != header.request_id || reply.Value.msg_id != retId) {
+                            continue;
+                        }
+                        var (ret, error) = Builtin.TryDeserializeReply(frame[amt..], parse);
+                        if (error != null) {
+                            throw error;
+                        }
+                        return ret!.Value;
+                    }
+                } finally {
+                    callLock.Release();
+                }
+            }
+
+            public Task SendOnewayAsync(UInt32 argId, IMessage req, CancellationToken ct) {
+                var header = new Builtin.RpcHeader();
+                header.msg_id = argId;
+                return transport.SendAsync(Builtin.SerializeFrame(header, req), ct);
+            }
+        }
+
+}
+namespace Proto {
+    [StructLayout(LayoutKind.Sequential)]
+    public struct AllFieldsPoint : IProtoMessage, ITextMessage
+    {
+        public Int32 X {get; set;}
+        public Int32 Y {get; set;}
+        // Fields of newer schema versions, written back when encoding.
+        public byte[]? UnknownFields {get; set;}
+
+        // The body runs to the end of the frame. Returns null for malformed ones,
+        // throws MessageTooLargeException for ones over MaxMessageSize.
+        public static (AllFieldsPoint?, int) tryDeserializeBody(byte[] bytes) {
+            var msg = new AllFieldsPoint();
+            if (!tryMergeBody(ref msg, bytes)) {
+                return (null, 0);
+            }
+            return (msg, bytes.Length);
+        }
+
+        // Decode the body on top of msg, like a repeated occurrence of a message
+        // field: set fields replace, repeated ones and messages merge. Returns
+        // false for malformed bodies, leaving msg partly merged.
+        public static bool tryMergeBody(ref AllFieldsPoint msg, byte[] bytes) {
+            if (bytes.Length > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(bytes.Length, Builtin.MaxMessageSize);
+            }
+            var r = new Builtin.WireReader(bytes);
+            var unknown = msg.UnknownFields?.ToList();
+            while (r.Next(out var field)) {
+                switch (field) {
+                    case 1: {
+                        msg.X = unchecked((Int32)r.Varint());
+                        break;
+                    }
+                    case 2: {
+                        msg.Y = unchecked((Int32)r.Varint());
+                        break;
+                    }
+                    default:
+                        // Fields of newer schema versions.
+                        (unknown ??= new List<byte>()).AddRange(r.SkipRaw());
+                        break;
+                }
+            }
+            if (r.Failed) {
+                return false;
+            }
+            msg.UnknownFields = unknown?.ToArray();
+            return true;
+        }
+
+        // Returns null if the message doesn't fit in bytes, throws
+        // MessageTooLargeException if it's over MaxMessageSize.
+        public int? serializeInto(byte[] bytes) {
+            var size = serializedSize();
+            if (size > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(size, Builtin.MaxMessageSize);
+            }
+            var w = new Builtin.WireWriter(bytes);
+            encodeFields(w);
+            return w.Overflow ? null : w.Position;
+        }
+
+        public void encodeFields(Builtin.WireWriter w) {
+            if (unchecked((Int64)X) != 0) {
+                w.Varint(1, unchecked((Int64)X));
+            }
+            if (unchecked((Int64)Y) != 0) {
+                w.Varint(2, unchecked((Int64)Y));
+            }
+            if (UnknownFields != null) {
+                w.Raw(UnknownFields);
+            }
+        }
+
+        public int serializedSize() {
+            var size = 0;
+            if (unchecked((Int64)X) != 0) {
+                size += Builtin.VarintFieldSize(1, unchecked((Int64)X));
+            }
+            if (unchecked((Int64)Y) != 0) {
+                size += Builtin.VarintFieldSize(2, unchecked((Int64)Y));
+            }
+            size += UnknownFields?.Length ?? 0;
+            return size;
+        }
+
+        // The text format, `name: "a" point { x: 1 }`, for logs and test data.
+        public override string ToString() {
+            return ToText();
+        }
+
+        public string ToText(bool pretty = false) {
+            return Builtin.TextFormatWriter.Write(this, pretty);
+        }
+
+        // Throws FormatException for malformed text.
+        public static AllFieldsPoint ParseText(string text) {
+            return Builtin.TextFormatReader.Parse<AllFieldsPoint>(text);
+        }
+
+        public void writeText(Builtin.TextFormatWriter w) {
+            if (unchecked((Int64)X) != 0) {
+                w.Int("x", X);
+            }
+            if (unchecked((Int64)Y) != 0) {
+                w.Int("y", Y);
+            }
+        }
+
+        public void mergeTextField(string name, Builtin.TextFormatReader r) {
+            switch (name) {
+                case "x": {
+                    X = (Int32)r.Signed(Int32.MinValue, Int32.MaxValue);
+                    break;
+                }
+                case "y": {
+                    Y = (Int32)r.Signed(Int32.MinValue, Int32.MaxValue);
+                    break;
+                }
+                default:
+                    throw r.UnknownField(name);
+            }
+        }
+    }
+}
+namespace Proto {
+
+    public enum AllFieldsColor : UInt32 {
+         Red = 0,  Green = 1, 
+    }
+
+    // Names of the values in the schema, for the text format.
+    public static class AllFieldsColorText {
+        public static string Name(AllFieldsColor value) {
+            return value switch {
+                AllFieldsColor.Red => "RED",
+                AllFieldsColor.Green => "GREEN",
+                _ => ((UInt32)value).ToString(CultureInfo.InvariantCulture),
+            };
+        }
+
+        public static AllFieldsColor? Parse(string name) {
+            return name switch {
+                "RED" => AllFieldsColor.Red,
+                "GREEN" => AllFieldsColor.Green,
+                _ => null,
+            };
+        }
+    }
+}
+namespace Proto {
+    [StructLayout(LayoutKind.Sequential)]
+    public struct AllFields : IProtoMessage, ITextMessage
+    {
+        public Int32 Small {get; set;}
+        public Int64 Big {get; set;}
+        public UInt32 Count {get; set;}
+        public UInt64 Total {get; set;}
+        public string Name {get; set;}
+        public AllFieldsColor Color {get; set;}
+        public AllFieldsPoint Point {get; set;}
+        public Int32? OptSmall {get; set;}
+        public string? OptName {get; set;}
+        public AllFieldsColor? OptColor {get; set;}
+        public AllFieldsPoint? OptPoint {get; set;}
+        public List<Int64> Bigs {get; set;}
+        public List<string> Names {get; set;}
+        public List<AllFieldsColor> Colors {get; set;}
+        public List<AllFieldsPoint> Points {get; set;}
+        public string Id {get; set;}
+        // Fields of newer schema versions, written back when encoding.
+        public byte[]? UnknownFields {get; set;}
+
+        // The body runs to the end of the frame. Returns null for malformed ones,
+        // throws MessageTooLargeException for ones over MaxMessageSize.
+        public static (AllFields?, int) tryDeserializeBody(byte[] bytes) {
+            var msg = new AllFields();
+            if (!tryMergeBody(ref msg, bytes)) {
+                return (null, 0);
+            }
+            return (msg, bytes.Length);
+        }
+
+        // Decode the body on top of msg, like a repeated occurrence of a message
+        // field: set fields replace, repeated ones and messages merge. Returns
+        // false for malformed bodies, leaving msg partly merged.
+        public static bool tryMergeBody(ref AllFields msg, byte[] bytes) {
+            if (bytes.Length > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(bytes.Length, Builtin.MaxMessageSize);
+            }
+            var r = new Builtin.WireReader(bytes);
+            var unknown = msg.UnknownFields?.ToList();
+            while (r.Next(out var field)) {
+                switch (field) {
+                    case 1: {
+                        msg.Small = unchecked((Int32)r.Varint());
+                        break;
+                    }
+                    case 2: {
+                        msg.Big = unchecked((Int64)r.Varint());
+                        break;
+                    }
+                    case 3: {
+                        msg.Count = unchecked((UInt32)r.Varint());
+                        break;
+                    }
+                    case 4: {
+                        msg.Total = unchecked((UInt64)r.Varint());
+                        break;
+                    }
+                    case 5: {
+                        msg.Name = r.String();
+                        break;
+                    }
+                    case 6: {
+                        msg.Color = unchecked((AllFieldsColor)r.Varint());
+                        break;
+                    }
+                    case 7: {
+                        // Occurring again merges into what's there.
+                        var m = msg.Point;
+                        if (!AllFieldsPoint.tryMergeBody(ref m, r.Bytes())) {
+                            return false;
+                        }
+                        msg.Point = m;
+                        break;
+                    }
+                    case 8: {
+                        msg.OptSmall = unchecked((Int32)r.Varint());
+                        break;
+                    }
+                    case 9: {
+                        msg.OptName = r.String();
+                        break;
+                    }
+                    case 10: {
+                        msg.OptColor = unchecked((AllFieldsColor)r.Varint());
+                        break;
+                    }
+                    case 11: {
+                        // Occurring again merges into what's there.
+                        var m = msg.OptPoint ?? new AllFieldsPoint();
+                        if (!AllFieldsPoint.tryMergeBody(ref m, r.Bytes())) {
+                            return false;
+                        }
+                        msg.OptPoint = m;
+                        break;
+                    }
+                    case 12: {
+                        (msg.Bigs ??= new List<Int64>()).AddRange(r.Varints().Select(v => unchecked((Int64)v)));
+                        break;
+                    }
+                    case 13: {
+                        (msg.Names ??= new List<string>()).Add(r.String());
+                        break;
+                    }
+                    case 14: {
+                        (msg.Colors ??= new List<AllFieldsColor>()).AddRange(r.Varints().Select(v => unchecked((AllFieldsColor)v)));
+                        break;
+                    }
+                    case 15: {
+                        var (m, _) = AllFieldsPoint.tryDeserializeBody(r.Bytes());
+                        if (m == null) {
+                            return false;
+                        }
+                        (msg.Points ??= new List<AllFieldsPoint>()).Add(m.Value);
+                        break;
+                    }
+                    case 16: {
+                        msg.Id = r.String();
+                        break;
+                    }
+                    default:
+                        // Fields of newer schema versions.
+                        (unknown ??= new List<byte>()).AddRange(r.SkipRaw());
+                        break;
+                }
+            }
+            if (r.Failed) {
+                return false;
+            }
+            msg.UnknownFields = unknown?.ToArray();
+            return true;
+        }
+
+        // Returns null if the message doesn't fit in bytes, throws
+        // MessageTooLargeException if it's over MaxMessageSize.
+        public int? serializeInto(byte[] bytes) {
+            var size = serializedSize();
+            if (size > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(size, Builtin.MaxMessageSize);
+            }
+            var w = new Builtin.WireWriter(bytes);
+            encodeFields(w);
+            return w.Overflow ? null : w.Position;
+        }
+
+        public void encodeFields(Builtin.WireWriter w) {
+            if (unchecked((Int64)Small) != 0) {
+                w.Varint(1, unchecked((Int64)Small));
+            }
+            if (unchecked((Int64)Big) != 0) {
+                w.Varint(2, unchecked((Int64)Big));
+            }
+            if (unchecked((Int64)Count) != 0) {
+                w.Varint(3, unchecked((Int64)Count));
+            }
+            if (unchecked((Int64)Total) != 0) {
+                w.Varint(4, unchecked((Int64)Total));
+            }
+            if (!string.IsNullOrEmpty(Name)) {
+                w.String(5, Name);
+            }
+            if (unchecked((Int64)Color) != 0) {
+                w.Varint(6, unchecked((Int64)Color));
+            }
+            if (Point.serializedSize() != 0) {
+                w.Message(7, Point);
+            }
+            if (OptSmall != null) {
+                w.Varint(8, unchecked((Int64)OptSmall.Value));
+            }
+            if (OptName != null) {
+                w.String(9, OptName);
+            }
+            if (OptColor != null) {
+                w.Varint(10, unchecked((Int64)OptColor.Value));
+            }
+            if (OptPoint != null) {
+                w.Message(11, OptPoint.Value);
+            }
+            if (Bigs != null) {
+                w.Packed(12, Bigs.Select(v => unchecked((Int64)v)));
+            }
+            if (Names != null) {
+                foreach (var v in Names) {
+                    w.String(13, v);
+                }
+            }
+            if (Colors != null) {
+                w.Packed(14, Colors.Select(v => unchecked((Int64)v)));
+            }
+            if (Points != null) {
+                foreach (var v in Points) {
+                    w.Message(15, v);
+                }
+            }
+            if (!string.IsNullOrEmpty(Id)) {
+                w.String(16, Id);
+            }
+            if (UnknownFields != null) {
+                w.Raw(UnknownFields);
+            }
+        }
+
+        public int serializedSize() {
+            var size = 0;
+            if (unchecked((Int64)Small) != 0) {
+                size += Builtin.VarintFieldSize(1, unchecked((Int64)Small));
+            }
+            if (unchecked((Int64)Big) != 0) {
+                size += Builtin.VarintFieldSize(2, unchecked((Int64)Big));
+            }
+            if (unchecked((Int64)Count) != 0) {
+                size += Builtin.VarintFieldSize(3, unchecked((Int64)Count));
+            }
+            if (unchecked((Int64)Total) != 0) {
+                size += Builtin.VarintFieldSize(4, unchecked((Int64)Total));
+            }
+            if (!string.IsNullOrEmpty(Name)) {
+                size += Builtin.LenFieldSize(5, Encoding.UTF8.GetByteCount(Name));
+            }
+            if (unchecked((Int64)Color) != 0) {
+                size += Builtin.VarintFieldSize(6, unchecked((Int64)Color));
+            }
+            if (Point.serializedSize() != 0) {
+                size += Builtin.LenFieldSize(7, Point.serializedSize());
+            }
+            if (OptSmall != null) {
+                size += Builtin.VarintFieldSize(8, unchecked((Int64)OptSmall.Value));
+            }
+            if (OptName != null) {
+                size += Builtin.LenFieldSize(9, Encoding.UTF8.GetByteCount(OptName));
+            }
+            if (OptColor != null) {
+                size += Builtin.VarintFieldSize(10, unchecked((Int64)OptColor.Value));
+            }
+            if (OptPoint != null) {
+                size += Builtin.LenFieldSize(11, OptPoint.Value.serializedSize());
+            }
+            if (Bigs != null) {
+                size += Builtin.PackedFieldSize(12, Bigs.Select(v => unchecked((Int64)v)));
+            }
+            if (Names != null) {
+                foreach (var v in Names) {
+                    size += Builtin.LenFieldSize(13, Encoding.UTF8.GetByteCount(v));
+                }
+            }
+            if (Colors != null) {
+                size += Builtin.PackedFieldSize(14, Colors.Select(v => unchecked((Int64)v)));
+            }
+            if (Points != null) {
+                foreach (var v in Points) {
+                    size += Builtin.LenFieldSize(15, v.serializedSize());
+                }
+            }
+            if (!string.IsNullOrEmpty(Id)) {
+                size += Builtin.LenFieldSize(16, Encoding.UTF8.GetByteCount(Id));
+            }
+            size += UnknownFields?.Length ?? 0;
+            return size;
+        }
+
+        // The text format, `name: "a" point { x: 1 }`, for logs and test data.
+        public override string ToString() {
+            return ToText();
+        }
+
+        public string ToText(bool pretty = false) {
+            return Builtin.TextFormatWriter.Write(this, pretty);
+        }
+
+        // Throws FormatException for malformed text.
+        public static AllFields ParseText(string text) {
+            return Builtin.TextFormatReader.Parse<AllFields>(text);
+        }
+
+        public void writeText(Builtin.TextFormatWriter w) {
+            if (unchecked((Int64)Small) != 0) {
+                w.Int("small", Small);
+            }
+            if (unchecked((Int64)Big) != 0) {
+                w.Int("big", Big);
+            }
+            if (unchecked((Int64)Count) != 0) {
+                w.Int("count", Count);
+            }
+            if (unchecked((Int64)Total) != 0) {
+                w.Int("total", Total);
+            }
+            if (!string.IsNullOrEmpty(Name)) {
+                w.String("name", Name);
+            }
+            if (unchecked((Int64)Color) != 0) {
+                w.Enum("color", AllFieldsColorText.Name(Color));
+            }
+            if (Point.serializedSize() != 0) {
+                w.Message("point", Point);
+            }
+            if (OptSmall != null) {
+                w.Int("opt_small", OptSmall.Value);
+            }
+            if (OptName != null) {
+                w.String("opt_name", OptName);
+            }
+            if (OptColor != null) {
+                w.Enum("opt_color", AllFieldsColorText.Name(OptColor.Value));
+            }
+            if (OptPoint != null) {
+                w.Message("opt_point", OptPoint.Value);
+            }
+            if (Bigs != null) {
+                foreach (var v in Bigs) {
+                    w.Int("bigs", v);
+                }
+            }
+            if (Names != null) {
+                foreach (var v in Names) {
+                    w.String("names", v);
+                }
+            }
+            if (Colors != null) {
+                foreach (var v in Colors) {
+                    w.Enum("colors", AllFieldsColorText.Name(v));
+                }
+            }
+            if (Points != null) {
+                foreach (var v in Points) {
+                    w.Message("points", v);
+                }
+            }
+            if (!string.IsNullOrEmpty(Id)) {
+                w.String("id", Id);
+            }
+        }
+
+        public void mergeTextField(string name, Builtin.TextFormatReader r) {
+            switch (name) {
+                case "small": {
+                    Small = (Int32)r.Signed(Int32.MinValue, Int32.MaxValue);
+                    break;
+                }
+                case "big": {
+                    Big = r.Signed(Int64.MinValue, Int64.MaxValue);
+                    break;
+                }
+                case "count": {
+                    Count = (UInt32)r.Unsigned(UInt32.MaxValue);
+                    break;
+                }
+                case "total": {
+                    Total = r.Unsigned(UInt64.MaxValue);
+                    break;
+                }
+                case "name": {
+                    Name = r.String();
+                    break;
+                }
+                case "color": {
+                    Color = r.EnumValue<AllFieldsColor>(AllFieldsColorText.Parse);
+                    break;
+                }
+                case "point": {
+                    // Occurring again merges into what's there.
+                    Point = r.Message(Point);
+                    break;
+                }
+                case "opt_small": {
+                    OptSmall = (Int32)r.Signed(Int32.MinValue, Int32.MaxValue);
+                    break;
+                }
+                case "opt_name": {
+                    OptName = r.String();
+                    break;
+                }
+                case "opt_color": {
+                    OptColor = r.EnumValue<AllFieldsColor>(AllFieldsColorText.Parse);
+                    break;
+                }
+                case "opt_point": {
+                    // Occurring again merges into what's there.
+                    OptPoint = r.Message(OptPoint ?? new AllFieldsPoint());
+                    break;
+                }
+                case "bigs": {
+                    // Lambdas can't capture this in a struct, they add to the list instead.
+                    var list = Bigs ??= new List<Int64>();
+                    r.Repeated(() => list.Add(r.Signed(Int64.MinValue, Int64.MaxValue)));
+                    break;
+                }
+                case "names": {
+                    // Lambdas can't capture this in a struct, they add to the list instead.
+                    var list = Names ??= new List<string>();
+                    r.Repeated(() => list.Add(r.String()));
+                    break;
+                }
+                case "colors": {
+                    // Lambdas can't capture this in a struct, they add to the list instead.
+                    var list = Colors ??= new List<AllFieldsColor>();
+                    r.Repeated(() => list.Add(r.EnumValue<AllFieldsColor>(AllFieldsColorText.Parse)));
+                    break;
+                }
+                case "points": {
+                    // Lambdas can't capture this in a struct, they add to the list instead.
+                    var list = Points ??= new List<AllFieldsPoint>();
+                    r.Repeated(() => list.Add(r.Message<AllFieldsPoint>()));
+                    break;
+                }
+                case "id": {
+                    Id = r.String();
+                    break;
+                }
+                default:
+                    throw r.UnknownField(name);
+            }
+        }
+    }
+}
+namespace Proto {
+    [StructLayout(LayoutKind.Sequential)]
+    public struct OnlyRepeated : IProtoMessage, ITextMessage
+    {
+        public List<UInt32> Values {get; set;}
+        // Fields of newer schema versions, written back when encoding.
+        public byte[]? UnknownFields {get; set;}
+
+        // The body runs to the end of the frame. Returns null for malformed ones,
+        // throws MessageTooLargeException for ones over MaxMessageSize.
+        public static (OnlyRepeated?, int) tryDeserializeBody(byte[] bytes) {
+            var msg = new OnlyRepeated();
+            if (!tryMergeBody(ref msg, bytes)) {
+                return (null, 0);
+            }
+            return (msg, bytes.Length);
+        }
+
+        // Decode the body on top of msg, like a repeated occurrence of a message
+        // field: set fields replace, repeated ones and messages merge. Returns
+        // false for malformed bodies, leaving msg partly merged.
+        public static bool tryMergeBody(ref OnlyRepeated msg, byte[] bytes) {
+            if (bytes.Length > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(bytes.Length, Builtin.MaxMessageSize);
+            }
+            var r = new Builtin.WireReader(bytes);
+            var unknown = msg.UnknownFields?.ToList();
+            while (r.Next(out var field)) {
+                switch (field) {
+                    case 1: {
+                        (msg.Values ??= new List<UInt32>()).AddRange(r.Varints().Select(v => unchecked((UInt32)v)));
+                        break;
+                    }
+                    default:
+                        // Fields of newer schema versions.
+                        (unknown ??= new List<byte>()).AddRange(r.SkipRaw());
+                        break;
+                }
+            }
+            if (r.Failed) {
+                return false;
+            }
+            msg.UnknownFields = unknown?.ToArray();
+            return true;
+        }
+
+        // Returns null if the message doesn't fit in bytes, throws
+        // MessageTooLargeException if it's over MaxMessageSize.
+        public int? serializeInto(byte[] bytes) {
+            var size = serializedSize();
+            if (size > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(size, Builtin.MaxMessageSize);
+            }
+            var w = new Builtin.WireWriter(bytes);
+            encodeFields(w);
+            return w.Overflow ? null : w.Position;
+        }
+
+        public void encodeFields(Builtin.WireWriter w) {
+            if (Values != null) {
+                w.Packed(1, Values.Select(v => unchecked((Int64)v)));
+            }
+            if (UnknownFields != null) {
+                w.Raw(UnknownFields);
+            }
+        }
+
+        public int serializedSize() {
+            var size = 0;
+            if (Values != null) {
+                size += Builtin.PackedFieldSize(1, Values.Select(v => unchecked((Int64)v)));
+            }
+            size += UnknownFields?.Length ?? 0;
+            return size;
+        }
+
+        // The text format, `name: "a" point { x: 1 }`, for logs and test data.
+        public override string ToString() {
+            return ToText();
+        }
+
+        public string ToText(bool pretty = false) {
+            return Builtin.TextFormatWriter.Write(this, pretty);
+        }
+
+        // Throws FormatException for malformed text.
+        public static OnlyRepeated ParseText(string text) {
+            return Builtin.TextFormatReader.Parse<OnlyRepeated>(text);
+        }
+
+        public void writeText(Builtin.TextFormatWriter w) {
+            if (Values != null) {
+                foreach (var v in Values) {
+                    w.Int("values", v);
+                }
+            }
+        }
+
+        public void mergeTextField(string name, Builtin.TextFormatReader r) {
+            switch (name) {
+                case "values": {
+                    // Lambdas can't capture this in a struct, they add to the list instead.
+                    var list = Values ??= new List<UInt32>();
+                    r.Repeated(() => list.Add((UInt32)r.Unsigned(UInt32.MaxValue)));
+                    break;
+                }
+                default:
+                    throw r.UnknownField(name);
+            }
+        }
+    }
+}
+namespace Proto {
+    [StructLayout(LayoutKind.Sequential)]
+    public struct Compact : IProtoMessage, ITextMessage
+    {
+        public UInt32 Value {get; set;}
+        // Upper bound of the encoded length, for buffers sized up front.
+        public const int MaxEncodedLen = 6;
+
+        // The body runs to the end of the frame. Returns null for malformed ones,
+        // throws MessageTooLargeException for ones over MaxMessageSize.
+        public static (Compact?, int) tryDeserializeBody(byte[] bytes) {
+            var msg = new Compact();
+            if (!tryMergeBody(ref msg, bytes)) {
+                return (null, 0);
+            }
+            return (msg, bytes.Length);
+        }
+
+        // Decode the body on top of msg, like a repeated occurrence of a message
+        // field: set fields replace, repeated ones and messages merge. Returns
+        // false for malformed bodies, leaving msg partly merged.
+        public static bool tryMergeBody(ref Compact msg, byte[] bytes) {
+            if (bytes.Length > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(bytes.Length, Builtin.MaxMessageSize);
+            }
+            var r = new Builtin.WireReader(bytes);
+            while (r.Next(out var field)) {
+                switch (field) {
+                    case 1: {
+                        msg.Value = unchecked((UInt32)r.Varint());
+                        break;
+                    }
+                    default:
+                        // Fields of newer schema versions.
+                        r.Skip();
+                        break;
+                }
+            }
+            if (r.Failed) {
+                return false;
+            }
+            return true;
+        }
+
+        // Returns null if the message doesn't fit in bytes, throws
+        // MessageTooLargeException if it's over MaxMessageSize.
+        public int? serializeInto(byte[] bytes) {
+            var size = serializedSize();
+            if (size > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(size, Builtin.MaxMessageSize);
+            }
+            var w = new Builtin.WireWriter(bytes);
+            encodeFields(w);
+            return w.Overflow ? null : w.Position;
+        }
+
+        public void encodeFields(Builtin.WireWriter w) {
+            if (unchecked((Int64)Value) != 0) {
+                w.Varint(1, unchecked((Int64)Value));
+            }
+        }
+
+        public int serializedSize() {
+            var size = 0;
+            if (unchecked((Int64)Value) != 0) {
+                size += Builtin.VarintFieldSize(1, unchecked((Int64)Value));
+            }
+            return size;
+        }
+
+        // The text format, `name: "a" point { x: 1 }`, for logs and test data.
+        public override string ToString() {
+            return ToText();
+        }
+
+        public string ToText(bool pretty = false) {
+            return Builtin.TextFormatWriter.Write(this, pretty);
+        }
+
+        // Throws FormatException for malformed text.
+        public static Compact ParseText(string text) {
+            return Builtin.TextFormatReader.Parse<Compact>(text);
+        }
+
+        public void writeText(Builtin.TextFormatWriter w) {
+            if (unchecked((Int64)Value) != 0) {
+                w.Int("value", Value);
+            }
+        }
+
+        public void mergeTextField(string name, Builtin.TextFormatReader r) {
+            switch (name) {
+                case "value": {
+                    Value = (UInt32)r.Unsigned(UInt32.MaxValue);
+                    break;
+                }
+                default:
+                    throw r.UnknownField(name);
+            }
+        }
+    }
+}
+namespace Proto {
+    [StructLayout(LayoutKind.Sequential)]
+    public struct Bounded : IProtoMessage, ITextMessage
+    {
+        public Int32 Small {get; set;}
+        public Compact Compact {get; set;}
+        public Int64? Big {get; set;}
+        // Upper bound of the encoded length, for buffers sized up front.
+        public const int MaxEncodedLen = 30;
+
+        // The body runs to the end of the frame. Returns null for malformed ones,
+        // throws MessageTooLargeException for ones over MaxMessageSize.
+        public static (Bounded?, int) tryDeserializeBody(byte[] bytes) {
+            var msg = new Bounded();
+            if (!tryMergeBody(ref msg, bytes)) {
+                return (null, 0);
+            }
+            return (msg, bytes.Length);
+        }
+
+        // Decode the body on top of msg, like a repeated occurrence of a message
+        // field: set fields replace, repeated ones and messages merge. Returns
+        // false for malformed bodies, leaving msg partly merged.
+        public static bool tryMergeBody(ref Bounded msg, byte[] bytes) {
+            if (bytes.Length > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(bytes.Length, Builtin.MaxMessageSize);
+            }
+            var r = new Builtin.WireReader(bytes);
+            while (r.Next(out var field)) {
+                switch (field) {
+                    case 1: {
+                        msg.Small = unchecked((Int32)r.Varint());
+                        break;
+                    }
+                    case 2: {
+                        // Occurring again merges into what's there.
+                        var m = msg.Compact;
+                        if (!Compact.tryMergeBody(ref m, r.Bytes())) {
+                            return false;
+                        }
+                        msg.Compact = m;
+                        break;
+                    }
+                    case 3: {
+                        msg.Big = unchecked((Int64)r.Varint());
+                        break;
+                    }
+                    default:
+                        // Fields of newer schema versions.
+                        r.Skip();
+                        break;
+                }
+            }
+            if (r.Failed) {
+                return false;
+            }
+            return true;
+        }
+
+        // Returns null if the message doesn't fit in bytes, throws
+        // MessageTooLargeException if it's over MaxMessageSize.
+        public int? serializeInto(byte[] bytes) {
+            var size = serializedSize();
+            if (size > Builtin.MaxMessageSize) {
+                throw new Builtin.MessageTooLargeException(size, Builtin.MaxMessageSize);
+            }
+            var w = new Builtin.WireWriter(bytes);
+            encodeFields(w);
+            return w.Overflow ? null : w.Position;
+        }
+
+        public void encodeFields(Builtin.WireWriter w) {
+            if (unchecked((Int64)Small) != 0) {
+                w.Varint(1, unchecked((Int64)Small));
+            }
+            if (Compact.serializedSize() != 0) {
+                w.Message(2, Compact);
+            }
+            if (Big != null) {
+                w.Varint(3, unchecked((Int64)Big.Value));
+            }
+        }
+
+        public int serializedSize() {
+            var size = 0;
+            if (unchecked((Int64)Small) != 0) {
+                size += Builtin.VarintFieldSize(1, unchecked((Int64)Small));
+            }
+            if (Compact.serializedSize() != 0) {
+                size += Builtin.LenFieldSize(2, Compact.serializedSize());
+            }
+            if (Big != null) {
+                size += Builtin.VarintFieldSize(3, unchecked((Int64)Big.Value));
+            }
+            return size;
+        }
+
+        // The text format, `name: "a" point { x: 1 }`, for logs and test data.
+        public override string ToString() {
+            return ToText();
+        }
+
+        public string ToText(bool pretty = false) {
+            return Builtin.TextFormatWriter.Write(this, pretty);
+        }
+
+        // Throws FormatException for malformed text.
+        public static Bounded ParseText(string text) {
+            return Builtin.TextFormatReader.Parse<Bounded>(text);
+        }
+
+        public void writeText(Builtin.TextFormatWriter w) {
+            if (unchecked((Int64)Small) != 0) {
+                w.Int("small", Small);
+            }
+            if (Compact.serializedSize() != 0) {
+                w.Message("compact", Compact);
+            }
+            if (Big != null) {
+                w.Int("big", Big.Value);
+            }
+        }
+
+        public void mergeTextField(string name, Builtin.TextFormatReader r) {
+            switch (name) {
+                case "small": {
+                    Small = (Int32)r.Signed(Int32.MinValue, Int32.MaxValue);
+                    break;
+                }
+                case "compact": {
+                    // Occurring again merges into what's there.
+                    Compact = r.Message(Compact);
+                    break;
+                }
+                case "big": {
+                    Big = r.Signed(Int64.MinValue, Int64.MaxValue);
+                    break;
+                }
+                default:
+                    throw r.UnknownField(name);
+            }
+        }
+    }
+}
@@ -0,0 +1,1047 @@
+pub use protogen_runtime::async_client::RpcClient;
+pub use protogen_runtime::rpc::*;
+pub use protogen_runtime::AsyncTransport;
+use protogen_runtime::wire::Varint as _;
+use serde::{Deserialize, Serialize};
+
+const _: () = protogen_runtime::VERSION_0_2;
+
+/// Largest frame clients and dispatchers send or accept.
+pub const MAX_FRAME_SIZE: usize = FRAME_BUFFER_SIZE;
+
+/// Largest encoded message, leaving room for the header and status of a reply.
+pub const MAX_MESSAGE_SIZE: usize = MAX_FRAME_SIZE - REPLY_OVERHEAD;
+
+/// Descriptors of everything in this file, to look types up by name.
+pub static FILE_DESCRIPTOR: protogen_runtime::descriptor::FileDescriptor = protogen_runtime::descriptor::FileDescriptor {
+    messages: protogen_runtime::descriptor::Cow::Borrowed(
+        &[NIL_DESCRIPTOR, INPUT_EVENT_DESCRIPTOR, LOCATION_DESCRIPTOR],
+    ),
+    enums: protogen_runtime::descriptor::Cow::Borrowed(&[KEY_CODE_DESCRIPTOR]),
+    services: protogen_runtime::descriptor::Cow::Borrowed(
+        &[GAME_BACKEND_DESCRIPTOR, GAME_FRONTEND_DESCRIPTOR],
+    ),
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct Nil {
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for Nil {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        e.unknown(&self.unknown_fields)
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        self.unknown_fields.push(field, value);
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        self.unknown_fields.len()
+    }
+}
+
+impl protogen_runtime::text::TextMessage for Nil {
+    fn write_fields(
+        &self,
+        _w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        Err(p.unknown_field(name))
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for Nil {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for Nil {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const NIL_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("nil"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(&[]),
+};
+
+impl protogen_runtime::descriptor::Describe for Nil {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "nil";
+}
+
+/// Borrowed view of an encoded [`Nil`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NilRef<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> NilRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            field?;
+        }
+        Ok(Self { buf })
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> Nil {
+        Nil {
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(self.buf, &[]),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for NilRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct InputEvent {
+    pub keycode: KeyCode,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for InputEvent {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.keycode.to_varint() != 0 {
+            e.varint(1, self.keycode)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        if field == 1 {
+            self.keycode = value.varint(field)?;
+        } else {
+            self.unknown_fields.push(field, value);
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.keycode.to_varint() != 0 {
+            size
+                += protogen_runtime::wire::varint_field_size(
+                    1,
+                    self.keycode.to_varint(),
+                );
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for InputEvent {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.keycode.to_varint() != 0 {
+            w.enum_value("keycode", self.keycode)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "keycode" => self.keycode = p.enum_value()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for InputEvent {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const INPUT_EVENT_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("InputEvent"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("keycode"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Enum(
+                    protogen_runtime::descriptor::Cow::Borrowed("KeyCode"),
+                ),
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for InputEvent {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "InputEvent";
+}
+
+/// Borrowed view of an encoded [`InputEvent`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputEventRef<'a> {
+    buf: &'a [u8],
+    pub keycode: KeyCode,
+}
+
+impl<'a> InputEventRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            if field == 1 {
+                view.keycode = value.varint(field)?;
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> InputEvent {
+        InputEvent {
+            keycode: self.keycode,
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(self.buf, &[1]),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for InputEventRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct Location {
+    pub x: i32,
+    pub y: i32,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for Location {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.x.to_varint() != 0 {
+            e.varint(1, self.x)?;
+        }
+        if self.y.to_varint() != 0 {
+            e.varint(2, self.y)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        match field {
+            1 => {
+                self.x = value.varint(field)?;
+            }
+            2 => {
+                self.y = value.varint(field)?;
+            }
+            _ => self.unknown_fields.push(field, value),
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.x.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.x.to_varint());
+        }
+        if self.y.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(2, self.y.to_varint());
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for Location {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.x.to_varint() != 0 {
+            w.int("x", self.x)?;
+        }
+        if self.y.to_varint() != 0 {
+            w.int("y", self.y)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "x" => self.x = p.int()?,
+            "y" => self.y = p.int()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for Location {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for Location {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const LOCATION_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Location"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("x"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Int32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("y"),
+                number: 2,
+                ty: protogen_runtime::descriptor::FieldType::Int32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for Location {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "Location";
+}
+
+/// Borrowed view of an encoded [`Location`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocationRef<'a> {
+    buf: &'a [u8],
+    pub x: i32,
+    pub y: i32,
+}
+
+impl<'a> LocationRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            match field {
+                1 => {
+                    view.x = value.varint(field)?;
+                }
+                2 => {
+                    view.y = value.varint(field)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> Location {
+        Location {
+            x: self.x,
+            y: self.y,
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(
+                self.buf,
+                &[1, 2],
+            ),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for LocationRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum KeyCode {
+    #[default]
+    Spacebar = 0,
+    Enter = 1,
+}
+
+impl protogen_runtime::wire::Varint for KeyCode {
+    fn to_varint(self) -> u64 {
+        self as i32 as i64 as u64
+    }
+    fn from_varint(value: u64) -> Self {
+        match value as i32 {
+            0 => Self::Spacebar,
+            1 => Self::Enter,
+            _ => Self::default(),
+        }
+    }
+}
+
+impl protogen_runtime::text::TextEnum for KeyCode {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Spacebar => "Spacebar",
+            Self::Enter => "Enter",
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Spacebar" => Some(Self::Spacebar),
+            "Enter" => Some(Self::Enter),
+            _ => None,
+        }
+    }
+}
+
+const KEY_CODE_DESCRIPTOR: protogen_runtime::descriptor::EnumDescriptor = protogen_runtime::descriptor::EnumDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("KeyCode"),
+    values: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::EnumValueDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("Spacebar"),
+                number: 0,
+            },
+            protogen_runtime::descriptor::EnumValueDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("Enter"),
+                number: 1,
+            },
+        ],
+    ),
+};
+
+/// A decoded request or reply of the GameBackend service.
+#[derive(Debug)]
+pub enum GameBackendMessage {
+    NotifyInputEventArg(InputEvent),
+    NotifyInputEventRet(Result<Nil, RpcError>),
+}
+
+pub const GAME_BACKEND_NOTIFY_INPUT_EVENT_ARG_ID: MessageId = 1288978408;
+pub const GAME_BACKEND_NOTIFY_INPUT_EVENT_RET_ID: MessageId = 1288978409;
+const GAME_BACKEND_DESCRIPTOR: protogen_runtime::descriptor::ServiceDescriptor = protogen_runtime::descriptor::ServiceDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("GameBackend"),
+    rpcs: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("NotifyInputEvent"),
+                arg_id: GAME_BACKEND_NOTIFY_INPUT_EVENT_ARG_ID,
+                ret_id: GAME_BACKEND_NOTIFY_INPUT_EVENT_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("InputEvent"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("nil"),
+            },
+        ],
+    ),
+};
+
+impl GameBackendMessage {
+    pub fn try_deserialize_msg(buf: &[u8]) -> Option<Self> {
+        Self::try_deserialize_frame(buf).map(|(_, msg)| msg)
+    }
+    /// Decode a frame, keeping the header so replies can be matched up by
+    /// request id. Returns `None` for message ids of other services.
+    pub fn try_deserialize_frame(buf: &[u8]) -> Option<(RpcHeader, Self)> {
+        let header = RpcHeader::try_deserialize(buf)?;
+        let buf = &buf[RpcHeader::SIZE..];
+        let msg = match header.msg_id {
+            GAME_BACKEND_NOTIFY_INPUT_EVENT_ARG_ID => {
+                Self::NotifyInputEventArg(InputEvent::try_deserialize_body(buf).ok()?)
+            }
+            GAME_BACKEND_NOTIFY_INPUT_EVENT_RET_ID => {
+                Self::NotifyInputEventRet(try_deserialize_reply(buf))
+            }
+            _ => return None,
+        };
+        Some((header, msg))
+    }
+}
+
+#[test]
+fn test_round_trip_game_backend_notify_input_event() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: GAME_BACKEND_NOTIFY_INPUT_EVENT_ARG_ID,
+        request_id: 0,
+    };
+    let arg = InputEvent::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match GameBackendMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        GameBackendMessage::NotifyInputEventArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: GAME_BACKEND_NOTIFY_INPUT_EVENT_RET_ID,
+        request_id: 3,
+    };
+    let ret = Nil::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match GameBackendMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, GameBackendMessage::NotifyInputEventRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<Nil>(&header, Err(&error), buf).unwrap();
+    match GameBackendMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        GameBackendMessage::NotifyInputEventRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+/// Server side of the GameBackend service.
+///
+/// Methods may be implemented with `async fn`, as long as the returned
+/// futures are `Send` so that requests can run on their own tasks.
+pub trait GameBackend: Send + Sync + 'static {
+    fn notify_input_event(
+        &self,
+        req: InputEvent,
+    ) -> impl std::future::Future<Output = Result<Nil, RpcError>> + Send;
+}
+
+/// Decodes request frames and routes them to a [`GameBackend`] implementation.
+pub struct GameBackendDispatcher<S: GameBackend> {
+    service: std::sync::Arc<S>,
+}
+
+impl<S: GameBackend> Clone for GameBackendDispatcher<S> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+        }
+    }
+}
+
+impl<S: GameBackend> GameBackendDispatcher<S> {
+    pub fn new(service: S) -> Self {
+        Self::from_arc(std::sync::Arc::new(service))
+    }
+    pub fn from_arc(service: std::sync::Arc<S>) -> Self {
+        Self { service }
+    }
+    pub fn service(&self) -> &std::sync::Arc<S> {
+        &self.service
+    }
+    /// Decode a request frame, call the matching service method and serialize
+    /// its reply, or the error it failed with, into `reply`.
+    ///
+    /// Returns the length of the reply frame, zero for one-way requests. Only
+    /// frames that can't be answered at all are an error.
+    pub async fn dispatch(
+        &self,
+        frame: &[u8],
+        reply: &mut [u8],
+    ) -> Result<usize, RpcError> {
+        let header = RpcHeader::try_deserialize(frame)
+            .ok_or_else(|| RpcError::new(
+                RpcStatus::InvalidArgument,
+                "Truncated rpc header",
+            ))?;
+        let body = &frame[RpcHeader::SIZE..];
+        match header.msg_id {
+            GAME_BACKEND_NOTIFY_INPUT_EVENT_ARG_ID => {
+                let ret = match InputEvent::try_deserialize_body(body) {
+                    Ok(req) => self.service.notify_input_event(req).await,
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed InputEvent: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        GAME_BACKEND_NOTIFY_INPUT_EVENT_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        GAME_BACKEND_NOTIFY_INPUT_EVENT_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            msg_id => {
+                serialize_error_reply(
+                    msg_id | 1,
+                    header.request_id,
+                    &RpcError::new(
+                        RpcStatus::Unimplemented,
+                        format!("Unknown message id {}", msg_id),
+                    ),
+                    reply,
+                )
+            }
+        }
+    }
+    /// Answer requests arriving on `transport` until receiving fails.
+    ///
+    /// Every request runs on its own task, at most `max_tasks` at a time. Once
+    /// that many are running no more frames are read until one finishes.
+    ///
+    /// Panics if `max_tasks` is 0, no request could ever be answered.
+    pub async fn serve<T: AsyncTransport>(
+        &self,
+        transport: std::sync::Arc<T>,
+        max_tasks: usize,
+    ) -> std::io::Result<()> {
+        assert!(max_tasks > 0, "serve needs max_tasks of at least 1");
+        let permits = std::sync::Arc::new(
+            protogen_runtime::tokio::sync::Semaphore::new(max_tasks),
+        );
+        loop {
+            let permit = permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Semaphore is never closed");
+            let mut frame = vec![0; MAX_FRAME_SIZE];
+            let (len, peer) = transport.recv_from(&mut frame).await?;
+            frame.truncate(len);
+            let dispatcher = self.clone();
+            let transport = transport.clone();
+            protogen_runtime::tokio::spawn(async move {
+                let mut reply = vec![0; MAX_FRAME_SIZE];
+                match dispatcher.dispatch(&frame, &mut reply).await {
+                    Ok(0) => {}
+                    Ok(len) => drop(transport.send_to(&reply[..len], &peer).await),
+                    Err(_) => {}
+                }
+                drop(permit);
+            });
+        }
+    }
+}
+
+/// Client side of the GameBackend service.
+pub struct GameBackendClient<T: AsyncTransport> {
+    client: RpcClient<T>,
+}
+
+impl<T: AsyncTransport> GameBackendClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            client: RpcClient::with_frame_size(transport, MAX_FRAME_SIZE),
+        }
+    }
+    /// How long to wait for each reply, [`DEFAULT_RPC_TIMEOUT`] by default.
+    pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+        self.client.set_timeout(timeout)
+    }
+    pub fn transport(&self) -> &T {
+        self.client.transport()
+    }
+    pub fn into_inner(self) -> T {
+        self.client.into_inner()
+    }
+    pub async fn notify_input_event(&self, req: &InputEvent) -> Result<Nil, RpcError> {
+        self.client
+            .call(
+                GAME_BACKEND_NOTIFY_INPUT_EVENT_ARG_ID,
+                GAME_BACKEND_NOTIFY_INPUT_EVENT_RET_ID,
+                req,
+            )
+            .await
+    }
+    /// Fire-and-forget variant of [`Self::notify_input_event`], doesn't wait for a reply.
+    pub async fn notify_input_event_oneway(
+        &self,
+        req: &InputEvent,
+    ) -> Result<(), RpcError> {
+        self.client.send_oneway(GAME_BACKEND_NOTIFY_INPUT_EVENT_ARG_ID, req).await
+    }
+}
+
+#[test]
+fn test_client_game_backend() {
+    use protogen_runtime::async_transport::AsyncChannelTransport;
+    struct DefaultService;
+    impl GameBackend for DefaultService {
+        async fn notify_input_event(&self, _req: InputEvent) -> Result<Nil, RpcError> {
+            Ok(Nil::default())
+        }
+    }
+    let runtime = protogen_runtime::tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime
+        .block_on(async {
+            let (client_transport, server_transport) = AsyncChannelTransport::pair();
+            let dispatcher = GameBackendDispatcher::new(DefaultService);
+            let server = protogen_runtime::tokio::spawn(async move {
+                dispatcher.serve(std::sync::Arc::new(server_transport), 4).await
+            });
+            let client = GameBackendClient::new(client_transport);
+            let ret = client.notify_input_event(&InputEvent::default()).await.unwrap();
+            assert_eq!(ret, Nil::default());
+            client.notify_input_event_oneway(&InputEvent::default()).await.unwrap();
+            server.abort();
+        });
+}
+
+/// A decoded request or reply of the GameFrontend service.
+#[derive(Debug)]
+pub enum GameFrontendMessage {
+    MoveScreenArg(Location),
+    MoveScreenRet(Result<Nil, RpcError>),
+}
+
+pub const GAME_FRONTEND_MOVE_SCREEN_ARG_ID: MessageId = 650730356;
+pub const GAME_FRONTEND_MOVE_SCREEN_RET_ID: MessageId = 650730357;
+const GAME_FRONTEND_DESCRIPTOR: protogen_runtime::descriptor::ServiceDescriptor = protogen_runtime::descriptor::ServiceDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("GameFrontend"),
+    rpcs: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("MoveScreen"),
+                arg_id: GAME_FRONTEND_MOVE_SCREEN_ARG_ID,
+                ret_id: GAME_FRONTEND_MOVE_SCREEN_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("Location"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("nil"),
+            },
+        ],
+    ),
+};
+
+impl GameFrontendMessage {
+    pub fn try_deserialize_msg(buf: &[u8]) -> Option<Self> {
+        Self::try_deserialize_frame(buf).map(|(_, msg)| msg)
+    }
+    /// Decode a frame, keeping the header so replies can be matched up by
+    /// request id. Returns `None` for message ids of other services.
+    pub fn try_deserialize_frame(buf: &[u8]) -> Option<(RpcHeader, Self)> {
+        let header = RpcHeader::try_deserialize(buf)?;
+        let buf = &buf[RpcHeader::SIZE..];
+        let msg = match header.msg_id {
+            GAME_FRONTEND_MOVE_SCREEN_ARG_ID => {
+                Self::MoveScreenArg(Location::try_deserialize_body(buf).ok()?)
+            }
+            GAME_FRONTEND_MOVE_SCREEN_RET_ID => {
+                Self::MoveScreenRet(try_deserialize_reply(buf))
+            }
+            _ => return None,
+        };
+        Some((header, msg))
+    }
+}
+
+#[test]
+fn test_round_trip_game_frontend_move_screen() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: GAME_FRONTEND_MOVE_SCREEN_ARG_ID,
+        request_id: 0,
+    };
+    let arg = Location::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match GameFrontendMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        GameFrontendMessage::MoveScreenArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: GAME_FRONTEND_MOVE_SCREEN_RET_ID,
+        request_id: 3,
+    };
+    let ret = Nil::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match GameFrontendMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, GameFrontendMessage::MoveScreenRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<Nil>(&header, Err(&error), buf).unwrap();
+    match GameFrontendMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        GameFrontendMessage::MoveScreenRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+/// Server side of the GameFrontend service.
+///
+/// Methods may be implemented with `async fn`, as long as the returned
+/// futures are `Send` so that requests can run on their own tasks.
+pub trait GameFrontend: Send + Sync + 'static {
+    fn move_screen(
+        &self,
+        req: Location,
+    ) -> impl std::future::Future<Output = Result<Nil, RpcError>> + Send;
+}
+
+/// Decodes request frames and routes them to a [`GameFrontend`] implementation.
+pub struct GameFrontendDispatcher<S: GameFrontend> {
+    service: std::sync::Arc<S>,
+}
+
+impl<S: GameFrontend> Clone for GameFrontendDispatcher<S> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+        }
+    }
+}
+
+impl<S: GameFrontend> GameFrontendDispatcher<S> {
+    pub fn new(service: S) -> Self {
+        Self::from_arc(std::sync::Arc::new(service))
+    }
+    pub fn from_arc(service: std::sync::Arc<S>) -> Self {
+        Self { service }
+    }
+    pub fn service(&self) -> &std::sync::Arc<S> {
+        &self.service
+    }
+    /// Decode a request frame, call the matching service method and serialize
+    /// its reply, or the error it failed with, into `reply`.
+    ///
+    /// Returns the length of the reply frame, zero for one-way requests. Only
+    /// frames that can't be answered at all are an error.
+    pub async fn dispatch(
+        &self,
+        frame: &[u8],
+        reply: &mut [u8],
+    ) -> Result<usize, RpcError> {
+        let header = RpcHeader::try_deserialize(frame)
+            .ok_or_else(|| RpcError::new(
+                RpcStatus::InvalidArgument,
+                "Truncated rpc header",
+            ))?;
+        let body = &frame[RpcHeader::SIZE..];
+        match header.msg_id {
+            GAME_FRONTEND_MOVE_SCREEN_ARG_ID => {
+                let ret = match Location::try_deserialize_body(body) {
+                    Ok(req) => self.service.move_screen(req).await,
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed Location: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        GAME_FRONTEND_MOVE_SCREEN_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        GAME_FRONTEND_MOVE_SCREEN_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            msg_id => {
+                serialize_error_reply(
+                    msg_id | 1,
+                    header.request_id,
+                    &RpcError::new(
+                        RpcStatus::Unimplemented,
+                        format!("Unknown message id {}", msg_id),
+                    ),
+                    reply,
+                )
+            }
+        }
+    }
+    /// Answer requests arriving on `transport` until receiving fails.
+    ///
+    /// Every request runs on its own task, at most `max_tasks` at a time. Once
+    /// that many are running no more frames are read until one finishes.
+    ///
+    /// Panics if `max_tasks` is 0, no request could ever be answered.
+    pub async fn serve<T: AsyncTransport>(
+        &self,
+        transport: std::sync::Arc<T>,
+        max_tasks: usize,
+    ) -> std::io::Result<()> {
+        assert!(max_tasks > 0, "serve needs max_tasks of at least 1");
+        let permits = std::sync::Arc::new(
+            protogen_runtime::tokio::sync::Semaphore::new(max_tasks),
+        );
+        loop {
+            let permit = permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Semaphore is never closed");
+            let mut frame = vec![0; MAX_FRAME_SIZE];
+            let (len, peer) = transport.recv_from(&mut frame).await?;
+            frame.truncate(len);
+            let dispatcher = self.clone();
+            let transport = transport.clone();
+            protogen_runtime::tokio::spawn(async move {
+                let mut reply = vec![0; MAX_FRAME_SIZE];
+                match dispatcher.dispatch(&frame, &mut reply).await {
+                    Ok(0) => {}
+                    Ok(len) => drop(transport.send_to(&reply[..len], &peer).await),
+                    Err(_) => {}
+                }
+                drop(permit);
+            });
+        }
+    }
+}
+
+/// Client side of the GameFrontend service.
+pub struct GameFrontendClient<T: AsyncTransport> {
+    client: RpcClient<T>,
+}
+
+impl<T: AsyncTransport> GameFrontendClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            client: RpcClient::with_frame_size(transport, MAX_FRAME_SIZE),
+        }
+    }
+    /// How long to wait for each reply, [`DEFAULT_RPC_TIMEOUT`] by default.
+    pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+        self.client.set_timeout(timeout)
+    }
+    pub fn transport(&self) -> &T {
+        self.client.transport()
+    }
+    pub fn into_inner(self) -> T {
+        self.client.into_inner()
+    }
+    pub async fn move_screen(&self, req: &Location) -> Result<Nil, RpcError> {
+        self.client
+            .call(
+                GAME_FRONTEND_MOVE_SCREEN_ARG_ID,
+                GAME_FRONTEND_MOVE_SCREEN_RET_ID,
+                req,
+            )
+            .await
+    }
+    /// Fire-and-forget variant of [`Self::move_screen`], doesn't wait for a reply.
+    pub async fn move_screen_oneway(&self, req: &Location) -> Result<(), RpcError> {
+        self.client.send_oneway(GAME_FRONTEND_MOVE_SCREEN_ARG_ID, req).await
+    }
+}
+
+#[test]
+fn test_client_game_frontend() {
+    use protogen_runtime::async_transport::AsyncChannelTransport;
+    struct DefaultService;
+    impl GameFrontend for DefaultService {
+        async fn move_screen(&self, _req: Location) -> Result<Nil, RpcError> {
+            Ok(Nil::default())
+        }
+    }
+    let runtime = protogen_runtime::tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime
+        .block_on(async {
+            let (client_transport, server_transport) = AsyncChannelTransport::pair();
+            let dispatcher = GameFrontendDispatcher::new(DefaultService);
+            let server = protogen_runtime::tokio::spawn(async move {
+                dispatcher.serve(std::sync::Arc::new(server_transport), 4).await
+            });
+            let client = GameFrontendClient::new(client_transport);
+            let ret = client.move_screen(&Location::default()).await.unwrap();
+            assert_eq!(ret, Nil::default());
+            client.move_screen_oneway(&Location::default()).await.unwrap();
+            server.abort();
+        });
+}
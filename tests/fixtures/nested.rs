@@ -0,0 +1,981 @@
+pub use protogen_runtime::client::RpcClient;
+pub use protogen_runtime::rpc::*;
+pub use protogen_runtime::Transport;
+use protogen_runtime::wire::Varint as _;
+use serde::{Deserialize, Serialize};
+
+const _: () = protogen_runtime::VERSION_0_2;
+
+/// Largest frame clients and dispatchers send or accept.
+pub const MAX_FRAME_SIZE: usize = FRAME_BUFFER_SIZE;
+
+/// Largest encoded message, leaving room for the header and status of a reply.
+pub const MAX_MESSAGE_SIZE: usize = MAX_FRAME_SIZE - REPLY_OVERHEAD;
+
+/// Descriptors of everything in this file, to look types up by name.
+pub static FILE_DESCRIPTOR: protogen_runtime::descriptor::FileDescriptor = protogen_runtime::descriptor::FileDescriptor {
+    messages: protogen_runtime::descriptor::Cow::Borrowed(
+        &[OUTER_DESCRIPTOR, OUTER_INNER_DESCRIPTOR, EMPTY_REPLY_DESCRIPTOR],
+    ),
+    enums: protogen_runtime::descriptor::Cow::Borrowed(&[OUTER_KIND_DESCRIPTOR]),
+    services: protogen_runtime::descriptor::Cow::Borrowed(&[NESTED_SERVICE_DESCRIPTOR]),
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct OuterInner {
+    pub kind: OuterKind,
+    pub tags: Vec<String>,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for OuterInner {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.kind.to_varint() != 0 {
+            e.varint(1, self.kind)?;
+        }
+        for v in &self.tags {
+            e.string(2, v)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        match field {
+            1 => {
+                self.kind = value.varint(field)?;
+            }
+            2 => {
+                self.tags.push(value.str(field)?.into());
+            }
+            _ => self.unknown_fields.push(field, value),
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.kind.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.kind.to_varint());
+        }
+        for v in &self.tags {
+            size += protogen_runtime::wire::len_field_size(2, v.len());
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for OuterInner {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.kind.to_varint() != 0 {
+            w.enum_value("kind", self.kind)?;
+        }
+        for v in &self.tags {
+            w.string("tags", v)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "kind" => self.kind = p.enum_value()?,
+            "tags" => {
+                p.repeated(|p| {
+                    self.tags.push(p.string()?);
+                    Ok(())
+                })?
+            }
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for OuterInner {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for OuterInner {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const OUTER_INNER_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Outer.Inner"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("kind"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Enum(
+                    protogen_runtime::descriptor::Cow::Borrowed("Outer.Kind"),
+                ),
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("tags"),
+                number: 2,
+                ty: protogen_runtime::descriptor::FieldType::String,
+                label: protogen_runtime::descriptor::Label::Repeated,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for OuterInner {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "Outer.Inner";
+}
+
+/// Borrowed view of an encoded [`OuterInner`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OuterInnerRef<'a> {
+    buf: &'a [u8],
+    pub kind: OuterKind,
+}
+
+impl<'a> OuterInnerRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            match field {
+                1 => {
+                    view.kind = value.varint(field)?;
+                }
+                2 => {
+                    value.str(field)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    pub fn tags(&self) -> protogen_runtime::view::Repeated<'a, &'a str> {
+        protogen_runtime::view::Repeated::new(self.buf, 2)
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> OuterInner {
+        OuterInner {
+            kind: self.kind,
+            tags: self.tags().map(Into::into).collect(),
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(
+                self.buf,
+                &[1, 2],
+            ),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for OuterInnerRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OuterKind {
+    #[default]
+    #[serde(rename = "FIRST_KIND")]
+    FirstKind = 0,
+    #[serde(rename = "secondKind")]
+    SecondKind = 1,
+}
+
+impl protogen_runtime::wire::Varint for OuterKind {
+    fn to_varint(self) -> u64 {
+        self as i32 as i64 as u64
+    }
+    fn from_varint(value: u64) -> Self {
+        match value as i32 {
+            0 => Self::FirstKind,
+            1 => Self::SecondKind,
+            _ => Self::default(),
+        }
+    }
+}
+
+impl protogen_runtime::text::TextEnum for OuterKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::FirstKind => "FIRST_KIND",
+            Self::SecondKind => "secondKind",
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "FIRST_KIND" => Some(Self::FirstKind),
+            "secondKind" => Some(Self::SecondKind),
+            _ => None,
+        }
+    }
+}
+
+const OUTER_KIND_DESCRIPTOR: protogen_runtime::descriptor::EnumDescriptor = protogen_runtime::descriptor::EnumDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Outer.Kind"),
+    values: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::EnumValueDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("FIRST_KIND"),
+                number: 0,
+            },
+            protogen_runtime::descriptor::EnumValueDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("secondKind"),
+                number: 1,
+            },
+        ],
+    ),
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct Outer {
+    pub inners: Vec<OuterInner>,
+    #[serde(rename = "maybeCount")]
+    pub maybe_count: Option<i64>,
+    pub kind: OuterKind,
+    #[serde(rename = "PlainID")]
+    pub plain_id: u32,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for Outer {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        for v in &self.inners {
+            e.message(1, v)?;
+        }
+        if let Some(v) = self.maybe_count {
+            e.varint(2, v)?;
+        }
+        if self.kind.to_varint() != 0 {
+            e.varint(3, self.kind)?;
+        }
+        if self.plain_id.to_varint() != 0 {
+            e.varint(4, self.plain_id)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        match field {
+            1 => {
+                self.inners.push(OuterInner::try_deserialize_body(value.bytes(field)?)?);
+            }
+            2 => {
+                self.maybe_count = Some(value.varint(field)?);
+            }
+            3 => {
+                self.kind = value.varint(field)?;
+            }
+            4 => {
+                self.plain_id = value.varint(field)?;
+            }
+            _ => self.unknown_fields.push(field, value),
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        for v in &self.inners {
+            size += protogen_runtime::wire::len_field_size(1, v.serialized_size());
+        }
+        if let Some(v) = self.maybe_count {
+            size += protogen_runtime::wire::varint_field_size(2, v.to_varint());
+        }
+        if self.kind.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(3, self.kind.to_varint());
+        }
+        if self.plain_id.to_varint() != 0 {
+            size
+                += protogen_runtime::wire::varint_field_size(
+                    4,
+                    self.plain_id.to_varint(),
+                );
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for Outer {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        for v in &self.inners {
+            w.message("inners", v)?;
+        }
+        if let Some(v) = &self.maybe_count {
+            w.int("maybeCount", *v)?;
+        }
+        if self.kind.to_varint() != 0 {
+            w.enum_value("kind", self.kind)?;
+        }
+        if self.plain_id.to_varint() != 0 {
+            w.int("PlainID", self.plain_id)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "inners" => {
+                p.repeated(|p| {
+                    self.inners.push(p.message()?);
+                    Ok(())
+                })?
+            }
+            "maybeCount" => self.maybe_count = Some(p.int()?),
+            "kind" => self.kind = p.enum_value()?,
+            "PlainID" => self.plain_id = p.int()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for Outer {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for Outer {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const OUTER_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Outer"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("inners"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Message(
+                    protogen_runtime::descriptor::Cow::Borrowed("Outer.Inner"),
+                ),
+                label: protogen_runtime::descriptor::Label::Repeated,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("maybeCount"),
+                number: 2,
+                ty: protogen_runtime::descriptor::FieldType::Int64,
+                label: protogen_runtime::descriptor::Label::Optional,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("kind"),
+                number: 3,
+                ty: protogen_runtime::descriptor::FieldType::Enum(
+                    protogen_runtime::descriptor::Cow::Borrowed("Outer.Kind"),
+                ),
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("PlainID"),
+                number: 4,
+                ty: protogen_runtime::descriptor::FieldType::Uint32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for Outer {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "Outer";
+}
+
+/// Borrowed view of an encoded [`Outer`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OuterRef<'a> {
+    buf: &'a [u8],
+    pub maybe_count: Option<i64>,
+    pub kind: OuterKind,
+    pub plain_id: u32,
+}
+
+impl<'a> OuterRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            match field {
+                1 => {
+                    OuterInnerRef::new(value.bytes(field)?)?;
+                }
+                2 => {
+                    view.maybe_count = Some(value.varint(field)?);
+                }
+                3 => {
+                    view.kind = value.varint(field)?;
+                }
+                4 => {
+                    view.plain_id = value.varint(field)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    pub fn inners(&self) -> protogen_runtime::view::Repeated<'a, OuterInnerRef<'a>> {
+        protogen_runtime::view::Repeated::new(self.buf, 1)
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> Outer {
+        Outer {
+            inners: self.inners().map(|v| v.to_owned()).collect(),
+            maybe_count: self.maybe_count,
+            kind: self.kind,
+            plain_id: self.plain_id,
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(
+                self.buf,
+                &[1, 2, 3, 4],
+            ),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for OuterRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct EmptyReply {
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for EmptyReply {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        e.unknown(&self.unknown_fields)
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        self.unknown_fields.push(field, value);
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        self.unknown_fields.len()
+    }
+}
+
+impl protogen_runtime::text::TextMessage for EmptyReply {
+    fn write_fields(
+        &self,
+        _w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        Err(p.unknown_field(name))
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for EmptyReply {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for EmptyReply {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const EMPTY_REPLY_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("empty_reply"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(&[]),
+};
+
+impl protogen_runtime::descriptor::Describe for EmptyReply {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "empty_reply";
+}
+
+/// Borrowed view of an encoded [`EmptyReply`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmptyReplyRef<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> EmptyReplyRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            field?;
+        }
+        Ok(Self { buf })
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> EmptyReply {
+        EmptyReply {
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(self.buf, &[]),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for EmptyReplyRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+/// A decoded request or reply of the NestedService service.
+#[derive(Debug)]
+pub enum NestedServiceMessage {
+    DoThingArg(OuterInner),
+    DoThingRet(Result<EmptyReply, RpcError>),
+    GetOuterArg(EmptyReply),
+    GetOuterRet(Result<Outer, RpcError>),
+}
+
+pub const NESTED_SERVICE_DO_THING_ARG_ID: MessageId = 3232989328;
+pub const NESTED_SERVICE_DO_THING_RET_ID: MessageId = 3232989329;
+pub const NESTED_SERVICE_GET_OUTER_ARG_ID: MessageId = 2176386118;
+pub const NESTED_SERVICE_GET_OUTER_RET_ID: MessageId = 2176386119;
+const NESTED_SERVICE_DESCRIPTOR: protogen_runtime::descriptor::ServiceDescriptor = protogen_runtime::descriptor::ServiceDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("NestedService"),
+    rpcs: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("DoThing"),
+                arg_id: NESTED_SERVICE_DO_THING_ARG_ID,
+                ret_id: NESTED_SERVICE_DO_THING_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("Outer.Inner"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("empty_reply"),
+            },
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("get_outer"),
+                arg_id: NESTED_SERVICE_GET_OUTER_ARG_ID,
+                ret_id: NESTED_SERVICE_GET_OUTER_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("empty_reply"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("Outer"),
+            },
+        ],
+    ),
+};
+
+impl NestedServiceMessage {
+    pub fn try_deserialize_msg(buf: &[u8]) -> Option<Self> {
+        Self::try_deserialize_frame(buf).map(|(_, msg)| msg)
+    }
+    /// Decode a frame, keeping the header so replies can be matched up by
+    /// request id. Returns `None` for message ids of other services.
+    pub fn try_deserialize_frame(buf: &[u8]) -> Option<(RpcHeader, Self)> {
+        let header = RpcHeader::try_deserialize(buf)?;
+        let buf = &buf[RpcHeader::SIZE..];
+        let msg = match header.msg_id {
+            NESTED_SERVICE_DO_THING_ARG_ID => {
+                Self::DoThingArg(OuterInner::try_deserialize_body(buf).ok()?)
+            }
+            NESTED_SERVICE_DO_THING_RET_ID => {
+                Self::DoThingRet(try_deserialize_reply(buf))
+            }
+            NESTED_SERVICE_GET_OUTER_ARG_ID => {
+                Self::GetOuterArg(EmptyReply::try_deserialize_body(buf).ok()?)
+            }
+            NESTED_SERVICE_GET_OUTER_RET_ID => {
+                Self::GetOuterRet(try_deserialize_reply(buf))
+            }
+            _ => return None,
+        };
+        Some((header, msg))
+    }
+}
+
+#[test]
+fn test_round_trip_nested_service_do_thing() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: NESTED_SERVICE_DO_THING_ARG_ID,
+        request_id: 0,
+    };
+    let arg = OuterInner::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match NestedServiceMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        NestedServiceMessage::DoThingArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: NESTED_SERVICE_DO_THING_RET_ID,
+        request_id: 3,
+    };
+    let ret = EmptyReply::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match NestedServiceMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, NestedServiceMessage::DoThingRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<EmptyReply>(&header, Err(&error), buf).unwrap();
+    match NestedServiceMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        NestedServiceMessage::DoThingRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_round_trip_nested_service_get_outer() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: NESTED_SERVICE_GET_OUTER_ARG_ID,
+        request_id: 0,
+    };
+    let arg = EmptyReply::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match NestedServiceMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        NestedServiceMessage::GetOuterArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: NESTED_SERVICE_GET_OUTER_RET_ID,
+        request_id: 3,
+    };
+    let ret = Outer::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match NestedServiceMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, NestedServiceMessage::GetOuterRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<Outer>(&header, Err(&error), buf).unwrap();
+    match NestedServiceMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        NestedServiceMessage::GetOuterRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+/// Server side of the NestedService service.
+pub trait NestedService {
+    fn do_thing(&mut self, req: OuterInner) -> Result<EmptyReply, RpcError>;
+    fn get_outer(&mut self, req: EmptyReply) -> Result<Outer, RpcError>;
+}
+
+/// Decodes request frames and routes them to a [`NestedService`] implementation.
+pub struct NestedServiceDispatcher<S: NestedService> {
+    service: S,
+}
+
+impl<S: NestedService> NestedServiceDispatcher<S> {
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+    pub fn service_mut(&mut self) -> &mut S {
+        &mut self.service
+    }
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+    /// Decode a request frame, call the matching service method and serialize
+    /// its reply, or the error it failed with, into `reply`.
+    ///
+    /// Returns the length of the reply frame, zero for one-way requests. Only
+    /// frames that can't be answered at all are an error.
+    pub fn dispatch(
+        &mut self,
+        frame: &[u8],
+        reply: &mut [u8],
+    ) -> Result<usize, RpcError> {
+        let header = RpcHeader::try_deserialize(frame)
+            .ok_or_else(|| RpcError::new(
+                RpcStatus::InvalidArgument,
+                "Truncated rpc header",
+            ))?;
+        let body = &frame[RpcHeader::SIZE..];
+        match header.msg_id {
+            NESTED_SERVICE_DO_THING_ARG_ID => {
+                let ret = match OuterInner::try_deserialize_body(body) {
+                    Ok(req) => self.service.do_thing(req),
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed OuterInner: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        NESTED_SERVICE_DO_THING_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        NESTED_SERVICE_DO_THING_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            NESTED_SERVICE_GET_OUTER_ARG_ID => {
+                let ret = match EmptyReply::try_deserialize_body(body) {
+                    Ok(req) => self.service.get_outer(req),
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed EmptyReply: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        NESTED_SERVICE_GET_OUTER_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        NESTED_SERVICE_GET_OUTER_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            msg_id => {
+                serialize_error_reply(
+                    msg_id | 1,
+                    header.request_id,
+                    &RpcError::new(
+                        RpcStatus::Unimplemented,
+                        format!("Unknown message id {}", msg_id),
+                    ),
+                    reply,
+                )
+            }
+        }
+    }
+    /// Answer requests arriving on `transport` until receiving or sending fails.
+    pub fn serve<T: Transport>(&mut self, transport: &mut T) -> std::io::Result<()> {
+        let mut frame = vec![0; MAX_FRAME_SIZE];
+        let mut reply = vec![0; MAX_FRAME_SIZE];
+        loop {
+            let len = transport.recv(&mut frame, None)?;
+            match self.dispatch(&frame[..len], &mut reply) {
+                Ok(0) => {}
+                Ok(len) => transport.send(&reply[..len])?,
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+/// Client side of the NestedService service.
+pub struct NestedServiceClient<T: Transport> {
+    client: RpcClient<T>,
+}
+
+impl<T: Transport> NestedServiceClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            client: RpcClient::with_frame_size(transport, MAX_FRAME_SIZE),
+        }
+    }
+    /// How long to wait for each reply, [`DEFAULT_RPC_TIMEOUT`] by default.
+    pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+        self.client.set_timeout(timeout)
+    }
+    pub fn transport(&self) -> &T {
+        self.client.transport()
+    }
+    pub fn transport_mut(&mut self) -> &mut T {
+        self.client.transport_mut()
+    }
+    pub fn into_inner(self) -> T {
+        self.client.into_inner()
+    }
+    pub fn do_thing(&mut self, req: &OuterInner) -> Result<EmptyReply, RpcError> {
+        self.client
+            .call(NESTED_SERVICE_DO_THING_ARG_ID, NESTED_SERVICE_DO_THING_RET_ID, req)
+    }
+    /// Fire-and-forget variant of [`Self::do_thing`], doesn't wait for a reply.
+    pub fn do_thing_oneway(&mut self, req: &OuterInner) -> Result<(), RpcError> {
+        self.client.send_oneway(NESTED_SERVICE_DO_THING_ARG_ID, req)
+    }
+    pub fn get_outer(&mut self, req: &EmptyReply) -> Result<Outer, RpcError> {
+        self.client
+            .call(NESTED_SERVICE_GET_OUTER_ARG_ID, NESTED_SERVICE_GET_OUTER_RET_ID, req)
+    }
+}
+
+#[test]
+fn test_dispatch_nested_service() {
+    struct DefaultService;
+    impl NestedService for DefaultService {
+        fn do_thing(&mut self, _req: OuterInner) -> Result<EmptyReply, RpcError> {
+            Ok(EmptyReply::default())
+        }
+        fn get_outer(&mut self, _req: EmptyReply) -> Result<Outer, RpcError> {
+            Ok(Outer::default())
+        }
+    }
+    let mut dispatcher = NestedServiceDispatcher::new(DefaultService);
+    let mut frame = vec![0u8; MAX_FRAME_SIZE];
+    let mut reply = vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: NESTED_SERVICE_DO_THING_ARG_ID,
+        request_id: 7,
+    };
+    let len = serialize_frame_into(&header, &OuterInner::default(), &mut frame).unwrap();
+    let reply_len = dispatcher.dispatch(&frame[..len], &mut reply).unwrap();
+    let reply_header = RpcHeader::try_deserialize(&reply[..reply_len]).unwrap();
+    assert_eq!(reply_header.msg_id, NESTED_SERVICE_DO_THING_RET_ID);
+    assert_eq!(reply_header.request_id, 7);
+    let header = RpcHeader {
+        msg_id: NESTED_SERVICE_GET_OUTER_ARG_ID,
+        request_id: 7,
+    };
+    let len = serialize_frame_into(&header, &EmptyReply::default(), &mut frame).unwrap();
+    let reply_len = dispatcher.dispatch(&frame[..len], &mut reply).unwrap();
+    let reply_header = RpcHeader::try_deserialize(&reply[..reply_len]).unwrap();
+    assert_eq!(reply_header.msg_id, NESTED_SERVICE_GET_OUTER_RET_ID);
+    assert_eq!(reply_header.request_id, 7);
+    let header = RpcHeader {
+        msg_id: u32::MAX - 1,
+        request_id: 8,
+    };
+    header.serialize_into(&mut frame).unwrap();
+    let reply_len = dispatcher.dispatch(&frame[..RpcHeader::SIZE], &mut reply).unwrap();
+    let reply_header = RpcHeader::try_deserialize(&reply).unwrap();
+    assert_eq!(reply_header.msg_id, u32::MAX);
+    let error = RpcError::try_deserialize(&reply[RpcHeader::SIZE..reply_len]).unwrap();
+    assert_eq!(error.status, RpcStatus::Unimplemented);
+}
+
+#[test]
+fn test_client_nested_service() {
+    struct DefaultService;
+    impl NestedService for DefaultService {
+        fn do_thing(&mut self, _req: OuterInner) -> Result<EmptyReply, RpcError> {
+            Ok(EmptyReply::default())
+        }
+        fn get_outer(&mut self, _req: EmptyReply) -> Result<Outer, RpcError> {
+            Ok(Outer::default())
+        }
+    }
+    struct LoopbackTransport {
+        dispatcher: NestedServiceDispatcher<DefaultService>,
+        replies: std::collections::VecDeque<Vec<u8>>,
+    }
+    impl Transport for LoopbackTransport {
+        fn send(&mut self, frame: &[u8]) -> std::io::Result<()> {
+            let mut reply = vec![0; MAX_FRAME_SIZE];
+            let len = self.dispatcher.dispatch(frame, &mut reply).unwrap();
+            if len > 0 {
+                reply.truncate(len);
+                self.replies.push_back(reply);
+            }
+            Ok(())
+        }
+        fn recv(
+            &mut self,
+            buf: &mut [u8],
+            _timeout: Option<std::time::Duration>,
+        ) -> std::io::Result<usize> {
+            let reply = self.replies.pop_front().ok_or(std::io::ErrorKind::TimedOut)?;
+            buf[..reply.len()].copy_from_slice(&reply);
+            Ok(reply.len())
+        }
+    }
+    let mut client = NestedServiceClient::new(LoopbackTransport {
+        dispatcher: NestedServiceDispatcher::new(DefaultService),
+        replies: Default::default(),
+    });
+    let ret = client.do_thing(&OuterInner::default()).unwrap();
+    assert_eq!(ret, EmptyReply::default());
+    client.do_thing_oneway(&OuterInner::default()).unwrap();
+    assert!(client.transport().replies.is_empty());
+    let ret = client.get_outer(&EmptyReply::default()).unwrap();
+    assert_eq!(ret, Outer::default());
+}
@@ -0,0 +1,911 @@
+pub use protogen_runtime::async_client::RpcClient;
+pub use protogen_runtime::rpc::*;
+pub use protogen_runtime::AsyncTransport;
+use protogen_runtime::wire::Varint as _;
+use serde::{Deserialize, Serialize};
+
+const _: () = protogen_runtime::VERSION_0_2;
+
+/// Largest frame clients and dispatchers send or accept.
+pub const MAX_FRAME_SIZE: usize = FRAME_BUFFER_SIZE;
+
+/// Largest encoded message, leaving room for the header and status of a reply.
+pub const MAX_MESSAGE_SIZE: usize = MAX_FRAME_SIZE - REPLY_OVERHEAD;
+
+/// Descriptors of everything in this file, to look types up by name.
+pub static FILE_DESCRIPTOR: protogen_runtime::descriptor::FileDescriptor = protogen_runtime::descriptor::FileDescriptor {
+    messages: protogen_runtime::descriptor::Cow::Borrowed(
+        &[PING_REQ_DESCRIPTOR, PING_REPLY_DESCRIPTOR],
+    ),
+    enums: protogen_runtime::descriptor::Cow::Borrowed(&[]),
+    services: protogen_runtime::descriptor::Cow::Borrowed(
+        &[PRIMARY_DESCRIPTOR, SECONDARY_DESCRIPTOR],
+    ),
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct PingReq {
+    pub seq: u32,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for PingReq {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.seq.to_varint() != 0 {
+            e.varint(1, self.seq)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        if field == 1 {
+            self.seq = value.varint(field)?;
+        } else {
+            self.unknown_fields.push(field, value);
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.seq.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.seq.to_varint());
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for PingReq {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.seq.to_varint() != 0 {
+            w.int("seq", self.seq)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "seq" => self.seq = p.int()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for PingReq {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for PingReq {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const PING_REQ_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("PingReq"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("seq"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Uint32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for PingReq {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "PingReq";
+}
+
+/// Borrowed view of an encoded [`PingReq`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PingReqRef<'a> {
+    buf: &'a [u8],
+    pub seq: u32,
+}
+
+impl<'a> PingReqRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            if field == 1 {
+                view.seq = value.varint(field)?;
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> PingReq {
+        PingReq {
+            seq: self.seq,
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(self.buf, &[1]),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for PingReqRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct PingReply {
+    pub seq: u32,
+    /// Fields of newer schema versions, written back when encoding.
+    #[serde(skip)]
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for PingReply {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.seq.to_varint() != 0 {
+            e.varint(1, self.seq)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        if field == 1 {
+            self.seq = value.varint(field)?;
+        } else {
+            self.unknown_fields.push(field, value);
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.seq.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.seq.to_varint());
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for PingReply {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.seq.to_varint() != 0 {
+            w.int("seq", self.seq)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "seq" => self.seq = p.int()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for PingReply {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for PingReply {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const PING_REPLY_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("PingReply"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("seq"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Uint32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for PingReply {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "PingReply";
+}
+
+/// Borrowed view of an encoded [`PingReply`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PingReplyRef<'a> {
+    buf: &'a [u8],
+    pub seq: u32,
+}
+
+impl<'a> PingReplyRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            if field == 1 {
+                view.seq = value.varint(field)?;
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> PingReply {
+        PingReply {
+            seq: self.seq,
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(self.buf, &[1]),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for PingReplyRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+/// A decoded request or reply of the Primary service.
+#[derive(Debug)]
+pub enum PrimaryMessage {
+    PingArg(PingReq),
+    PingRet(Result<PingReply, RpcError>),
+    StopArg(PingReq),
+    StopRet(Result<PingReply, RpcError>),
+}
+
+pub const PRIMARY_PING_ARG_ID: MessageId = 4044594546;
+pub const PRIMARY_PING_RET_ID: MessageId = 4044594547;
+pub const PRIMARY_STOP_ARG_ID: MessageId = 2481913258;
+pub const PRIMARY_STOP_RET_ID: MessageId = 2481913259;
+const PRIMARY_DESCRIPTOR: protogen_runtime::descriptor::ServiceDescriptor = protogen_runtime::descriptor::ServiceDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Primary"),
+    rpcs: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("Ping"),
+                arg_id: PRIMARY_PING_ARG_ID,
+                ret_id: PRIMARY_PING_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("PingReq"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("PingReply"),
+            },
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("Stop"),
+                arg_id: PRIMARY_STOP_ARG_ID,
+                ret_id: PRIMARY_STOP_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("PingReq"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("PingReply"),
+            },
+        ],
+    ),
+};
+
+impl PrimaryMessage {
+    pub fn try_deserialize_msg(buf: &[u8]) -> Option<Self> {
+        Self::try_deserialize_frame(buf).map(|(_, msg)| msg)
+    }
+    /// Decode a frame, keeping the header so replies can be matched up by
+    /// request id. Returns `None` for message ids of other services.
+    pub fn try_deserialize_frame(buf: &[u8]) -> Option<(RpcHeader, Self)> {
+        let header = RpcHeader::try_deserialize(buf)?;
+        let buf = &buf[RpcHeader::SIZE..];
+        let msg = match header.msg_id {
+            PRIMARY_PING_ARG_ID => {
+                Self::PingArg(PingReq::try_deserialize_body(buf).ok()?)
+            }
+            PRIMARY_PING_RET_ID => Self::PingRet(try_deserialize_reply(buf)),
+            PRIMARY_STOP_ARG_ID => {
+                Self::StopArg(PingReq::try_deserialize_body(buf).ok()?)
+            }
+            PRIMARY_STOP_RET_ID => Self::StopRet(try_deserialize_reply(buf)),
+            _ => return None,
+        };
+        Some((header, msg))
+    }
+}
+
+#[test]
+fn test_round_trip_primary_ping() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: PRIMARY_PING_ARG_ID,
+        request_id: 0,
+    };
+    let arg = PingReq::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match PrimaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        PrimaryMessage::PingArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: PRIMARY_PING_RET_ID,
+        request_id: 3,
+    };
+    let ret = PingReply::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match PrimaryMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, PrimaryMessage::PingRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<PingReply>(&header, Err(&error), buf).unwrap();
+    match PrimaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        PrimaryMessage::PingRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_round_trip_primary_stop() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: PRIMARY_STOP_ARG_ID,
+        request_id: 0,
+    };
+    let arg = PingReq::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match PrimaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        PrimaryMessage::StopArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: PRIMARY_STOP_RET_ID,
+        request_id: 3,
+    };
+    let ret = PingReply::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match PrimaryMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, PrimaryMessage::StopRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<PingReply>(&header, Err(&error), buf).unwrap();
+    match PrimaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        PrimaryMessage::StopRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+/// Server side of the Primary service.
+///
+/// Methods may be implemented with `async fn`, as long as the returned
+/// futures are `Send` so that requests can run on their own tasks.
+pub trait Primary: Send + Sync + 'static {
+    fn ping(
+        &self,
+        req: PingReq,
+    ) -> impl std::future::Future<Output = Result<PingReply, RpcError>> + Send;
+    fn stop(
+        &self,
+        req: PingReq,
+    ) -> impl std::future::Future<Output = Result<PingReply, RpcError>> + Send;
+}
+
+/// Decodes request frames and routes them to a [`Primary`] implementation.
+pub struct PrimaryDispatcher<S: Primary> {
+    service: std::sync::Arc<S>,
+}
+
+impl<S: Primary> Clone for PrimaryDispatcher<S> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+        }
+    }
+}
+
+impl<S: Primary> PrimaryDispatcher<S> {
+    pub fn new(service: S) -> Self {
+        Self::from_arc(std::sync::Arc::new(service))
+    }
+    pub fn from_arc(service: std::sync::Arc<S>) -> Self {
+        Self { service }
+    }
+    pub fn service(&self) -> &std::sync::Arc<S> {
+        &self.service
+    }
+    /// Decode a request frame, call the matching service method and serialize
+    /// its reply, or the error it failed with, into `reply`.
+    ///
+    /// Returns the length of the reply frame, zero for one-way requests. Only
+    /// frames that can't be answered at all are an error.
+    pub async fn dispatch(
+        &self,
+        frame: &[u8],
+        reply: &mut [u8],
+    ) -> Result<usize, RpcError> {
+        let header = RpcHeader::try_deserialize(frame)
+            .ok_or_else(|| RpcError::new(
+                RpcStatus::InvalidArgument,
+                "Truncated rpc header",
+            ))?;
+        let body = &frame[RpcHeader::SIZE..];
+        match header.msg_id {
+            PRIMARY_PING_ARG_ID => {
+                let ret = match PingReq::try_deserialize_body(body) {
+                    Ok(req) => self.service.ping(req).await,
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed PingReq: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        PRIMARY_PING_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        PRIMARY_PING_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            PRIMARY_STOP_ARG_ID => {
+                let ret = match PingReq::try_deserialize_body(body) {
+                    Ok(req) => self.service.stop(req).await,
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed PingReq: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        PRIMARY_STOP_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        PRIMARY_STOP_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            msg_id => {
+                serialize_error_reply(
+                    msg_id | 1,
+                    header.request_id,
+                    &RpcError::new(
+                        RpcStatus::Unimplemented,
+                        format!("Unknown message id {}", msg_id),
+                    ),
+                    reply,
+                )
+            }
+        }
+    }
+    /// Answer requests arriving on `transport` until receiving fails.
+    ///
+    /// Every request runs on its own task, at most `max_tasks` at a time. Once
+    /// that many are running no more frames are read until one finishes.
+    ///
+    /// Panics if `max_tasks` is 0, no request could ever be answered.
+    pub async fn serve<T: AsyncTransport>(
+        &self,
+        transport: std::sync::Arc<T>,
+        max_tasks: usize,
+    ) -> std::io::Result<()> {
+        assert!(max_tasks > 0, "serve needs max_tasks of at least 1");
+        let permits = std::sync::Arc::new(
+            protogen_runtime::tokio::sync::Semaphore::new(max_tasks),
+        );
+        loop {
+            let permit = permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Semaphore is never closed");
+            let mut frame = vec![0; MAX_FRAME_SIZE];
+            let (len, peer) = transport.recv_from(&mut frame).await?;
+            frame.truncate(len);
+            let dispatcher = self.clone();
+            let transport = transport.clone();
+            protogen_runtime::tokio::spawn(async move {
+                let mut reply = vec![0; MAX_FRAME_SIZE];
+                match dispatcher.dispatch(&frame, &mut reply).await {
+                    Ok(0) => {}
+                    Ok(len) => drop(transport.send_to(&reply[..len], &peer).await),
+                    Err(_) => {}
+                }
+                drop(permit);
+            });
+        }
+    }
+}
+
+/// Client side of the Primary service.
+pub struct PrimaryClient<T: AsyncTransport> {
+    client: RpcClient<T>,
+}
+
+impl<T: AsyncTransport> PrimaryClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            client: RpcClient::with_frame_size(transport, MAX_FRAME_SIZE),
+        }
+    }
+    /// How long to wait for each reply, [`DEFAULT_RPC_TIMEOUT`] by default.
+    pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+        self.client.set_timeout(timeout)
+    }
+    pub fn transport(&self) -> &T {
+        self.client.transport()
+    }
+    pub fn into_inner(self) -> T {
+        self.client.into_inner()
+    }
+    pub async fn ping(&self, req: &PingReq) -> Result<PingReply, RpcError> {
+        self.client.call(PRIMARY_PING_ARG_ID, PRIMARY_PING_RET_ID, req).await
+    }
+    pub async fn stop(&self, req: &PingReq) -> Result<PingReply, RpcError> {
+        self.client.call(PRIMARY_STOP_ARG_ID, PRIMARY_STOP_RET_ID, req).await
+    }
+}
+
+#[test]
+fn test_client_primary() {
+    use protogen_runtime::async_transport::AsyncChannelTransport;
+    struct DefaultService;
+    impl Primary for DefaultService {
+        async fn ping(&self, _req: PingReq) -> Result<PingReply, RpcError> {
+            Ok(PingReply::default())
+        }
+        async fn stop(&self, _req: PingReq) -> Result<PingReply, RpcError> {
+            Ok(PingReply::default())
+        }
+    }
+    let runtime = protogen_runtime::tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime
+        .block_on(async {
+            let (client_transport, server_transport) = AsyncChannelTransport::pair();
+            let dispatcher = PrimaryDispatcher::new(DefaultService);
+            let server = protogen_runtime::tokio::spawn(async move {
+                dispatcher.serve(std::sync::Arc::new(server_transport), 4).await
+            });
+            let client = PrimaryClient::new(client_transport);
+            let ret = client.ping(&PingReq::default()).await.unwrap();
+            assert_eq!(ret, PingReply::default());
+            let ret = client.stop(&PingReq::default()).await.unwrap();
+            assert_eq!(ret, PingReply::default());
+            server.abort();
+        });
+}
+
+/// A decoded request or reply of the Secondary service.
+#[derive(Debug)]
+pub enum SecondaryMessage {
+    PingArg(PingReq),
+    PingRet(Result<PingReply, RpcError>),
+}
+
+pub const SECONDARY_PING_ARG_ID: MessageId = 3844383650;
+pub const SECONDARY_PING_RET_ID: MessageId = 3844383651;
+const SECONDARY_DESCRIPTOR: protogen_runtime::descriptor::ServiceDescriptor = protogen_runtime::descriptor::ServiceDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Secondary"),
+    rpcs: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("Ping"),
+                arg_id: SECONDARY_PING_ARG_ID,
+                ret_id: SECONDARY_PING_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("PingReq"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("PingReply"),
+            },
+        ],
+    ),
+};
+
+impl SecondaryMessage {
+    pub fn try_deserialize_msg(buf: &[u8]) -> Option<Self> {
+        Self::try_deserialize_frame(buf).map(|(_, msg)| msg)
+    }
+    /// Decode a frame, keeping the header so replies can be matched up by
+    /// request id. Returns `None` for message ids of other services.
+    pub fn try_deserialize_frame(buf: &[u8]) -> Option<(RpcHeader, Self)> {
+        let header = RpcHeader::try_deserialize(buf)?;
+        let buf = &buf[RpcHeader::SIZE..];
+        let msg = match header.msg_id {
+            SECONDARY_PING_ARG_ID => {
+                Self::PingArg(PingReq::try_deserialize_body(buf).ok()?)
+            }
+            SECONDARY_PING_RET_ID => Self::PingRet(try_deserialize_reply(buf)),
+            _ => return None,
+        };
+        Some((header, msg))
+    }
+}
+
+#[test]
+fn test_round_trip_secondary_ping() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: SECONDARY_PING_ARG_ID,
+        request_id: 0,
+    };
+    let arg = PingReq::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match SecondaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        SecondaryMessage::PingArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: SECONDARY_PING_RET_ID,
+        request_id: 3,
+    };
+    let ret = PingReply::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match SecondaryMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, SecondaryMessage::PingRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<PingReply>(&header, Err(&error), buf).unwrap();
+    match SecondaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        SecondaryMessage::PingRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+/// Server side of the Secondary service.
+///
+/// Methods may be implemented with `async fn`, as long as the returned
+/// futures are `Send` so that requests can run on their own tasks.
+pub trait Secondary: Send + Sync + 'static {
+    fn ping(
+        &self,
+        req: PingReq,
+    ) -> impl std::future::Future<Output = Result<PingReply, RpcError>> + Send;
+}
+
+/// Decodes request frames and routes them to a [`Secondary`] implementation.
+pub struct SecondaryDispatcher<S: Secondary> {
+    service: std::sync::Arc<S>,
+}
+
+impl<S: Secondary> Clone for SecondaryDispatcher<S> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+        }
+    }
+}
+
+impl<S: Secondary> SecondaryDispatcher<S> {
+    pub fn new(service: S) -> Self {
+        Self::from_arc(std::sync::Arc::new(service))
+    }
+    pub fn from_arc(service: std::sync::Arc<S>) -> Self {
+        Self { service }
+    }
+    pub fn service(&self) -> &std::sync::Arc<S> {
+        &self.service
+    }
+    /// Decode a request frame, call the matching service method and serialize
+    /// its reply, or the error it failed with, into `reply`.
+    ///
+    /// Returns the length of the reply frame, zero for one-way requests. Only
+    /// frames that can't be answered at all are an error.
+    pub async fn dispatch(
+        &self,
+        frame: &[u8],
+        reply: &mut [u8],
+    ) -> Result<usize, RpcError> {
+        let header = RpcHeader::try_deserialize(frame)
+            .ok_or_else(|| RpcError::new(
+                RpcStatus::InvalidArgument,
+                "Truncated rpc header",
+            ))?;
+        let body = &frame[RpcHeader::SIZE..];
+        match header.msg_id {
+            SECONDARY_PING_ARG_ID => {
+                let ret = match PingReq::try_deserialize_body(body) {
+                    Ok(req) => self.service.ping(req).await,
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed PingReq: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        SECONDARY_PING_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        SECONDARY_PING_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            msg_id => {
+                serialize_error_reply(
+                    msg_id | 1,
+                    header.request_id,
+                    &RpcError::new(
+                        RpcStatus::Unimplemented,
+                        format!("Unknown message id {}", msg_id),
+                    ),
+                    reply,
+                )
+            }
+        }
+    }
+    /// Answer requests arriving on `transport` until receiving fails.
+    ///
+    /// Every request runs on its own task, at most `max_tasks` at a time. Once
+    /// that many are running no more frames are read until one finishes.
+    ///
+    /// Panics if `max_tasks` is 0, no request could ever be answered.
+    pub async fn serve<T: AsyncTransport>(
+        &self,
+        transport: std::sync::Arc<T>,
+        max_tasks: usize,
+    ) -> std::io::Result<()> {
+        assert!(max_tasks > 0, "serve needs max_tasks of at least 1");
+        let permits = std::sync::Arc::new(
+            protogen_runtime::tokio::sync::Semaphore::new(max_tasks),
+        );
+        loop {
+            let permit = permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Semaphore is never closed");
+            let mut frame = vec![0; MAX_FRAME_SIZE];
+            let (len, peer) = transport.recv_from(&mut frame).await?;
+            frame.truncate(len);
+            let dispatcher = self.clone();
+            let transport = transport.clone();
+            protogen_runtime::tokio::spawn(async move {
+                let mut reply = vec![0; MAX_FRAME_SIZE];
+                match dispatcher.dispatch(&frame, &mut reply).await {
+                    Ok(0) => {}
+                    Ok(len) => drop(transport.send_to(&reply[..len], &peer).await),
+                    Err(_) => {}
+                }
+                drop(permit);
+            });
+        }
+    }
+}
+
+/// Client side of the Secondary service.
+pub struct SecondaryClient<T: AsyncTransport> {
+    client: RpcClient<T>,
+}
+
+impl<T: AsyncTransport> SecondaryClient<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            client: RpcClient::with_frame_size(transport, MAX_FRAME_SIZE),
+        }
+    }
+    /// How long to wait for each reply, [`DEFAULT_RPC_TIMEOUT`] by default.
+    pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+        self.client.set_timeout(timeout)
+    }
+    pub fn transport(&self) -> &T {
+        self.client.transport()
+    }
+    pub fn into_inner(self) -> T {
+        self.client.into_inner()
+    }
+    pub async fn ping(&self, req: &PingReq) -> Result<PingReply, RpcError> {
+        self.client.call(SECONDARY_PING_ARG_ID, SECONDARY_PING_RET_ID, req).await
+    }
+}
+
+#[test]
+fn test_client_secondary() {
+    use protogen_runtime::async_transport::AsyncChannelTransport;
+    struct DefaultService;
+    impl Secondary for DefaultService {
+        async fn ping(&self, _req: PingReq) -> Result<PingReply, RpcError> {
+            Ok(PingReply::default())
+        }
+    }
+    let runtime = protogen_runtime::tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime
+        .block_on(async {
+            let (client_transport, server_transport) = AsyncChannelTransport::pair();
+            let dispatcher = SecondaryDispatcher::new(DefaultService);
+            let server = protogen_runtime::tokio::spawn(async move {
+                dispatcher.serve(std::sync::Arc::new(server_transport), 4).await
+            });
+            let client = SecondaryClient::new(client_transport);
+            let ret = client.ping(&PingReq::default()).await.unwrap();
+            assert_eq!(ret, PingReply::default());
+            server.abort();
+        });
+}
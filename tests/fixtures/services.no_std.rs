@@ -0,0 +1,769 @@
+#[allow(unused_imports)]
+use alloc::{format, string::String, vec, vec::Vec};
+pub use protogen_runtime::rpc::*;
+use protogen_runtime::wire::Varint as _;
+
+const _: () = protogen_runtime::VERSION_0_2;
+
+/// Largest frame clients and dispatchers send or accept.
+pub const MAX_FRAME_SIZE: usize = FRAME_BUFFER_SIZE;
+
+/// Largest encoded message, leaving room for the header and status of a reply.
+pub const MAX_MESSAGE_SIZE: usize = MAX_FRAME_SIZE - REPLY_OVERHEAD;
+
+/// Descriptors of everything in this file, to look types up by name.
+pub static FILE_DESCRIPTOR: protogen_runtime::descriptor::FileDescriptor = protogen_runtime::descriptor::FileDescriptor {
+    messages: protogen_runtime::descriptor::Cow::Borrowed(
+        &[PING_REQ_DESCRIPTOR, PING_REPLY_DESCRIPTOR],
+    ),
+    enums: protogen_runtime::descriptor::Cow::Borrowed(&[]),
+    services: protogen_runtime::descriptor::Cow::Borrowed(
+        &[PRIMARY_DESCRIPTOR, SECONDARY_DESCRIPTOR],
+    ),
+};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PingReq {
+    pub seq: u32,
+    /// Fields of newer schema versions, written back when encoding.
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for PingReq {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.seq.to_varint() != 0 {
+            e.varint(1, self.seq)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        if field == 1 {
+            self.seq = value.varint(field)?;
+        } else {
+            self.unknown_fields.push(field, value);
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.seq.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.seq.to_varint());
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for PingReq {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.seq.to_varint() != 0 {
+            w.int("seq", self.seq)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "seq" => self.seq = p.int()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for PingReq {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for PingReq {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const PING_REQ_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("PingReq"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("seq"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Uint32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for PingReq {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "PingReq";
+}
+
+/// Borrowed view of an encoded [`PingReq`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PingReqRef<'a> {
+    buf: &'a [u8],
+    pub seq: u32,
+}
+
+impl<'a> PingReqRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            if field == 1 {
+                view.seq = value.varint(field)?;
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> PingReq {
+        PingReq {
+            seq: self.seq,
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(self.buf, &[1]),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for PingReqRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PingReply {
+    pub seq: u32,
+    /// Fields of newer schema versions, written back when encoding.
+    pub unknown_fields: protogen_runtime::wire::UnknownFields,
+}
+
+impl ProtoMessage for PingReply {
+    const MAX_SIZE: usize = MAX_MESSAGE_SIZE;
+    fn encode_fields(
+        &self,
+        e: &mut protogen_runtime::wire::Encoder,
+    ) -> Result<(), protogen_runtime::wire::EncodeError> {
+        if self.seq.to_varint() != 0 {
+            e.varint(1, self.seq)?;
+        }
+        e.unknown(&self.unknown_fields)?;
+        Ok(())
+    }
+    fn merge_field(
+        &mut self,
+        field: u32,
+        value: protogen_runtime::wire::Value,
+    ) -> Result<(), protogen_runtime::wire::DecodeError> {
+        if field == 1 {
+            self.seq = value.varint(field)?;
+        } else {
+            self.unknown_fields.push(field, value);
+        }
+        Ok(())
+    }
+    fn serialized_size(&self) -> usize {
+        let mut size = 0;
+        if self.seq.to_varint() != 0 {
+            size += protogen_runtime::wire::varint_field_size(1, self.seq.to_varint());
+        }
+        size += self.unknown_fields.len();
+        size
+    }
+}
+
+impl protogen_runtime::text::TextMessage for PingReply {
+    fn write_fields(
+        &self,
+        w: &mut protogen_runtime::text::TextWriter,
+    ) -> core::fmt::Result {
+        if self.seq.to_varint() != 0 {
+            w.int("seq", self.seq)?;
+        }
+        Ok(())
+    }
+    fn merge_text_field(
+        &mut self,
+        name: &str,
+        p: &mut protogen_runtime::text::TextParser,
+    ) -> Result<(), protogen_runtime::text::TextError> {
+        match name {
+            "seq" => self.seq = p.int()?,
+            _ => return Err(p.unknown_field(name)),
+        }
+        Ok(())
+    }
+}
+
+/// The text format, `{:#}` puts every field on its own line.
+impl core::fmt::Display for PingReply {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        protogen_runtime::text::write(self, f)
+    }
+}
+
+impl core::str::FromStr for PingReply {
+    type Err = protogen_runtime::text::TextError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protogen_runtime::text::parse(s)
+    }
+}
+
+const PING_REPLY_DESCRIPTOR: protogen_runtime::descriptor::MessageDescriptor = protogen_runtime::descriptor::MessageDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("PingReply"),
+    fields: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::FieldDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("seq"),
+                number: 1,
+                ty: protogen_runtime::descriptor::FieldType::Uint32,
+                label: protogen_runtime::descriptor::Label::Singular,
+            },
+        ],
+    ),
+};
+
+impl protogen_runtime::descriptor::Describe for PingReply {
+    const FILE: &'static protogen_runtime::descriptor::FileDescriptor = &FILE_DESCRIPTOR;
+    const NAME: &'static str = "PingReply";
+}
+
+/// Borrowed view of an encoded [`PingReply`], repeated fields are decoded as they're iterated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PingReplyRef<'a> {
+    buf: &'a [u8],
+    pub seq: u32,
+}
+
+impl<'a> PingReplyRef<'a> {
+    /// Check all of `buf` and decode its singular fields.
+    pub fn new(buf: &'a [u8]) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        let mut view = Self { buf, ..Default::default() };
+        for field in protogen_runtime::wire::Decoder::new(buf) {
+            let (field, value) = field?;
+            if field == 1 {
+                view.seq = value.varint(field)?;
+            }
+        }
+        Ok(view)
+    }
+    /// The encoded message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+    /// Copy the message out of the buffer.
+    pub fn to_owned(&self) -> PingReply {
+        PingReply {
+            seq: self.seq,
+            unknown_fields: protogen_runtime::wire::UnknownFields::filter(self.buf, &[1]),
+        }
+    }
+}
+
+impl<'a> protogen_runtime::view::FieldRef<'a> for PingReplyRef<'a> {
+    fn decode(
+        field: u32,
+        value: protogen_runtime::wire::Value<'a>,
+    ) -> Result<Self, protogen_runtime::wire::DecodeError> {
+        Self::new(value.bytes(field)?)
+    }
+}
+
+/// A decoded request or reply of the Primary service.
+#[derive(Debug)]
+pub enum PrimaryMessage {
+    PingArg(PingReq),
+    PingRet(Result<PingReply, RpcError>),
+    StopArg(PingReq),
+    StopRet(Result<PingReply, RpcError>),
+}
+
+pub const PRIMARY_PING_ARG_ID: MessageId = 4044594546;
+pub const PRIMARY_PING_RET_ID: MessageId = 4044594547;
+pub const PRIMARY_STOP_ARG_ID: MessageId = 2481913258;
+pub const PRIMARY_STOP_RET_ID: MessageId = 2481913259;
+const PRIMARY_DESCRIPTOR: protogen_runtime::descriptor::ServiceDescriptor = protogen_runtime::descriptor::ServiceDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Primary"),
+    rpcs: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("Ping"),
+                arg_id: PRIMARY_PING_ARG_ID,
+                ret_id: PRIMARY_PING_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("PingReq"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("PingReply"),
+            },
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("Stop"),
+                arg_id: PRIMARY_STOP_ARG_ID,
+                ret_id: PRIMARY_STOP_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("PingReq"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("PingReply"),
+            },
+        ],
+    ),
+};
+
+impl PrimaryMessage {
+    pub fn try_deserialize_msg(buf: &[u8]) -> Option<Self> {
+        Self::try_deserialize_frame(buf).map(|(_, msg)| msg)
+    }
+    /// Decode a frame, keeping the header so replies can be matched up by
+    /// request id. Returns `None` for message ids of other services.
+    pub fn try_deserialize_frame(buf: &[u8]) -> Option<(RpcHeader, Self)> {
+        let header = RpcHeader::try_deserialize(buf)?;
+        let buf = &buf[RpcHeader::SIZE..];
+        let msg = match header.msg_id {
+            PRIMARY_PING_ARG_ID => {
+                Self::PingArg(PingReq::try_deserialize_body(buf).ok()?)
+            }
+            PRIMARY_PING_RET_ID => Self::PingRet(try_deserialize_reply(buf)),
+            PRIMARY_STOP_ARG_ID => {
+                Self::StopArg(PingReq::try_deserialize_body(buf).ok()?)
+            }
+            PRIMARY_STOP_RET_ID => Self::StopRet(try_deserialize_reply(buf)),
+            _ => return None,
+        };
+        Some((header, msg))
+    }
+}
+
+#[test]
+fn test_round_trip_primary_ping() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: PRIMARY_PING_ARG_ID,
+        request_id: 0,
+    };
+    let arg = PingReq::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match PrimaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        PrimaryMessage::PingArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: PRIMARY_PING_RET_ID,
+        request_id: 3,
+    };
+    let ret = PingReply::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match PrimaryMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, PrimaryMessage::PingRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<PingReply>(&header, Err(&error), buf).unwrap();
+    match PrimaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        PrimaryMessage::PingRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_round_trip_primary_stop() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: PRIMARY_STOP_ARG_ID,
+        request_id: 0,
+    };
+    let arg = PingReq::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match PrimaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        PrimaryMessage::StopArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: PRIMARY_STOP_RET_ID,
+        request_id: 3,
+    };
+    let ret = PingReply::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match PrimaryMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, PrimaryMessage::StopRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<PingReply>(&header, Err(&error), buf).unwrap();
+    match PrimaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        PrimaryMessage::StopRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+/// Server side of the Primary service.
+pub trait Primary {
+    fn ping(&mut self, req: PingReq) -> Result<PingReply, RpcError>;
+    fn stop(&mut self, req: PingReq) -> Result<PingReply, RpcError>;
+}
+
+/// Decodes request frames and routes them to a [`Primary`] implementation.
+pub struct PrimaryDispatcher<S: Primary> {
+    service: S,
+}
+
+impl<S: Primary> PrimaryDispatcher<S> {
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+    pub fn service_mut(&mut self) -> &mut S {
+        &mut self.service
+    }
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+    /// Decode a request frame, call the matching service method and serialize
+    /// its reply, or the error it failed with, into `reply`.
+    ///
+    /// Returns the length of the reply frame, zero for one-way requests. Only
+    /// frames that can't be answered at all are an error.
+    pub fn dispatch(
+        &mut self,
+        frame: &[u8],
+        reply: &mut [u8],
+    ) -> Result<usize, RpcError> {
+        let header = RpcHeader::try_deserialize(frame)
+            .ok_or_else(|| RpcError::new(
+                RpcStatus::InvalidArgument,
+                "Truncated rpc header",
+            ))?;
+        let body = &frame[RpcHeader::SIZE..];
+        match header.msg_id {
+            PRIMARY_PING_ARG_ID => {
+                let ret = match PingReq::try_deserialize_body(body) {
+                    Ok(req) => self.service.ping(req),
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed PingReq: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        PRIMARY_PING_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        PRIMARY_PING_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            PRIMARY_STOP_ARG_ID => {
+                let ret = match PingReq::try_deserialize_body(body) {
+                    Ok(req) => self.service.stop(req),
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed PingReq: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        PRIMARY_STOP_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        PRIMARY_STOP_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            msg_id => {
+                serialize_error_reply(
+                    msg_id | 1,
+                    header.request_id,
+                    &RpcError::new(
+                        RpcStatus::Unimplemented,
+                        format!("Unknown message id {}", msg_id),
+                    ),
+                    reply,
+                )
+            }
+        }
+    }
+}
+
+#[test]
+fn test_dispatch_primary() {
+    struct DefaultService;
+    impl Primary for DefaultService {
+        fn ping(&mut self, _req: PingReq) -> Result<PingReply, RpcError> {
+            Ok(PingReply::default())
+        }
+        fn stop(&mut self, _req: PingReq) -> Result<PingReply, RpcError> {
+            Ok(PingReply::default())
+        }
+    }
+    let mut dispatcher = PrimaryDispatcher::new(DefaultService);
+    let mut frame = vec![0u8; MAX_FRAME_SIZE];
+    let mut reply = vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: PRIMARY_PING_ARG_ID,
+        request_id: 7,
+    };
+    let len = serialize_frame_into(&header, &PingReq::default(), &mut frame).unwrap();
+    let reply_len = dispatcher.dispatch(&frame[..len], &mut reply).unwrap();
+    let reply_header = RpcHeader::try_deserialize(&reply[..reply_len]).unwrap();
+    assert_eq!(reply_header.msg_id, PRIMARY_PING_RET_ID);
+    assert_eq!(reply_header.request_id, 7);
+    let header = RpcHeader {
+        msg_id: PRIMARY_STOP_ARG_ID,
+        request_id: 7,
+    };
+    let len = serialize_frame_into(&header, &PingReq::default(), &mut frame).unwrap();
+    let reply_len = dispatcher.dispatch(&frame[..len], &mut reply).unwrap();
+    let reply_header = RpcHeader::try_deserialize(&reply[..reply_len]).unwrap();
+    assert_eq!(reply_header.msg_id, PRIMARY_STOP_RET_ID);
+    assert_eq!(reply_header.request_id, 7);
+    let header = RpcHeader {
+        msg_id: u32::MAX - 1,
+        request_id: 8,
+    };
+    header.serialize_into(&mut frame).unwrap();
+    let reply_len = dispatcher.dispatch(&frame[..RpcHeader::SIZE], &mut reply).unwrap();
+    let reply_header = RpcHeader::try_deserialize(&reply).unwrap();
+    assert_eq!(reply_header.msg_id, u32::MAX);
+    let error = RpcError::try_deserialize(&reply[RpcHeader::SIZE..reply_len]).unwrap();
+    assert_eq!(error.status, RpcStatus::Unimplemented);
+}
+
+/// A decoded request or reply of the Secondary service.
+#[derive(Debug)]
+pub enum SecondaryMessage {
+    PingArg(PingReq),
+    PingRet(Result<PingReply, RpcError>),
+}
+
+pub const SECONDARY_PING_ARG_ID: MessageId = 3844383650;
+pub const SECONDARY_PING_RET_ID: MessageId = 3844383651;
+const SECONDARY_DESCRIPTOR: protogen_runtime::descriptor::ServiceDescriptor = protogen_runtime::descriptor::ServiceDescriptor {
+    name: protogen_runtime::descriptor::Cow::Borrowed("Secondary"),
+    rpcs: protogen_runtime::descriptor::Cow::Borrowed(
+        &[
+            protogen_runtime::descriptor::RpcDescriptor {
+                name: protogen_runtime::descriptor::Cow::Borrowed("Ping"),
+                arg_id: SECONDARY_PING_ARG_ID,
+                ret_id: SECONDARY_PING_RET_ID,
+                arg: protogen_runtime::descriptor::Cow::Borrowed("PingReq"),
+                ret: protogen_runtime::descriptor::Cow::Borrowed("PingReply"),
+            },
+        ],
+    ),
+};
+
+impl SecondaryMessage {
+    pub fn try_deserialize_msg(buf: &[u8]) -> Option<Self> {
+        Self::try_deserialize_frame(buf).map(|(_, msg)| msg)
+    }
+    /// Decode a frame, keeping the header so replies can be matched up by
+    /// request id. Returns `None` for message ids of other services.
+    pub fn try_deserialize_frame(buf: &[u8]) -> Option<(RpcHeader, Self)> {
+        let header = RpcHeader::try_deserialize(buf)?;
+        let buf = &buf[RpcHeader::SIZE..];
+        let msg = match header.msg_id {
+            SECONDARY_PING_ARG_ID => {
+                Self::PingArg(PingReq::try_deserialize_body(buf).ok()?)
+            }
+            SECONDARY_PING_RET_ID => Self::PingRet(try_deserialize_reply(buf)),
+            _ => return None,
+        };
+        Some((header, msg))
+    }
+}
+
+#[test]
+fn test_round_trip_secondary_ping() {
+    let buf = &mut vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: SECONDARY_PING_ARG_ID,
+        request_id: 0,
+    };
+    let arg = PingReq::default();
+    let len = serialize_frame_into(&header, &arg, buf).unwrap();
+    match SecondaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        SecondaryMessage::PingArg(input) => assert_eq!(arg, input),
+        _ => panic!(),
+    }
+    let header = RpcHeader {
+        msg_id: SECONDARY_PING_RET_ID,
+        request_id: 3,
+    };
+    let ret = PingReply::default();
+    let len = serialize_reply_into(&header, Ok(&ret), buf).unwrap();
+    match SecondaryMessage::try_deserialize_frame(&buf[..len]).unwrap() {
+        (h, SecondaryMessage::PingRet(Ok(output))) => {
+            assert_eq!(h, header);
+            assert_eq!(ret, output);
+        }
+        _ => panic!(),
+    }
+    let error = RpcError::new(RpcStatus::NotFound, "missing")
+        .with_details(vec![1, 2, 3]);
+    let len = serialize_reply_into::<PingReply>(&header, Err(&error), buf).unwrap();
+    match SecondaryMessage::try_deserialize_msg(&buf[..len]).unwrap() {
+        SecondaryMessage::PingRet(Err(e)) => assert_eq!(error, e),
+        _ => panic!(),
+    }
+}
+
+/// Server side of the Secondary service.
+pub trait Secondary {
+    fn ping(&mut self, req: PingReq) -> Result<PingReply, RpcError>;
+}
+
+/// Decodes request frames and routes them to a [`Secondary`] implementation.
+pub struct SecondaryDispatcher<S: Secondary> {
+    service: S,
+}
+
+impl<S: Secondary> SecondaryDispatcher<S> {
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+    pub fn service_mut(&mut self) -> &mut S {
+        &mut self.service
+    }
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+    /// Decode a request frame, call the matching service method and serialize
+    /// its reply, or the error it failed with, into `reply`.
+    ///
+    /// Returns the length of the reply frame, zero for one-way requests. Only
+    /// frames that can't be answered at all are an error.
+    pub fn dispatch(
+        &mut self,
+        frame: &[u8],
+        reply: &mut [u8],
+    ) -> Result<usize, RpcError> {
+        let header = RpcHeader::try_deserialize(frame)
+            .ok_or_else(|| RpcError::new(
+                RpcStatus::InvalidArgument,
+                "Truncated rpc header",
+            ))?;
+        let body = &frame[RpcHeader::SIZE..];
+        match header.msg_id {
+            SECONDARY_PING_ARG_ID => {
+                let ret = match PingReq::try_deserialize_body(body) {
+                    Ok(req) => self.service.ping(req),
+                    Err(e) => {
+                        Err(
+                            RpcError::new(
+                                RpcStatus::InvalidArgument,
+                                format!("Malformed PingReq: {}", e),
+                            ),
+                        )
+                    }
+                };
+                serialize_reply(
+                        SECONDARY_PING_RET_ID,
+                        header.request_id,
+                        ret.as_ref(),
+                        reply,
+                    )
+                    .or_else(|e| serialize_error_reply(
+                        SECONDARY_PING_RET_ID,
+                        header.request_id,
+                        &e,
+                        reply,
+                    ))
+            }
+            msg_id => {
+                serialize_error_reply(
+                    msg_id | 1,
+                    header.request_id,
+                    &RpcError::new(
+                        RpcStatus::Unimplemented,
+                        format!("Unknown message id {}", msg_id),
+                    ),
+                    reply,
+                )
+            }
+        }
+    }
+}
+
+#[test]
+fn test_dispatch_secondary() {
+    struct DefaultService;
+    impl Secondary for DefaultService {
+        fn ping(&mut self, _req: PingReq) -> Result<PingReply, RpcError> {
+            Ok(PingReply::default())
+        }
+    }
+    let mut dispatcher = SecondaryDispatcher::new(DefaultService);
+    let mut frame = vec![0u8; MAX_FRAME_SIZE];
+    let mut reply = vec![0u8; MAX_FRAME_SIZE];
+    let header = RpcHeader {
+        msg_id: SECONDARY_PING_ARG_ID,
+        request_id: 7,
+    };
+    let len = serialize_frame_into(&header, &PingReq::default(), &mut frame).unwrap();
+    let reply_len = dispatcher.dispatch(&frame[..len], &mut reply).unwrap();
+    let reply_header = RpcHeader::try_deserialize(&reply[..reply_len]).unwrap();
+    assert_eq!(reply_header.msg_id, SECONDARY_PING_RET_ID);
+    assert_eq!(reply_header.request_id, 7);
+    let header = RpcHeader {
+        msg_id: u32::MAX - 1,
+        request_id: 8,
+    };
+    header.serialize_into(&mut frame).unwrap();
+    let reply_len = dispatcher.dispatch(&frame[..RpcHeader::SIZE], &mut reply).unwrap();
+    let reply_header = RpcHeader::try_deserialize(&reply).unwrap();
+    assert_eq!(reply_header.msg_id, u32::MAX);
+    let error = RpcError::try_deserialize(&reply[RpcHeader::SIZE..reply_len]).unwrap();
+    assert_eq!(error.status, RpcStatus::Unimplemented);
+}